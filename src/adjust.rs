@@ -0,0 +1,611 @@
+// adjust.rs    Color adjustments
+//
+// Copyright (c) 2023  Douglas P Lau
+//
+//! Color adjustments for a [Raster].
+//!
+//! [Raster]: ../struct.Raster.html
+#![allow(clippy::excessive_precision)]
+
+use crate::chan::{Ch32, Channel, Gamma, Linear, Srgb, Straight};
+use crate::el::{circ_rotate, Pix4, Pixel};
+use crate::hsl::Hsl;
+use crate::oklab::{lab_from_rgb, rgb_from_lab};
+use crate::raster::{Raster, Region};
+use crate::rgb::Rgb;
+
+/// Approximate maximum [Oklab] chroma within the sRGB gamut
+///
+/// [Oklab]: ../oklab/struct.Oklab.html
+const MAX_CHROMA: f32 = 0.33;
+
+/// 3x3 color matrix
+type Matrix = [[f32; 3]; 3];
+
+/// Linear sRGB to CIE XYZ (D65)
+#[rustfmt::skip]
+const XYZ_FROM_RGB: Matrix = [
+    [0.4124564, 0.3575761, 0.1804375],
+    [0.2126729, 0.7151522, 0.0721750],
+    [0.0193339, 0.1191920, 0.9503041],
+];
+
+/// CIE XYZ (D65) to linear sRGB
+#[rustfmt::skip]
+const RGB_FROM_XYZ: Matrix = [
+    [ 3.2404542, -1.5371385, -0.4985314],
+    [-0.9692660,  1.8760108,  0.0415560],
+    [ 0.0556434, -0.2040259,  1.0572252],
+];
+
+/// Identity matrix
+#[rustfmt::skip]
+const IDENTITY: Matrix = [
+    [1.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0],
+    [0.0, 0.0, 1.0],
+];
+
+/// Color space for saturation and hue adjustments
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Space {
+    /// [Oklab] *LCh*, which keeps perceived lightness constant.
+    ///
+    /// [Oklab]: ../oklab/struct.Oklab.html
+    #[default]
+    Oklab,
+    /// [Hsl], with [sRGB] gamma.
+    ///
+    /// [Hsl]: ../hsl/struct.Hsl.html
+    /// [sRGB]: ../chan/struct.Srgb.html
+    Hsl,
+}
+
+/// Color adjustment, which can be applied with [Raster::adjust].
+///
+/// Amounts are relative, with `0.0` leaving pixels unchanged.
+///
+/// ### Warm up an image
+/// ```
+/// use pix::adjust::{Adjustment, Space};
+/// use pix::rgb::SRgba8;
+/// use pix::Raster;
+///
+/// let mut r = Raster::with_color(4, 4, SRgba8::new(0x80, 0x60, 0x40, 0xFF));
+/// r.adjust((), Adjustment::HueRotate(-10.0, Space::Oklab));
+/// r.adjust((), Adjustment::Vibrance(0.5));
+/// assert_eq!(r.pixel(0, 0), SRgba8::new(0x8D, 0x58, 0x35, 0xFF));
+/// ```
+///
+/// [Raster::adjust]: ../struct.Raster.html#method.adjust
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Adjustment {
+    /// Brightness, added to [sRGB] *red*, *green* and *blue* (`-1.0` to
+    /// `1.0`).
+    ///
+    /// [sRGB]: ../chan/struct.Srgb.html
+    Brightness(f32),
+    /// Contrast around [sRGB] middle gray (`-1.0` to `1.0`).  With `-1.0`,
+    /// all pixels become middle gray.
+    ///
+    /// [sRGB]: ../chan/struct.Srgb.html
+    Contrast(f32),
+    /// Exposure, in stops, scaling [linear] light.
+    ///
+    /// [linear]: ../chan/struct.Linear.html
+    Exposure(f32),
+    /// Saturation (`-1.0` to `1.0`).  With `-1.0`, all pixels become gray.
+    Saturation(f32, Space),
+    /// Vibrance (`-1.0` to `1.0`), which is like saturation, but has less
+    /// effect on colors which are already saturated.  [Oklab] chroma is
+    /// used.
+    ///
+    /// [Oklab]: ../oklab/struct.Oklab.html
+    Vibrance(f32),
+    /// Hue rotation, in degrees.
+    HueRotate(f32, Space),
+}
+
+/// Chromatic adaptation transform, used for [WhiteBalance].
+///
+/// [WhiteBalance]: struct.WhiteBalance.html
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Adaptation {
+    /// Bradford transform
+    #[default]
+    Bradford,
+    /// CIECAM02 transform
+    Cat02,
+}
+
+/// White balance correction, as a chromatic adaptation to the D65 white
+/// point of sRGB.
+///
+/// This is done in linear light, using the [Adaptation] cone response, rather
+/// than applying a gain to each channel.
+///
+/// ### Correct tungsten lighting
+/// ```
+/// use pix::adjust::{Adaptation, WhiteBalance};
+/// use pix::rgb::{Rgb, SRgb8};
+/// use pix::Raster;
+///
+/// let mut r = Raster::with_color(4, 4, SRgb8::new(0xFF, 0xBE, 0x7A));
+/// let wb = WhiteBalance::with_temperature(3200.0, 0.0, Adaptation::Bradford);
+/// r.white_balance((), &wb);
+/// let (red, blue) = (Rgb::red(r.pixel(0, 0)), Rgb::blue(r.pixel(0, 0)));
+/// assert!(u8::from(red).abs_diff(u8::from(blue)) < 8);
+/// ```
+///
+/// [Adaptation]: enum.Adaptation.html
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WhiteBalance {
+    matrix: Matrix,
+}
+
+/// Rgba pixel with `Ch32` channels and straight alpha
+type Rgba<G> = Pix4<Ch32, Rgb, Straight, G>;
+
+/// Hsla pixel with `Ch32` channels, straight alpha and sRGB gamma
+type Hsla = Pix4<Ch32, Hsl, Straight, Srgb>;
+
+/// Map *red*, *green* and *blue* values of a pixel
+fn map_rgb<G, F>(p: &mut Rgba<G>, f: F)
+where
+    G: Gamma,
+    F: Fn([f32; 3]) -> [f32; 3],
+{
+    let ch = p.channels_mut();
+    let rgb = f([ch[0].to_f32(), ch[1].to_f32(), ch[2].to_f32()]);
+    for (c, v) in ch.iter_mut().zip(rgb) {
+        *c = Ch32::new(v);
+    }
+}
+
+impl Adjustment {
+    /// Apply adjustment to one pixel
+    fn apply<P>(self, p: P) -> P
+    where
+        P: Pixel,
+        P::Chan: From<Ch32>,
+        Ch32: From<P::Chan>,
+    {
+        match self {
+            Adjustment::Brightness(amount) => {
+                let mut rgba: Rgba<Srgb> = p.convert();
+                map_rgb(&mut rgba, |rgb| rgb.map(|v| v + amount));
+                rgba.convert()
+            }
+            Adjustment::Contrast(amount) => {
+                let scale = 1.0 + amount;
+                let mut rgba: Rgba<Srgb> = p.convert();
+                map_rgb(&mut rgba, |rgb| rgb.map(|v| (v - 0.5) * scale + 0.5));
+                rgba.convert()
+            }
+            Adjustment::Exposure(stops) => {
+                let scale = stops.exp2();
+                let mut rgba: Rgba<Linear> = p.convert();
+                map_rgb(&mut rgba, |rgb| rgb.map(|v| v * scale));
+                rgba.convert()
+            }
+            Adjustment::Saturation(amount, Space::Oklab) => {
+                Self::adjust_lab(p, |a, b| {
+                    (a * (1.0 + amount), b * (1.0 + amount))
+                })
+            }
+            Adjustment::Saturation(amount, Space::Hsl) => {
+                let mut hsla: Hsla = p.convert();
+                let s = Hsl::saturation_mut(&mut hsla);
+                *s = Ch32::new(s.to_f32() * (1.0 + amount));
+                hsla.convert()
+            }
+            Adjustment::Vibrance(amount) => Self::adjust_lab(p, |a, b| {
+                let chroma = a.hypot(b);
+                let weight = 1.0 - (chroma / MAX_CHROMA).min(1.0);
+                let scale = 1.0 + amount * weight;
+                (a * scale, b * scale)
+            }),
+            Adjustment::HueRotate(degrees, Space::Oklab) => {
+                let (sin, cos) = degrees.to_radians().sin_cos();
+                Self::adjust_lab(p, |a, b| {
+                    (a * cos - b * sin, a * sin + b * cos)
+                })
+            }
+            Adjustment::HueRotate(degrees, Space::Hsl) => {
+                let mut hsla: Hsla = p.convert();
+                let h = Hsl::hue_mut(&mut hsla);
+                *h = circ_rotate(*h, degrees / 360.0);
+                hsla.convert()
+            }
+        }
+    }
+
+    /// Adjust the *a* and *b* components of a pixel in Oklab
+    fn adjust_lab<P, F>(p: P, adjust: F) -> P
+    where
+        P: Pixel,
+        P::Chan: From<Ch32>,
+        Ch32: From<P::Chan>,
+        F: Fn(f32, f32) -> (f32, f32),
+    {
+        let mut rgba: Rgba<Linear> = p.convert();
+        map_rgb(&mut rgba, |rgb| {
+            let [l, a, b] = lab_from_rgb(rgb);
+            let (a, b) = adjust(a, b);
+            rgb_from_lab([l, a, b])
+        });
+        rgba.convert()
+    }
+}
+
+impl<P: Pixel> Raster<P>
+where
+    P::Chan: From<Ch32>,
+    Ch32: From<P::Chan>,
+{
+    /// Apply a color adjustment to a region.
+    ///
+    /// * `reg` Region within `self`.  It can be a `Region` struct, tuple of
+    ///   (*x*, *y*, *width*, *height*) or the unit type `()`.  Using
+    ///   `()` has the same result as `Raster::region()`.
+    /// * `adj` Color [Adjustment].
+    ///
+    /// The *alpha* channel is unchanged.  Results outside of the sRGB gamut
+    /// are clamped.
+    ///
+    /// ### Increase exposure
+    /// ```
+    /// use pix::adjust::Adjustment;
+    /// use pix::rgb::Rgba8;
+    /// use pix::Raster;
+    ///
+    /// let mut r = Raster::with_color(2, 2, Rgba8::new(0x40, 0x20, 0x10, 0x80));
+    /// r.adjust((0, 0, 1, 2), Adjustment::Exposure(1.0));
+    /// assert_eq!(r.pixel(0, 1), Rgba8::new(0x80, 0x40, 0x20, 0x80));
+    /// assert_eq!(r.pixel(1, 1), Rgba8::new(0x40, 0x20, 0x10, 0x80));
+    /// ```
+    ///
+    /// [Adjustment]: adjust/enum.Adjustment.html
+    pub fn adjust<R>(&mut self, reg: R, adj: Adjustment)
+    where
+        R: Into<Region>,
+    {
+        let reg = self.intersection(reg);
+        for row in self.rows_mut(reg) {
+            for p in row.iter_mut() {
+                *p = adj.apply(*p);
+            }
+        }
+    }
+}
+
+/// Multiply two matrices
+fn mul(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut m = [[0.0; 3]; 3];
+    for (row, a_row) in m.iter_mut().zip(a) {
+        for (j, v) in row.iter_mut().enumerate() {
+            *v = a_row.iter().zip(b).map(|(a, b_row)| a * b_row[j]).sum();
+        }
+    }
+    m
+}
+
+/// Multiply a matrix by a vector
+fn mul_vec(m: &Matrix, v: [f32; 3]) -> [f32; 3] {
+    m.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+}
+
+/// Invert a matrix (which must not be singular)
+fn invert(m: &Matrix) -> Matrix {
+    let cofactor = |r0: usize, c0: usize, r1: usize, c1: usize| {
+        m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+    };
+    let c = [
+        [
+            cofactor(1, 1, 2, 2),
+            cofactor(0, 2, 2, 1),
+            cofactor(0, 1, 1, 2),
+        ],
+        [
+            cofactor(1, 2, 2, 0),
+            cofactor(0, 0, 2, 2),
+            cofactor(0, 2, 1, 0),
+        ],
+        [
+            cofactor(1, 0, 2, 1),
+            cofactor(0, 1, 2, 0),
+            cofactor(0, 0, 1, 1),
+        ],
+    ];
+    let det = m[0][0] * c[0][0] + m[0][1] * c[1][0] + m[0][2] * c[2][0];
+    c.map(|row| row.map(|v| v / det))
+}
+
+/// Get CIE *x*, *y* chromaticity of the Planckian locus.
+///
+/// See "Design of Advanced Color Temperature Control System for HDTV
+/// Applications" (Kim et al., 2002).
+fn planckian_xy(kelvin: f32) -> (f32, f32) {
+    let t = kelvin.clamp(1667.0, 25000.0);
+    let (t2, t3) = (t * t, t * t * t);
+    let x = if t <= 4000.0 {
+        -0.2661239e9 / t3 - 0.2343589e6 / t2 + 0.8776956e3 / t + 0.179910
+    } else {
+        -3.0258469e9 / t3 + 2.1070379e6 / t2 + 0.2226347e3 / t + 0.240390
+    };
+    let (x2, x3) = (x * x, x * x * x);
+    let y = if t <= 2222.0 {
+        -1.1063814 * x3 - 1.34811020 * x2 + 2.18555832 * x - 0.20219683
+    } else if t <= 4000.0 {
+        -0.9549476 * x3 - 1.37418593 * x2 + 2.09137015 * x - 0.16748867
+    } else {
+        3.0817580 * x3 - 5.87338670 * x2 + 3.75112997 * x - 0.37001483
+    };
+    (x, y)
+}
+
+/// Convert CIE *x*, *y* chromaticity to CIE 1960 *u*, *v*
+fn uv_from_xy((x, y): (f32, f32)) -> (f32, f32) {
+    let d = -2.0 * x + 12.0 * y + 3.0;
+    (4.0 * x / d, 6.0 * y / d)
+}
+
+/// Convert CIE 1960 *u*, *v* chromaticity to CIE *x*, *y*
+fn xy_from_uv((u, v): (f32, f32)) -> (f32, f32) {
+    let d = 2.0 * u - 8.0 * v + 4.0;
+    (3.0 * u / d, 2.0 * v / d)
+}
+
+impl Adaptation {
+    /// Get the cone response matrix (from XYZ)
+    fn matrix(self) -> Matrix {
+        match self {
+            #[rustfmt::skip]
+            Adaptation::Bradford => [
+                [ 0.8951,  0.2664, -0.1614],
+                [-0.7502,  1.7135,  0.0367],
+                [ 0.0389, -0.0685,  1.0296],
+            ],
+            #[rustfmt::skip]
+            Adaptation::Cat02 => [
+                [ 0.7328,  0.4296, -0.1624],
+                [-0.7036,  1.6975,  0.0061],
+                [ 0.0030,  0.0136,  0.9834],
+            ],
+        }
+    }
+}
+
+impl Default for WhiteBalance {
+    fn default() -> Self {
+        WhiteBalance { matrix: IDENTITY }
+    }
+}
+
+impl WhiteBalance {
+    /// Create a white balance adapting a white point to D65.
+    ///
+    /// * `white` CIE XYZ of the source white point.
+    /// * `adapt` Chromatic adaptation transform.
+    fn with_white(white: [f32; 3], adapt: Adaptation) -> Self {
+        if white[1].is_nan() || white[1] <= 0.0 {
+            return WhiteBalance::default();
+        }
+        let src = mul_vec(&adapt.matrix(), white.map(|v| v / white[1]));
+        let d65 = mul_vec(&XYZ_FROM_RGB, [1.0; 3]);
+        let dst = mul_vec(&adapt.matrix(), d65);
+        let mut gain = IDENTITY;
+        for i in 0..3 {
+            gain[i][i] = dst[i] / src[i];
+        }
+        let cone = mul(&gain, &mul(&adapt.matrix(), &XYZ_FROM_RGB));
+        let xyz = mul(&invert(&adapt.matrix()), &cone);
+        WhiteBalance {
+            matrix: mul(&RGB_FROM_XYZ, &xyz),
+        }
+    }
+
+    /// Create a white balance from a neutral pixel.
+    ///
+    /// * `neutral` Pixel which should be neutral (gray).
+    /// * `adapt` Chromatic adaptation transform.
+    ///
+    /// After correction, `neutral` becomes gray, with the same luminance.
+    pub fn with_neutral<P>(neutral: P, adapt: Adaptation) -> Self
+    where
+        P: Pixel<Model = Rgb>,
+        Ch32: From<P::Chan>,
+    {
+        let rgba: Rgba<Linear> = neutral.convert();
+        let ch = rgba.channels();
+        let rgb = [ch[0].to_f32(), ch[1].to_f32(), ch[2].to_f32()];
+        Self::with_white(mul_vec(&XYZ_FROM_RGB, rgb), adapt)
+    }
+
+    /// Create a white balance from a color temperature and tint.
+    ///
+    /// * `kelvin` Color temperature of the light source, from 1667 to 25000
+    ///   kelvin.
+    /// * `tint` Distance of the light source from the Planckian locus, as
+    ///   CIE 1960 Δ*uv* (typically `-0.02` to `0.02`).  Positive values are
+    ///   greener; negative values are more magenta.
+    /// * `adapt` Chromatic adaptation transform.
+    pub fn with_temperature(kelvin: f32, tint: f32, adapt: Adaptation) -> Self {
+        let (u0, v0) = uv_from_xy(planckian_xy(kelvin));
+        let (u1, v1) = uv_from_xy(planckian_xy(kelvin * 1.01));
+        // normal to the locus, pointing toward green (above)
+        let (du, dv) = (u1 - u0, v1 - v0);
+        let len = du.hypot(dv);
+        let (nu, nv) = if len > 0.0 {
+            (-dv / len, du / len)
+        } else {
+            (0.0, 1.0)
+        };
+        let (nu, nv) = if nv < 0.0 { (-nu, -nv) } else { (nu, nv) };
+        let (x, y) = xy_from_uv((u0 + nu * tint, v0 + nv * tint));
+        Self::with_white([x / y, 1.0, (1.0 - x - y) / y], adapt)
+    }
+
+    /// Get the correction matrix, for linear sRGB *red*, *green* and
+    /// *blue*.
+    pub fn matrix(&self) -> [[f32; 3]; 3] {
+        self.matrix
+    }
+}
+
+impl<P> Raster<P>
+where
+    P: Pixel<Model = Rgb>,
+    P::Chan: From<Ch32>,
+    Ch32: From<P::Chan>,
+{
+    /// Correct white balance of a region.
+    ///
+    /// * `reg` Region within `self`.  It can be a `Region` struct, tuple of
+    ///   (*x*, *y*, *width*, *height*) or the unit type `()`.  Using
+    ///   `()` has the same result as `Raster::region()`.
+    /// * `wb` [WhiteBalance] correction.
+    ///
+    /// Pixels are corrected in linear light, with either [Gamma].  The
+    /// *alpha* channel is unchanged.
+    ///
+    /// [Gamma]: chan/trait.Gamma.html
+    /// [WhiteBalance]: adjust/struct.WhiteBalance.html
+    pub fn white_balance<R>(&mut self, reg: R, wb: &WhiteBalance)
+    where
+        R: Into<Region>,
+    {
+        let reg = self.intersection(reg);
+        for row in self.rows_mut(reg) {
+            for p in row.iter_mut() {
+                let mut rgba: Rgba<Linear> = p.convert();
+                map_rgb(&mut rgba, |rgb| mul_vec(&wb.matrix, rgb));
+                *p = rgba.convert();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gray::*;
+    use crate::hsl::*;
+    use crate::rgb::*;
+
+    fn adjusted(p: SRgb8, adj: Adjustment) -> SRgb8 {
+        let mut r = Raster::with_color(1, 1, p);
+        r.adjust((), adj);
+        r.pixel(0, 0)
+    }
+
+    #[test]
+    fn brightness_contrast() {
+        let p = SRgb8::new(0x40, 0x80, 0xF0);
+        let b = adjusted(p, Adjustment::Brightness(0.25));
+        assert_eq!(b, SRgb8::new(0x80, 0xC0, 0xFF));
+        let c = adjusted(p, Adjustment::Contrast(-1.0));
+        assert_eq!(c, SRgb8::new(0x80, 0x80, 0x80));
+        let c = adjusted(p, Adjustment::Contrast(1.0));
+        assert_eq!(c, SRgb8::new(0x01, 0x81, 0xFF));
+        let mut r = Raster::with_color(2, 1, SGray8::new(0x7F));
+        r.adjust((1, 0, 1, 1), Adjustment::Brightness(-0.5));
+        assert_eq!(r.pixel(0, 0), SGray8::new(0x7F));
+        assert_eq!(r.pixel(1, 0), SGray8::new(0));
+    }
+
+    #[test]
+    fn saturation() {
+        let p = SRgb8::new(0xC0, 0x40, 0x40);
+        for space in [Space::Oklab, Space::Hsl] {
+            let g = adjusted(p, Adjustment::Saturation(-1.0, space));
+            let [r, g, b] = [g.one(), g.two(), g.three()].map(u8::from);
+            assert!(r.abs_diff(g) <= 1 && g.abs_diff(b) <= 1, "{space:?}");
+        }
+        // gray is unchanged
+        let g = SRgb8::new(0x77, 0x77, 0x77);
+        assert_eq!(adjusted(g, Adjustment::Saturation(0.5, Space::Oklab)), g);
+        assert_eq!(adjusted(g, Adjustment::Vibrance(1.0)), g);
+    }
+
+    #[test]
+    fn vibrance() {
+        let dull = SRgb8::new(0x90, 0x80, 0x78);
+        let vivid = SRgb8::new(0xF0, 0x20, 0x10);
+        let spread = |p: SRgb8| u8::from(p.one()) - u8::from(p.three());
+        let d = adjusted(dull, Adjustment::Vibrance(1.0));
+        let v = adjusted(vivid, Adjustment::Vibrance(1.0));
+        assert!(spread(d) as f32 > spread(dull) as f32 * 1.5);
+        assert!((spread(v) as f32) < spread(vivid) as f32 * 1.2);
+    }
+
+    #[test]
+    fn hue_rotate() {
+        let p = SRgb8::new(0xFF, 0x00, 0x00);
+        let h = adjusted(p, Adjustment::HueRotate(120.0, Space::Hsl));
+        assert_eq!(h, SRgb8::new(0x00, 0xFF, 0x00));
+        let h = adjusted(p, Adjustment::HueRotate(-120.0, Space::Hsl));
+        assert_eq!(h, SRgb8::new(0x00, 0x00, 0xFF));
+        let h = adjusted(p, Adjustment::HueRotate(360.0, Space::Oklab));
+        assert_eq!(h, p);
+        let mut r = Raster::with_color(1, 1, Hsl8::new(0xF0, 0x80, 0x80));
+        r.adjust((), Adjustment::HueRotate(45.0, Space::Hsl));
+        assert_eq!(r.pixel(0, 0).one(), 0x11.into());
+    }
+
+    fn approx(a: &Matrix, b: &Matrix, tolerance: f32) -> bool {
+        a.iter()
+            .flatten()
+            .zip(b.iter().flatten())
+            .all(|(a, b)| (a - b).abs() < tolerance)
+    }
+
+    #[test]
+    fn matrices() {
+        assert!(approx(&mul(&RGB_FROM_XYZ, &XYZ_FROM_RGB), &IDENTITY, 1e-4));
+        for adapt in [Adaptation::Bradford, Adaptation::Cat02] {
+            let m = adapt.matrix();
+            assert!(approx(&mul(&m, &invert(&m)), &IDENTITY, 1e-5));
+        }
+        let (x, y) = planckian_xy(6504.0);
+        assert!((x - 0.3135).abs() < 1e-3 && (y - 0.3237).abs() < 1e-3);
+        let (x, y) = xy_from_uv(uv_from_xy((0.3127, 0.329)));
+        assert!((x - 0.3127).abs() < 1e-6 && (y - 0.329).abs() < 1e-6);
+    }
+
+    #[test]
+    fn white_balance() {
+        let wb = WhiteBalance::with_neutral(
+            SRgb8::new(0xFF, 0xFF, 0xFF),
+            Adaptation::Cat02,
+        );
+        assert!(approx(&wb.matrix(), &IDENTITY, 1e-4));
+        let wb = WhiteBalance::with_temperature(
+            6504.0,
+            0.0032,
+            Adaptation::Bradford,
+        );
+        assert!(approx(&wb.matrix(), &IDENTITY, 0.01));
+        let neutral = Rgb16::new(0x9000, 0x8000, 0x6000);
+        let wb = WhiteBalance::with_neutral(neutral, Adaptation::Bradford);
+        let mut r = Raster::with_color(2, 1, neutral);
+        r.white_balance((1, 0, 1, 1), &wb);
+        assert_eq!(r.pixel(0, 0), neutral);
+        let p = r.pixel(1, 0);
+        let [red, green, blue] = [p.one(), p.two(), p.three()].map(u16::from);
+        assert!(red.abs_diff(green) < 0x10 && green.abs_diff(blue) < 0x10);
+        let luma = |p: Rgb16| {
+            let ch = p.channels();
+            mul_vec(&XYZ_FROM_RGB, [0, 1, 2].map(|i| ch[i].to_f32()))[1]
+        };
+        assert!((luma(p) - luma(neutral)).abs() < 1e-3);
+        let warm =
+            WhiteBalance::with_temperature(3000.0, 0.0, Adaptation::Bradford);
+        let green =
+            WhiteBalance::with_temperature(3000.0, 0.01, Adaptation::Bradford);
+        let g = mul_vec(&warm.matrix(), [1.0; 3]);
+        let gg = mul_vec(&green.matrix(), [1.0; 3]);
+        assert!(g[2] > g[0]);
+        assert!(gg[1] < g[1]);
+    }
+}
@@ -0,0 +1,445 @@
+// analysis.rs  Edge detection and image analysis
+//
+// Copyright (c) 2023  Douglas P Lau
+//
+//! Edge detection and image analysis.
+//!
+//! Operators are applied to the *luma* of a `Raster`, computed by
+//! converting to [Gray32] (linear gamma).
+//!
+//! [Gray32]: ../gray/type.Gray32.html
+use crate::chan::{Ch32, Channel};
+use crate::el::Pixel;
+use crate::filter::Separable;
+use crate::gray::Gray32;
+use crate::matte::Matte8;
+use crate::raster::Raster;
+use crate::warp::Edge;
+
+/// Gradient operator.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Operator {
+    /// Sobel 3x3 operator
+    #[default]
+    Sobel,
+    /// Scharr 3x3 operator, with better rotational symmetry
+    Scharr,
+}
+
+/// Luma gradient of a `Raster`.
+///
+/// Gradients are scaled to units of luma per pixel, so a vertical edge
+/// between black and white has a peak `dx` of `0.5`.  Pixels outside of the
+/// `Raster` are clamped to the nearest edge.
+///
+/// ### Find a vertical edge
+/// ```
+/// use pix::analysis::{Gradient, Operator};
+/// use pix::gray::Gray8;
+/// use pix::Raster;
+///
+/// let mut r = Raster::with_clear(4, 3);
+/// r.copy_color((2, 0, 2, 3), Gray8::new(0xFF));
+/// let g = Gradient::new(&r, Operator::Sobel);
+/// assert_eq!(g.magnitude(0, 1), 0.0);
+/// assert_eq!(g.magnitude(1, 1), 0.5);
+/// assert_eq!(g.direction(1, 1), 0.0);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    width: u32,
+    height: u32,
+    dx: Vec<f32>,
+    dy: Vec<f32>,
+}
+
+/// Laplacian of a `Raster`.
+///
+/// The 4-neighbor Laplacian of luma, with pixels outside of the `Raster`
+/// clamped to the nearest edge.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Laplacian {
+    width: u32,
+    height: u32,
+    values: Vec<f32>,
+}
+
+/// Canny edge detector.
+///
+/// ### Detect edges
+/// ```
+/// use pix::analysis::Canny;
+/// use pix::matte::Matte8;
+/// use pix::rgb::SRgb8;
+/// use pix::Raster;
+///
+/// let mut r = Raster::with_clear(16, 16);
+/// r.copy_color((4, 4, 8, 8), SRgb8::new(0xFF, 0xFF, 0xFF));
+/// let edges = Canny::default().edges(&r);
+/// assert_eq!(edges.pixel(4, 8), Matte8::new(0xFF));
+/// assert_eq!(edges.pixel(8, 8), Matte8::new(0));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Canny {
+    /// Gaussian smoothing standard deviation, in pixels
+    pub sigma: f32,
+    /// Low threshold for gradient magnitude (weak edges)
+    pub low: f32,
+    /// High threshold for gradient magnitude (strong edges)
+    pub high: f32,
+    /// Gradient operator
+    pub operator: Operator,
+}
+
+impl Operator {
+    /// Get smoothing weights, perpendicular to the derivative
+    fn weights(self) -> [f32; 3] {
+        match self {
+            Operator::Sobel => [1.0, 2.0, 1.0],
+            Operator::Scharr => [3.0, 10.0, 3.0],
+        }
+    }
+}
+
+/// Get luma values of a raster
+fn luma<P>(raster: &Raster<P>) -> Raster<Gray32>
+where
+    P: Pixel,
+    Ch32: From<P::Chan>,
+{
+    Raster::with_raster(raster)
+}
+
+/// Get a luma value, clamped to the nearest edge
+fn luma_at(luma: &Raster<Gray32>, x: i32, y: i32) -> f32 {
+    let x = x.clamp(0, luma.width() as i32 - 1);
+    let y = y.clamp(0, luma.height() as i32 - 1);
+    luma.pixel(x, y).one().to_f32()
+}
+
+impl Gradient {
+    /// Compute the gradient of a `Raster`.
+    ///
+    /// * `raster` Source raster.
+    /// * `op` Gradient operator.
+    pub fn new<P>(raster: &Raster<P>, op: Operator) -> Self
+    where
+        P: Pixel,
+        Ch32: From<P::Chan>,
+    {
+        Self::with_luma(&luma(raster), op)
+    }
+
+    /// Compute the gradient of luma values
+    fn with_luma(luma: &Raster<Gray32>, op: Operator) -> Self {
+        let (width, height) = (luma.width(), luma.height());
+        let w = op.weights();
+        let scale = 1.0 / (2.0 * w.iter().sum::<f32>());
+        let len = width as usize * height as usize;
+        let mut dx = Vec::with_capacity(len);
+        let mut dy = Vec::with_capacity(len);
+        let lum = |x, y| luma_at(luma, x, y);
+        for y in 0..height as i32 {
+            for x in 0..width as i32 {
+                let mut gx = 0.0;
+                let mut gy = 0.0;
+                for (d, wt) in (-1..=1).zip(w) {
+                    gx += wt * (lum(x + 1, y + d) - lum(x - 1, y + d));
+                    gy += wt * (lum(x + d, y + 1) - lum(x + d, y - 1));
+                }
+                dx.push(gx * scale);
+                dy.push(gy * scale);
+            }
+        }
+        Gradient {
+            width,
+            height,
+            dx,
+            dy,
+        }
+    }
+
+    /// Get width in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get height in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get index of a pixel
+    fn index(&self, x: i32, y: i32) -> usize {
+        assert!(x >= 0 && (x as u32) < self.width);
+        assert!(y >= 0 && (y as u32) < self.height);
+        y as usize * self.width as usize + x as usize
+    }
+
+    /// Get the horizontal gradient at a pixel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` or `y` is out of bounds.
+    pub fn dx(&self, x: i32, y: i32) -> f32 {
+        self.dx[self.index(x, y)]
+    }
+
+    /// Get the vertical gradient at a pixel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` or `y` is out of bounds.
+    pub fn dy(&self, x: i32, y: i32) -> f32 {
+        self.dy[self.index(x, y)]
+    }
+
+    /// Get the gradient magnitude at a pixel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` or `y` is out of bounds.
+    pub fn magnitude(&self, x: i32, y: i32) -> f32 {
+        let i = self.index(x, y);
+        self.dx[i].hypot(self.dy[i])
+    }
+
+    /// Get the gradient direction at a pixel.
+    ///
+    /// The angle is in radians, from `-PI` to `PI`, with `0` pointing in
+    /// the +X direction and `PI / 2` pointing in the +Y direction.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` or `y` is out of bounds.
+    pub fn direction(&self, x: i32, y: i32) -> f32 {
+        let i = self.index(x, y);
+        self.dy[i].atan2(self.dx[i])
+    }
+
+    /// Get gradient magnitudes as a `Raster`.
+    ///
+    /// * `scale` Multiplier for magnitudes, which are clamped to `1.0`.
+    pub fn to_matte(&self, scale: f32) -> Raster<Matte8> {
+        let mut r = Raster::with_clear(self.width, self.height);
+        let mags = self.dx.iter().zip(&self.dy).map(|(x, y)| x.hypot(*y));
+        for (p, m) in r.pixels_mut().iter_mut().zip(mags) {
+            *p = Matte8::new(Ch32::new(m * scale));
+        }
+        r
+    }
+}
+
+impl Laplacian {
+    /// Compute the Laplacian of a `Raster`.
+    pub fn new<P>(raster: &Raster<P>) -> Self
+    where
+        P: Pixel,
+        Ch32: From<P::Chan>,
+    {
+        let luma = luma(raster);
+        let (width, height) = (luma.width(), luma.height());
+        let mut values = Vec::with_capacity(width as usize * height as usize);
+        for y in 0..height as i32 {
+            for x in 0..width as i32 {
+                let v = luma_at(&luma, x, y - 1)
+                    + luma_at(&luma, x - 1, y)
+                    + luma_at(&luma, x + 1, y)
+                    + luma_at(&luma, x, y + 1)
+                    - 4.0 * luma_at(&luma, x, y);
+                values.push(v);
+            }
+        }
+        Laplacian {
+            width,
+            height,
+            values,
+        }
+    }
+
+    /// Get width in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get height in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get the Laplacian at a pixel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` or `y` is out of bounds.
+    pub fn value(&self, x: i32, y: i32) -> f32 {
+        assert!(x >= 0 && (x as u32) < self.width);
+        assert!(y >= 0 && (y as u32) < self.height);
+        self.values[y as usize * self.width as usize + x as usize]
+    }
+
+    /// Get absolute values as a `Raster`.
+    ///
+    /// * `scale` Multiplier for values, which are clamped to `1.0`.
+    pub fn to_matte(&self, scale: f32) -> Raster<Matte8> {
+        let mut r = Raster::with_clear(self.width, self.height);
+        for (p, v) in r.pixels_mut().iter_mut().zip(&self.values) {
+            *p = Matte8::new(Ch32::new(v.abs() * scale));
+        }
+        r
+    }
+}
+
+impl Default for Canny {
+    fn default() -> Self {
+        Canny {
+            sigma: 1.4,
+            low: 0.05,
+            high: 0.1,
+            operator: Operator::Sobel,
+        }
+    }
+}
+
+impl Canny {
+    /// Detect edges in a `Raster`.
+    ///
+    /// Returns an edge map, with edge pixels set to full *alpha*.
+    pub fn edges<P>(&self, raster: &Raster<P>) -> Raster<Matte8>
+    where
+        P: Pixel,
+        Ch32: From<P::Chan>,
+    {
+        let mut luma = luma(raster);
+        if self.sigma > 0.0 {
+            let gauss = Separable::with_gaussian(self.sigma);
+            luma.convolve_separable((), &gauss, Edge::Clamp);
+        }
+        let grad = Gradient::with_luma(&luma, self.operator);
+        let (w, h) = (grad.width as i32, grad.height as i32);
+        let mut edges = Raster::<Matte8>::with_clear(grad.width, grad.height);
+        let mut stack = Vec::new();
+        // non-maximum suppression
+        let mut weak = vec![false; grad.dx.len()];
+        for y in 0..h {
+            for x in 0..w {
+                let mag = grad.magnitude(x, y);
+                if mag < self.low || !Self::is_maximum(&grad, x, y, mag) {
+                    continue;
+                }
+                if mag >= self.high {
+                    stack.push((x, y));
+                } else {
+                    weak[grad.index(x, y)] = true;
+                }
+            }
+        }
+        // hysteresis: trace weak edges connected to strong edges
+        while let Some((x, y)) = stack.pop() {
+            *edges.pixel_mut(x, y) = Matte8::new(0xFF);
+            for ny in (y - 1).max(0)..=(y + 1).min(h - 1) {
+                for nx in (x - 1).max(0)..=(x + 1).min(w - 1) {
+                    let i = grad.index(nx, ny);
+                    if weak[i] {
+                        weak[i] = false;
+                        stack.push((nx, ny));
+                    }
+                }
+            }
+        }
+        edges
+    }
+
+    /// Check if a magnitude is a maximum along the gradient direction
+    fn is_maximum(grad: &Gradient, x: i32, y: i32, mag: f32) -> bool {
+        let (dx, dy) = (grad.dx(x, y), grad.dy(x, y));
+        // quantize direction to one of four neighbor pairs
+        let (ox, oy) = if dy.abs() * 2.4142 < dx.abs() {
+            (1, 0)
+        } else if dx.abs() * 2.4142 < dy.abs() {
+            (0, 1)
+        } else if (dx > 0.0) == (dy > 0.0) {
+            (1, 1)
+        } else {
+            (1, -1)
+        };
+        let neighbor = |x: i32, y: i32| {
+            let x = x.clamp(0, grad.width as i32 - 1);
+            let y = y.clamp(0, grad.height as i32 - 1);
+            grad.magnitude(x, y)
+        };
+        mag >= neighbor(x - ox, y - oy) && mag > neighbor(x + ox, y + oy)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gray::*;
+    use crate::rgb::*;
+
+    #[test]
+    fn sobel_scharr() {
+        let mut r = Raster::<Gray8>::with_clear(5, 5);
+        r.copy_color((0, 3, 5, 2), Gray8::new(0xFF));
+        for op in [Operator::Sobel, Operator::Scharr] {
+            let g = Gradient::new(&r, op);
+            assert_eq!(g.dx(2, 2), 0.0);
+            assert_eq!(g.dy(2, 2), 0.5);
+            assert_eq!(g.dy(2, 0), 0.0);
+            assert_eq!(g.direction(2, 3), std::f32::consts::FRAC_PI_2);
+        }
+        let m = Gradient::new(&r, Operator::Sobel).to_matte(2.0);
+        assert_eq!(m.pixel(0, 2), Matte8::new(0xFF));
+        assert_eq!(m.pixel(0, 1), Matte8::new(0));
+    }
+
+    #[test]
+    fn laplacian() {
+        let mut r = Raster::<Gray32>::with_clear(5, 1);
+        for x in 0..5 {
+            *r.pixel_mut(x, 0) = Gray32::new(x as f32 * 0.25);
+        }
+        let l = Laplacian::new(&r);
+        assert_eq!(l.value(2, 0), 0.0);
+        assert_eq!(l.value(4, 0), -0.25);
+        assert_eq!(l.to_matte(4.0).pixel(0, 0), Matte8::new(0xFF));
+    }
+
+    #[test]
+    fn canny() {
+        let mut r = Raster::<SRgb8>::with_color(24, 20, SRgb8::new(0, 0, 0));
+        r.copy_color((6, 5, 12, 10), SRgb8::new(0xC0, 0xC0, 0xC0));
+        let edges = Canny::default().edges(&r);
+        for y in 6..14 {
+            let edge = Matte8::new(0xFF);
+            assert!(edges.pixel(5, y) == edge || edges.pixel(6, y) == edge);
+        }
+        for y in 0..20 {
+            for x in 0..24 {
+                let e = edges.pixel(x, y) == Matte8::new(0xFF);
+                let inside = (7..17).contains(&x) && (6..14).contains(&y);
+                let outside = !(4..20).contains(&x) || !(3..17).contains(&y);
+                if inside || outside {
+                    assert!(!e, "{x},{y}");
+                }
+            }
+        }
+        let edges = Canny::default().edges(&Raster::<Rgb8>::with_clear(4, 4));
+        assert!(edges.pixels().iter().all(|p| *p == Matte8::new(0)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn gradient_bounds() {
+        let r = Raster::<Gray8>::with_clear(4, 4);
+        let _ = Gradient::new(&r, Operator::Sobel).dx(4, 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn laplacian_bounds() {
+        let r = Raster::<Gray8>::with_clear(4, 4);
+        let _ = Laplacian::new(&r).value(4, 0);
+    }
+}
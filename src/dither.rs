@@ -0,0 +1,514 @@
+// dither.rs    Dithering for bit depth reduction
+//
+// Copyright (c) 2023  Douglas P Lau
+//
+use crate::chan::{Ch16, Ch32, Ch8, Channel, Gamma};
+use crate::el::{convert_alpha_gamma, Pixel};
+use crate::raster::Raster;
+use crate::ColorModel;
+use std::any::TypeId;
+use std::sync::OnceLock;
+
+/// Width and height of blue noise matrix
+const BLUE_NOISE_SIZE: usize = 32;
+
+/// Standard deviation of void-and-cluster energy filter
+const BLUE_NOISE_SIGMA: f32 = 1.5;
+
+/// Padding on each side of error diffusion rows
+const PAD: usize = 2;
+
+/// Scan order for error diffusion [Dither]ing.
+///
+/// [Dither]: enum.Dither.html
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Scan {
+    /// All rows left to right
+    #[default]
+    Raster,
+    /// Alternating rows left to right and right to left, which reduces
+    /// directional artifacts
+    Serpentine,
+}
+
+/// Dithering method, for [Raster::with_raster_dithered].
+///
+/// [Raster::with_raster_dithered]: struct.Raster.html#method.with_raster_dithered
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dither {
+    /// Ordered, with a 2x2 Bayer matrix
+    Bayer2,
+    /// Ordered, with a 4x4 Bayer matrix
+    Bayer4,
+    /// Ordered, with an 8x8 Bayer matrix
+    Bayer8,
+    /// Ordered, with a 32x32 blue noise matrix
+    BlueNoise,
+    /// Floyd-Steinberg error diffusion
+    FloydSteinberg(Scan),
+    /// Atkinson error diffusion, which only diffuses 3/4 of the error
+    Atkinson(Scan),
+    /// Jarvis-Judice-Ninke error diffusion
+    JarvisJudiceNinke(Scan),
+    /// Sierra error diffusion
+    Sierra(Scan),
+}
+
+/// Error diffusion kernel
+struct Diffusion {
+    /// Offsets (*x*, *y*) and weights
+    taps: &'static [(isize, usize, f32)],
+    /// Sum of all weights (including undiffused error)
+    divisor: f32,
+}
+
+/// Floyd-Steinberg kernel
+const FLOYD_STEINBERG: Diffusion = Diffusion {
+    taps: &[(1, 0, 7.0), (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0)],
+    divisor: 16.0,
+};
+
+/// Atkinson kernel
+const ATKINSON: Diffusion = Diffusion {
+    taps: &[
+        (1, 0, 1.0),
+        (2, 0, 1.0),
+        (-1, 1, 1.0),
+        (0, 1, 1.0),
+        (1, 1, 1.0),
+        (0, 2, 1.0),
+    ],
+    divisor: 8.0,
+};
+
+/// Jarvis-Judice-Ninke kernel
+#[rustfmt::skip]
+const JARVIS_JUDICE_NINKE: Diffusion = Diffusion {
+    taps: &[
+                                             (1, 0, 7.0), (2, 0, 5.0),
+        (-2, 1, 3.0), (-1, 1, 5.0), (0, 1, 7.0), (1, 1, 5.0), (2, 1, 3.0),
+        (-2, 2, 1.0), (-1, 2, 3.0), (0, 2, 5.0), (1, 2, 3.0), (2, 2, 1.0),
+    ],
+    divisor: 48.0,
+};
+
+/// Sierra kernel
+#[rustfmt::skip]
+const SIERRA: Diffusion = Diffusion {
+    taps: &[
+                                             (1, 0, 5.0), (2, 0, 3.0),
+        (-2, 1, 2.0), (-1, 1, 4.0), (0, 1, 5.0), (1, 1, 4.0), (2, 1, 2.0),
+                      (-1, 2, 2.0), (0, 2, 3.0), (1, 2, 2.0),
+    ],
+    divisor: 32.0,
+};
+
+/// Void-and-cluster state
+#[derive(Clone)]
+struct VoidCluster {
+    /// Width and height
+    size: usize,
+    /// Energy filter, by toroidal offset
+    filter: Vec<f32>,
+    /// Binary pattern
+    pattern: Vec<bool>,
+    /// Energy of all pixels
+    energy: Vec<f32>,
+}
+
+impl VoidCluster {
+    /// Create an empty pattern
+    fn new(size: usize) -> Self {
+        let n = size * size;
+        let dist = |d: usize| d.min(size - d) as f32;
+        let filter = (0..n)
+            .map(|i| {
+                let (dx, dy) = (dist(i % size), dist(i / size));
+                let d2 = dx * dx + dy * dy;
+                (-d2 / (2.0 * BLUE_NOISE_SIGMA * BLUE_NOISE_SIGMA)).exp()
+            })
+            .collect();
+        VoidCluster {
+            size,
+            filter,
+            pattern: vec![false; n],
+            energy: vec![0.0; n],
+        }
+    }
+
+    /// Set one pixel of the pattern, updating energy
+    fn set(&mut self, i: usize, on: bool) {
+        self.pattern[i] = on;
+        let sign = if on { 1.0 } else { -1.0 };
+        let size = self.size;
+        let (ix, iy) = (i % size, i / size);
+        for (j, e) in self.energy.iter_mut().enumerate() {
+            let dx = (j % size + size - ix) % size;
+            let dy = (j / size + size - iy) % size;
+            *e += sign * self.filter[dy * size + dx];
+        }
+    }
+
+    /// Find the pixel which is the center of the tightest cluster
+    fn tightest_cluster(&self) -> usize {
+        let mut best = (0, f32::NEG_INFINITY);
+        for (i, (p, e)) in self.pattern.iter().zip(&self.energy).enumerate() {
+            if *p && *e > best.1 {
+                best = (i, *e);
+            }
+        }
+        best.0
+    }
+
+    /// Find the pixel which is the center of the largest void
+    fn largest_void(&self) -> usize {
+        let mut best = (0, f32::INFINITY);
+        for (i, (p, e)) in self.pattern.iter().zip(&self.energy).enumerate() {
+            if !*p && *e < best.1 {
+                best = (i, *e);
+            }
+        }
+        best.0
+    }
+
+    /// Generate dither array ranks.
+    ///
+    /// See "The void-and-cluster method for dither array generation"
+    /// (Ulichney, 1993).
+    fn ranks(size: usize) -> Vec<usize> {
+        let n = size * size;
+        let ones = n / 10;
+        let mut vc = VoidCluster::new(size);
+        // initial pattern, with pseudo-random pixels (xorshift)
+        let mut seed = 0x2545_F491_u32;
+        let mut count = 0;
+        while count < ones {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            let i = seed as usize % n;
+            if !vc.pattern[i] {
+                vc.set(i, true);
+                count += 1;
+            }
+        }
+        // move pixels from tightest clusters to largest voids
+        loop {
+            let c = vc.tightest_cluster();
+            vc.set(c, false);
+            let v = vc.largest_void();
+            vc.set(v, true);
+            if v == c {
+                break;
+            }
+        }
+        let mut ranks = vec![0; n];
+        let mut remove = vc.clone();
+        for r in (0..ones).rev() {
+            let c = remove.tightest_cluster();
+            remove.set(c, false);
+            ranks[c] = r;
+        }
+        for r in ones..n {
+            let v = vc.largest_void();
+            vc.set(v, true);
+            ranks[v] = r;
+        }
+        ranks
+    }
+}
+
+/// Get value from a Bayer matrix.
+///
+/// * `bits` Matrix size, in bits (1 for 2x2).
+fn bayer(bits: u32, x: usize, y: usize) -> usize {
+    (0..bits).fold(0, |v, i| {
+        let (xi, yi) = ((x >> i) & 1, (y >> i) & 1);
+        v + (((xi ^ yi) << 1) | yi) * (1 << (2 * (bits - 1 - i)))
+    })
+}
+
+/// Get blue noise threshold matrix
+fn blue_noise() -> &'static [f32] {
+    static MATRIX: OnceLock<Vec<f32>> = OnceLock::new();
+    MATRIX.get_or_init(|| {
+        let n = (BLUE_NOISE_SIZE * BLUE_NOISE_SIZE) as f32;
+        VoidCluster::ranks(BLUE_NOISE_SIZE)
+            .into_iter()
+            .map(|r| (r as f32 + 0.5) / n - 0.5)
+            .collect()
+    })
+}
+
+/// Get number of quantization levels for a channel type
+fn channel_levels<C: 'static>() -> Option<f32> {
+    if TypeId::of::<C>() == TypeId::of::<Ch8>() {
+        Some(255.0)
+    } else if TypeId::of::<C>() == TypeId::of::<Ch16>() {
+        Some(65535.0)
+    } else {
+        None
+    }
+}
+
+impl Dither {
+    /// Get ordered threshold offset (`-0.5` to `0.5`) at a pixel
+    fn threshold(self, x: usize, y: usize) -> f32 {
+        let ordered = |bits: u32| {
+            let n = (1 << (2 * bits)) as f32;
+            (bayer(bits, x, y) as f32 + 0.5) / n - 0.5
+        };
+        match self {
+            Dither::Bayer2 => ordered(1),
+            Dither::Bayer4 => ordered(2),
+            Dither::Bayer8 => ordered(3),
+            Dither::BlueNoise => {
+                let (x, y) = (x % BLUE_NOISE_SIZE, y % BLUE_NOISE_SIZE);
+                blue_noise()[y * BLUE_NOISE_SIZE + x]
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// Get error diffusion kernel and scan order
+    fn diffusion(self) -> Option<(Diffusion, Scan)> {
+        match self {
+            Dither::FloydSteinberg(scan) => Some((FLOYD_STEINBERG, scan)),
+            Dither::Atkinson(scan) => Some((ATKINSON, scan)),
+            Dither::JarvisJudiceNinke(scan) => {
+                Some((JARVIS_JUDICE_NINKE, scan))
+            }
+            Dither::Sierra(scan) => Some((SIERRA, scan)),
+            _ => None,
+        }
+    }
+}
+
+/// Get channel values of a source pixel, in the destination format
+fn dest_values<P, S>(src: S, values: &mut Vec<f32>)
+where
+    P: Pixel,
+    S: Pixel<Model = P::Model>,
+    Ch32: From<S::Chan>,
+{
+    let mut channels = [Ch32::MIN; 4];
+    let n = src.channels().len();
+    for (c, s) in channels.iter_mut().zip(src.channels()) {
+        *c = Ch32::from(*s);
+    }
+    if TypeId::of::<S::Alpha>() != TypeId::of::<P::Alpha>()
+        || TypeId::of::<S::Gamma>() != TypeId::of::<P::Gamma>()
+    {
+        let alpha = Ch32::from(src.alpha());
+        convert_alpha_gamma::<P, S, Ch32>(&mut channels[..n], alpha);
+    }
+    values.extend(channels[..n].iter().map(|c| c.to_f32()));
+}
+
+/// Diffuse quantization error.
+///
+/// * `P` Destination pixel format.
+/// * `values` Channel values, replaced with quantized values.
+/// * `width` Width in pixels.
+/// * `levels` Number of quantization levels.
+/// * `kernel` Error diffusion kernel.
+/// * `scan` Scan order.
+///
+/// Error is computed in linear light for gamma-encoded channels.
+fn diffuse<P: Pixel>(
+    values: &mut [f32],
+    width: usize,
+    levels: f32,
+    kernel: &Diffusion,
+    scan: Scan,
+) {
+    if width == 0 {
+        return;
+    }
+    let n = P::default().channels().len();
+    let to_work = |c: usize, v: f32| {
+        if P::Model::LINEAR.contains(&c) {
+            P::Gamma::to_linear(Ch32::new(v)).to_f32()
+        } else {
+            v
+        }
+    };
+    let from_work = |c: usize, v: f32| {
+        if P::Model::LINEAR.contains(&c) {
+            P::Gamma::from_linear(Ch32::new(v)).to_f32()
+        } else {
+            v
+        }
+    };
+    let row_len = (width + PAD * 2) * n;
+    let mut errors = vec![vec![0.0; row_len]; 3];
+    for (y, row) in values.chunks_exact_mut(width * n).enumerate() {
+        let reverse = scan == Scan::Serpentine && y % 2 == 1;
+        let dir = if reverse { -1 } else { 1 };
+        for i in 0..width {
+            let x = if reverse { width - 1 - i } else { i };
+            for c in 0..n {
+                let v = &mut row[x * n + c];
+                let e = errors[0][(x + PAD) * n + c];
+                let desired = (to_work(c, *v) + e).clamp(0.0, 1.0);
+                let q = (from_work(c, desired) * levels).round() / levels;
+                *v = q;
+                let err = (desired - to_work(c, q)) / kernel.divisor;
+                for (dx, dy, w) in kernel.taps {
+                    let tx = (x + PAD).wrapping_add_signed(dx * dir);
+                    errors[*dy][tx * n + c] += err * w;
+                }
+            }
+        }
+        errors.rotate_left(1);
+        errors[2].fill(0.0);
+    }
+}
+
+impl<P: Pixel> Raster<P>
+where
+    P::Chan: From<Ch32>,
+{
+    /// Construct a `Raster` with another `Raster`, using dithering.
+    ///
+    /// * `S` `Pixel` format of source `Raster`, with the same color model.
+    /// * `src` Source `Raster`.
+    /// * `dither` Dithering method.
+    ///
+    /// This avoids banding when reducing bit depth, such as from `Rgb16` to
+    /// `Rgb8`.  All channels are dithered, including *alpha*.  For error
+    /// diffusion, error is computed in linear light when `P` has [sRGB]
+    /// gamma.  If `P` has `Ch32` channels, no dithering is done.
+    ///
+    /// ### Reduce bit depth
+    /// ```
+    /// use pix::gray::{SGray16, SGray8};
+    /// use pix::{Dither, Raster, Scan};
+    ///
+    /// let mut src = Raster::<SGray16>::with_clear(64, 4);
+    /// for (x, p) in src.pixels_mut().iter_mut().enumerate() {
+    ///     *p = SGray16::new(0x8000 + (x % 64) as u16 * 4);
+    /// }
+    /// let ordered = Raster::<SGray8>::with_raster_dithered(&src, Dither::Bayer4);
+    /// let diffused = Raster::<SGray8>::with_raster_dithered(
+    ///     &src,
+    ///     Dither::FloydSteinberg(Scan::Serpentine),
+    /// );
+    /// assert!(ordered.pixels().contains(&SGray8::new(0x81)));
+    /// assert!(diffused.pixels().contains(&SGray8::new(0x81)));
+    /// ```
+    ///
+    /// [sRGB]: chan/struct.Srgb.html
+    pub fn with_raster_dithered<S>(src: &Raster<S>, dither: Dither) -> Self
+    where
+        S: Pixel<Model = P::Model>,
+        Ch32: From<S::Chan>,
+    {
+        let width = src.width() as usize;
+        let n = P::default().channels().len();
+        let mut values = Vec::with_capacity(src.pixels().len() * n);
+        for s in src.pixels() {
+            dest_values::<P, S>(*s, &mut values);
+        }
+        if let Some(levels) = channel_levels::<P::Chan>() {
+            match dither.diffusion() {
+                Some((kernel, scan)) => {
+                    diffuse::<P>(&mut values, width, levels, &kernel, scan)
+                }
+                None => {
+                    for (i, v) in values.iter_mut().enumerate() {
+                        let (x, y) = ((i / n) % width, (i / n) / width);
+                        let t = dither.threshold(x, y) / levels;
+                        *v = (*v + t).clamp(0.0, 1.0);
+                    }
+                }
+            }
+        }
+        let mut dst: Raster<P> = Raster::with_clear(src.width(), src.height());
+        for (p, v) in dst.pixels_mut().iter_mut().zip(values.chunks_exact(n)) {
+            for (c, v) in p.channels_mut().iter_mut().zip(v) {
+                *c = P::Chan::from(Ch32::new(*v));
+            }
+        }
+        dst
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chan::Srgb;
+    use crate::gray::*;
+    use crate::rgb::*;
+
+    const ALL: [Dither; 8] = [
+        Dither::Bayer2,
+        Dither::Bayer4,
+        Dither::Bayer8,
+        Dither::BlueNoise,
+        Dither::FloydSteinberg(Scan::Serpentine),
+        Dither::Atkinson(Scan::Raster),
+        Dither::JarvisJudiceNinke(Scan::Raster),
+        Dither::Sierra(Scan::Serpentine),
+    ];
+
+    #[test]
+    fn matrices() {
+        let m: Vec<usize> = (0..16).map(|i| bayer(2, i % 4, i / 4)).collect();
+        #[rustfmt::skip]
+        assert_eq!(m, [
+            0,  8,  2,  10,
+            12, 4,  14, 6,
+            3,  11, 1,  9,
+            15, 7,  13, 5,
+        ]);
+        let mut ranks = VoidCluster::ranks(16);
+        ranks.sort();
+        assert!(ranks.iter().enumerate().all(|(i, r)| i == *r));
+        let noise = blue_noise();
+        let mean = noise.iter().sum::<f32>() / noise.len() as f32;
+        assert!(mean.abs() < 1e-4);
+        // neighbors should not have similar thresholds
+        let near = (0..BLUE_NOISE_SIZE - 1)
+            .filter(|x| (noise[*x] - noise[x + 1]).abs() < 0.05)
+            .count();
+        assert!(near < 4);
+    }
+
+    #[test]
+    fn mean_preserved() {
+        let mut src = Raster::<Gray16>::with_clear(32, 32);
+        src.pixels_mut()
+            .iter_mut()
+            .for_each(|p| *p = Gray16::new(0x4080));
+        let target = f32::from(Ch32::from(Ch16::new(0x4080)));
+        let rounded = Raster::<Gray8>::with_raster(&src);
+        assert!(rounded.pixels().iter().all(|p| *p == Gray8::new(0x40)));
+        for dither in ALL {
+            let r = Raster::<Gray8>::with_raster_dithered(&src, dither);
+            let sum: f32 = r.pixels().iter().map(|p| p.one().to_f32()).sum();
+            let mean = sum / 1024.0;
+            assert!((mean - target).abs() < 0.0005, "{dither:?} {mean}");
+            assert!(r.pixels().iter().any(|p| *p == Gray8::new(0x41)));
+        }
+    }
+
+    #[test]
+    fn linear_light() {
+        let src = Raster::with_color(16, 16, SRgba32::new(0.3, 0.3, 0.3, 1.0));
+        let r = Raster::<SRgba8>::with_raster_dithered(
+            &src,
+            Dither::FloydSteinberg(Scan::Raster),
+        );
+        let target = Srgb::to_linear(Ch32::new(0.3)).to_f32();
+        let linear: f32 = r
+            .pixels()
+            .iter()
+            .map(|p| Srgb::to_linear(Ch32::from(p.one())).to_f32())
+            .sum();
+        assert!((linear / 256.0 - target).abs() < 0.0002);
+        assert!(r.pixels().iter().all(|p| p.four() == Ch8::new(0xFF)));
+        let r = Raster::<SRgba32>::with_raster_dithered(&src, Dither::Bayer8);
+        assert_eq!(r.pixels(), src.pixels());
+    }
+}
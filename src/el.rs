@@ -287,23 +287,44 @@ where
     // 2. If difference > 180 degrees, rotate both by 180 degrees
     let rotate = s.max(*d) - s.min(*d) > C::MID;
     if rotate {
-        if s > *d {
-            s = s - C::MID;
-            *d = *d + C::MID;
-        } else {
-            s = s + C::MID;
-            *d = *d - C::MID;
-        }
+        s = circ_half_turn(s);
+        *d = circ_half_turn(*d);
     }
     // 3. Lerp between src and dest.
     *d = d.lerp(s, t);
     // 4. If rotated, rotate by 180 degrees
     if rotate {
-        if *d < C::MID {
-            *d = *d + C::MID;
-        } else {
-            *d = *d - C::MID;
-        }
+        *d = circ_half_turn(*d);
+    }
+}
+
+/// Rotate a circular channel by 180 degrees
+#[inline]
+fn circ_half_turn<C: Channel>(c: C) -> C {
+    if c < C::MID {
+        c + C::MID
+    } else {
+        c - C::MID
+    }
+}
+
+/// Rotate a circular channel.
+///
+/// * `c` Channel value.
+/// * `turns` Amount to rotate, in turns (`1.0` is 360 degrees).
+pub(crate) fn circ_rotate<C: Channel>(c: C, turns: f32) -> C {
+    let mut turns = turns.rem_euclid(1.0);
+    let mut c = c;
+    if turns >= 0.5 {
+        c = circ_half_turn(c);
+        turns -= 0.5;
+    }
+    let r = C::from(turns);
+    // MAX is the same angle as MIN, so wrap around it
+    if r > C::MAX - c {
+        c - (C::MAX - r)
+    } else {
+        c + r
     }
 }
 
@@ -328,16 +349,20 @@ where
     {
         let alpha = dst.alpha();
         let channels = dst.channels_mut();
-        convert_alpha_gamma::<D, S>(channels, alpha);
+        convert_alpha_gamma::<D, S, D::Chan>(channels, alpha);
     }
     dst
 }
 
-/// Convert *alpha* / *gamma* to another pixel format
-fn convert_alpha_gamma<D, S>(channels: &mut [D::Chan], alpha: D::Chan)
+/// Convert *alpha* / *gamma* to another pixel format.
+///
+/// * `channels` Channel values, in the color model of `D` and `S`.
+/// * `alpha` Value of *alpha* channel.
+pub(crate) fn convert_alpha_gamma<D, S, C>(channels: &mut [C], alpha: C)
 where
     D: Pixel,
     S: Pixel,
+    C: Channel,
 {
     for c in channels[D::Model::LINEAR].iter_mut() {
         *c = S::Gamma::to_linear(*c);
@@ -728,6 +753,7 @@ where
 
 #[cfg(test)]
 mod test {
+    use crate::chan::Ch32;
     use crate::el::*;
     use crate::gray::*;
     use crate::matte::*;
@@ -862,4 +888,11 @@ mod test {
             SRgba32::new(0.5, 1.0, 0.75, 0.75).convert(),
         );
     }
+
+    #[test]
+    fn circular() {
+        assert_eq!(circ_rotate(Ch32::new(0.75), 0.5), Ch32::new(0.25));
+        assert_eq!(circ_rotate(Ch32::new(0.75), 0.375), Ch32::new(0.125));
+        assert_eq!(circ_rotate(Ch32::new(0.25), -0.5), Ch32::new(0.75));
+    }
 }
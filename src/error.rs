@@ -0,0 +1,64 @@
+// error.rs     Error types.
+//
+// Copyright (c) 2023  Douglas P Lau
+//
+use std::fmt;
+
+/// Errors for fallible `Raster` / view operations.
+///
+/// These are returned by the *try_* methods, such as
+/// [Raster::try_with_pixels](struct.Raster.html#method.try_with_pixels).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// Width greater than `i32::MAX`
+    WidthTooBig,
+
+    /// Height greater than `i32::MAX`
+    HeightTooBig,
+
+    /// Width * height greater than `i32::MAX`
+    TooBig,
+
+    /// Buffer length does not match dimensions
+    LengthMismatch,
+
+    /// Buffer or stride not aligned for the pixel format
+    Misaligned,
+
+    /// Width or height is zero
+    ZeroSize,
+
+    /// Stride less than width of one row
+    StrideTooSmall,
+
+    /// Pixel location outside of bounds
+    OutOfBounds,
+
+    /// Chroma subsampling does not match frame layout
+    SubsamplingMismatch,
+
+    /// Look-up table data or dimensions invalid
+    InvalidLut,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::WidthTooBig => write!(f, "Raster width too big"),
+            Error::HeightTooBig => write!(f, "Raster height too big"),
+            Error::TooBig => write!(f, "Raster too big"),
+            Error::LengthMismatch => write!(f, "Buffer length mismatch"),
+            Error::Misaligned => write!(f, "Buffer misaligned"),
+            Error::ZeroSize => write!(f, "Raster size zero"),
+            Error::StrideTooSmall => write!(f, "Stride too small"),
+            Error::OutOfBounds => write!(f, "Pixel out of bounds"),
+            Error::SubsamplingMismatch => {
+                write!(f, "Chroma subsampling mismatch")
+            }
+            Error::InvalidLut => write!(f, "Invalid look-up table"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,1237 @@
+// filter.rs    Convolution filters
+//
+// Copyright (c) 2023  Douglas P Lau
+//
+//! Convolution filters.
+//!
+//! A [Kernel] or [Separable] kernel is applied to a `Raster` using
+//! [convolve] or [convolve_separable].
+//!
+//! Channels are accumulated as `f32`, with linear gamma and premultiplied
+//! *alpha*, then converted back to the pixel format.  Circular channels
+//! (*hue*) are accumulated as angles, so they wrap around correctly.
+//!
+//! [convolve]: ../struct.Raster.html#method.convolve
+//! [convolve_separable]: ../struct.Raster.html#method.convolve_separable
+//! [Kernel]: struct.Kernel.html
+//! [Separable]: struct.Separable.html
+use crate::chan::{Ch32, Ch8, Channel};
+use crate::el::Pixel;
+use crate::oklab::{lab_from_pixel, pixel_from_lab};
+use crate::raster::{Raster, Region};
+use crate::resize::{
+    from_work, raster_from_work, raster_to_work, work_index, work_len,
+};
+use crate::warp::Edge;
+use crate::ColorModel;
+use std::any::TypeId;
+
+/// Number of box blur passes to approximate a Gaussian
+const BOX_PASSES: usize = 3;
+
+/// Two-dimensional convolution kernel.
+///
+/// Both dimensions are odd, with the center element aligned to the pixel
+/// being filtered.
+///
+/// ### Sharpen an image
+/// ```
+/// use pix::filter::Kernel;
+/// use pix::gray::SGray8;
+/// use pix::{Edge, Raster};
+///
+/// let mut r = Raster::with_color(4, 4, SGray8::new(0x80));
+/// #[rustfmt::skip]
+/// let k = Kernel::new(3, 3, &[
+///      0.0, -1.0,  0.0,
+///     -1.0,  5.0, -1.0,
+///      0.0, -1.0,  0.0,
+/// ]);
+/// r.convolve((), &k, Edge::Clamp);
+/// assert_eq!(r.pixel(1, 1), SGray8::new(0x80));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Kernel {
+    width: usize,
+    height: usize,
+    weights: Vec<f32>,
+}
+
+/// Channels affected by a sharpening filter
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Channels {
+    /// All channels except *alpha*
+    #[default]
+    All,
+    /// Luminance only, using the [Oklab] *L* component.
+    ///
+    /// This avoids color fringing around edges.
+    ///
+    /// [Oklab]: ../oklab/struct.Oklab.html
+    Luminance,
+}
+
+/// Separable convolution kernel.
+///
+/// A row kernel is applied horizontally, followed by a column kernel
+/// vertically.  This is equivalent to a [Kernel] of their outer product,
+/// but much faster for large kernels.
+///
+/// [Kernel]: struct.Kernel.html
+#[derive(Clone, Debug, PartialEq)]
+pub struct Separable {
+    row: Vec<f32>,
+    column: Vec<f32>,
+}
+
+impl Kernel {
+    /// Create a new kernel.
+    ///
+    /// * `width` Kernel width (odd).
+    /// * `height` Kernel height (odd).
+    /// * `weights` Kernel weights, in row-major order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` or `height` is even, or if the length of `weights`
+    /// is not `width * height`.
+    pub fn new(width: usize, height: usize, weights: &[f32]) -> Self {
+        assert!(width % 2 == 1 && height % 2 == 1, "Kernel size not odd");
+        assert_eq!(weights.len(), width * height, "Kernel length mismatch");
+        Kernel {
+            width,
+            height,
+            weights: weights.to_vec(),
+        }
+    }
+
+    /// Create a box kernel, with all weights equal and summing to one.
+    ///
+    /// * `radius` Number of pixels on each side of the center.
+    pub fn with_box(radius: usize) -> Self {
+        let size = radius * 2 + 1;
+        let weight = 1.0 / (size * size) as f32;
+        Self::new(size, size, &vec![weight; size * size])
+    }
+
+    /// Get kernel width.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Get kernel height.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Get kernel weights, in row-major order.
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+
+    /// Normalize weights so that they sum to one.
+    ///
+    /// Kernels which sum to zero (such as edge detectors) are unchanged.
+    pub fn normalized(mut self) -> Self {
+        normalize(&mut self.weights);
+        self
+    }
+}
+
+impl Separable {
+    /// Create a new separable kernel.
+    ///
+    /// * `row` Horizontal kernel (odd length).
+    /// * `column` Vertical kernel (odd length).
+    ///
+    /// # Panics
+    ///
+    /// Panics if either kernel has an even length.
+    pub fn new(row: &[f32], column: &[f32]) -> Self {
+        assert!(row.len() % 2 == 1, "Kernel size not odd");
+        assert!(column.len() % 2 == 1, "Kernel size not odd");
+        Separable {
+            row: row.to_vec(),
+            column: column.to_vec(),
+        }
+    }
+
+    /// Create a separable Gaussian kernel.
+    ///
+    /// * `sigma` Standard deviation, in pixels.  The kernel radius is
+    ///   `ceil(sigma * 3)`.
+    pub fn with_gaussian(sigma: f32) -> Self {
+        let radius = (sigma * 3.0).ceil().max(0.0) as usize;
+        let mut k: Vec<f32> = (0..=radius * 2)
+            .map(|i| {
+                let x = i as f32 - radius as f32;
+                (-(x * x) / (2.0 * sigma * sigma)).exp()
+            })
+            .collect();
+        if sigma <= 0.0 {
+            k = vec![1.0];
+        }
+        normalize(&mut k);
+        Self::new(&k, &k)
+    }
+
+    /// Get the horizontal kernel.
+    pub fn row(&self) -> &[f32] {
+        &self.row
+    }
+
+    /// Get the vertical kernel.
+    pub fn column(&self) -> &[f32] {
+        &self.column
+    }
+
+    /// Get the equivalent two-dimensional kernel.
+    pub fn to_kernel(&self) -> Kernel {
+        let mut weights =
+            Vec::with_capacity(self.row.len() * self.column.len());
+        for c in &self.column {
+            weights.extend(self.row.iter().map(|r| r * c));
+        }
+        Kernel::new(self.row.len(), self.column.len(), &weights)
+    }
+}
+
+/// Normalize weights to sum to one
+fn normalize(weights: &mut [f32]) {
+    let sum: f32 = weights.iter().sum();
+    if sum.abs() > f32::EPSILON {
+        weights.iter_mut().for_each(|w| *w /= sum);
+    }
+}
+
+/// Working values for an entire raster
+#[derive(Clone)]
+pub(crate) struct Work {
+    /// Width in pixels
+    pub(crate) width: i32,
+    /// Height in pixels
+    pub(crate) height: i32,
+    /// Values per pixel
+    pub(crate) n: usize,
+    /// Working values
+    pub(crate) values: Vec<f32>,
+}
+
+impl Work {
+    /// Create working values from a raster
+    pub(crate) fn new<P: Pixel>(raster: &Raster<P>) -> Self {
+        Work {
+            width: raster.width() as i32,
+            height: raster.height() as i32,
+            n: work_len::<P>(),
+            values: raster_to_work(raster),
+        }
+    }
+
+    /// Get working values of one pixel, using an edge mode
+    pub(crate) fn get(&self, x: i32, y: i32, edge: Edge) -> Option<&[f32]> {
+        let x = edge.coord(x, self.width)?;
+        let y = edge.coord(y, self.height)?;
+        let i = (y * self.width + x) as usize * self.n;
+        Some(&self.values[i..i + self.n])
+    }
+}
+
+/// Box sizes for a Gaussian approximation, using repeated box blurs
+///
+/// See "Fast Almost-Gaussian Filtering" (Kovesi, 2010)
+fn gaussian_box_radii(sigma: f32) -> [usize; BOX_PASSES] {
+    let n = BOX_PASSES as f32;
+    let var = 12.0 * sigma * sigma;
+    let mut wl = (var / n + 1.0).sqrt().floor() as usize;
+    if wl % 2 == 0 {
+        wl = wl.saturating_sub(1);
+    }
+    let wl = wl.max(1);
+    let w = wl as f32;
+    let m = ((var - n * w * w - 4.0 * n * w - 3.0 * n) / (-4.0 * w - 4.0))
+        .round()
+        .max(0.0) as usize;
+    let mut radii = [0; BOX_PASSES];
+    for (i, r) in radii.iter_mut().enumerate() {
+        *r = if i < m { wl / 2 } else { wl / 2 + 1 };
+    }
+    radii
+}
+
+impl Work {
+    /// Apply box blur passes with a list of radii
+    fn box_blur(&mut self, radii: &[usize], edge: Edge) {
+        for radius in radii {
+            self.box_rows(*radius, edge);
+        }
+        for radius in radii {
+            self.box_columns(*radius, edge);
+        }
+    }
+
+    /// Get the mean of the four neighbors of each pixel
+    fn neighbor_mean(&self) -> Self {
+        let n = self.n;
+        let mut values = Vec::with_capacity(self.values.len());
+        let mut acc = vec![0.0; n];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                acc.fill(0.0);
+                for (nx, ny) in [(x, y - 1), (x - 1, y), (x + 1, y), (x, y + 1)]
+                {
+                    if let Some(v) = self.get(nx, ny, Edge::Clamp) {
+                        accumulate(&mut acc, v, 0.25);
+                    }
+                }
+                values.extend_from_slice(&acc);
+            }
+        }
+        Work {
+            width: self.width,
+            height: self.height,
+            n,
+            values,
+        }
+    }
+
+    /// Sharpen values by adding the difference from blurred values.
+    ///
+    /// * `blurred` Blurred working values.
+    /// * `amount` Multiplier for the difference.
+    /// * `threshold` Minimum difference to sharpen.
+    /// * `index` Working value index to sharpen, or `None` for all.
+    /// * `skip` Working value index to leave unchanged.
+    fn sharpen(
+        &mut self,
+        blurred: &Work,
+        (amount, threshold): (f32, f32),
+        index: Option<usize>,
+        skip: Option<usize>,
+    ) {
+        let n = self.n;
+        let pixels = self.values.chunks_exact_mut(n);
+        for (vals, bvals) in pixels.zip(blurred.values.chunks_exact(n)) {
+            for (i, (v, b)) in vals.iter_mut().zip(bvals).enumerate() {
+                if index.is_some_and(|idx| idx != i) || skip == Some(i) {
+                    continue;
+                }
+                let diff = *v - b;
+                if diff.abs() >= threshold {
+                    *v += diff * amount;
+                }
+            }
+        }
+    }
+
+    /// Box blur each row, using a running sum
+    fn box_rows(&mut self, radius: usize, edge: Edge) {
+        let (w, n) = (self.width, self.n);
+        let r = limit_radius(radius);
+        let inv = 1.0 / (r * 2 + 1) as f64;
+        let mut line = vec![0.0; w as usize * n];
+        let mut sums = vec![0.0f64; n];
+        for row in self.values.chunks_exact_mut(w as usize * n) {
+            line.copy_from_slice(row);
+            let value = |x: i64| {
+                let i = edge_coord(edge, x, w)? as usize * n;
+                Some(&line[i..i + n])
+            };
+            range_sum(&mut sums, (-r, r), w, edge, value);
+            for (x, out) in (0..).zip(row.chunks_exact_mut(n)) {
+                add_values(&mut sums, value(x + r), 1.0);
+                for (o, s) in out.iter_mut().zip(&sums) {
+                    *o = (s * inv) as f32;
+                }
+                add_values(&mut sums, value(x - r), -1.0);
+            }
+        }
+    }
+
+    /// Box blur each column, using running sums for an entire row
+    fn box_columns(&mut self, radius: usize, edge: Edge) {
+        let h = self.height;
+        let stride = self.width as usize * self.n;
+        if stride == 0 {
+            return;
+        }
+        let r = limit_radius(radius);
+        let inv = 1.0 / (r * 2 + 1) as f64;
+        let src = self.values.clone();
+        let value = |y: i64| {
+            let i = edge_coord(edge, y, h)? as usize * stride;
+            Some(&src[i..i + stride])
+        };
+        let mut sums = vec![0.0f64; stride];
+        range_sum(&mut sums, (-r, r), h, edge, value);
+        for (y, out) in (0..).zip(self.values.chunks_exact_mut(stride)) {
+            add_values(&mut sums, value(y + r), 1.0);
+            for (o, s) in out.iter_mut().zip(&sums) {
+                *o = (s * inv) as f32;
+            }
+            add_values(&mut sums, value(y - r), -1.0);
+        }
+    }
+}
+
+/// Limit a box radius, so that coordinates cannot overflow
+fn limit_radius(radius: usize) -> i64 {
+    radius.min(i32::MAX as usize) as i64
+}
+
+/// Get a sample coordinate within a length, for any position
+fn edge_coord(edge: Edge, i: i64, len: i32) -> Option<i32> {
+    let n = i64::from(len);
+    let i = match edge {
+        Edge::Clear | Edge::Clamp => i.clamp(-1, n),
+        Edge::Repeat | Edge::Mirror => i.rem_euclid(n * 2),
+    };
+    edge.coord(i as i32, len)
+}
+
+/// Set running sums to values within a range of positions.
+///
+/// The cost depends on `len`, not on the size of the range.
+fn range_sum<'a, F>(
+    sums: &mut [f64],
+    (start, end): (i64, i64),
+    len: i32,
+    edge: Edge,
+    value: F,
+) where
+    F: Fn(i64) -> Option<&'a [f32]>,
+{
+    let n = i64::from(len);
+    sums.fill(0.0);
+    match edge {
+        Edge::Clear | Edge::Clamp => {
+            for i in start.max(0)..end.min(n) {
+                add_values(sums, value(i), 1.0);
+            }
+            if edge == Edge::Clamp {
+                let below = end.min(0) - start.min(0);
+                let above = end.max(n) - start.max(n);
+                add_values(sums, value(-1), below as f64);
+                add_values(sums, value(n), above as f64);
+            }
+        }
+        Edge::Repeat | Edge::Mirror => {
+            let period = if edge == Edge::Repeat { n } else { n * 2 };
+            let tiles = (end - start) / period;
+            if tiles > 0 {
+                for i in 0..period {
+                    add_values(sums, value(i), tiles as f64);
+                }
+            }
+            for i in start + tiles * period..end {
+                add_values(sums, value(i), 1.0);
+            }
+        }
+    }
+}
+
+/// Add (or subtract) values to running sums
+fn add_values(sums: &mut [f64], values: Option<&[f32]>, sign: f64) {
+    if let Some(values) = values {
+        for (s, v) in sums.iter_mut().zip(values) {
+            *s += f64::from(*v) * sign;
+        }
+    }
+}
+
+/// Median filter one 8-bit plane, using column histograms.
+///
+/// See "Median Filtering in Constant Time" (Perreault & Hébert, 2007)
+///
+/// * `plane` Values of one channel for an entire raster.
+/// * `reg` Region to filter.
+/// * `radius` Number of pixels on each side of the center.
+///
+/// Returns median values within the region.
+fn median_plane(
+    plane: &[u8],
+    (width, height): (i32, i32),
+    reg: Region,
+    radius: i32,
+) -> Vec<u8> {
+    let at = |x: i32, y: i32| {
+        let x = x.clamp(0, width - 1);
+        let y = y.clamp(0, height - 1);
+        usize::from(plane[(y * width + x) as usize])
+    };
+    let x0 = (reg.left() - radius).max(0);
+    let x1 = (reg.right() + radius).min(width);
+    let mut columns = vec![[0u32; 256]; (x1 - x0) as usize];
+    for (x, col) in (x0..).zip(columns.iter_mut()) {
+        for y in reg.top() - radius..=reg.top() + radius {
+            col[at(x, y)] += 1;
+        }
+    }
+    let column = |x: i32| (x.clamp(x0, x1 - 1) - x0) as usize;
+    let half = (radius as u64 * 2 + 1).pow(2) / 2;
+    let mut values = Vec::with_capacity(reg.width() as usize);
+    for y in reg.top()..reg.bottom() {
+        if y > reg.top() {
+            for (x, col) in (x0..).zip(columns.iter_mut()) {
+                col[at(x, y - radius - 1)] -= 1;
+                col[at(x, y + radius)] += 1;
+            }
+        }
+        let mut hist = [0u64; 256];
+        for x in reg.left() - radius..=reg.left() + radius {
+            add_histogram(&mut hist, &columns[column(x)], true);
+        }
+        for x in reg.left()..reg.right() {
+            values.push(histogram_median(&hist, half));
+            add_histogram(&mut hist, &columns[column(x - radius)], false);
+            add_histogram(&mut hist, &columns[column(x + radius + 1)], true);
+        }
+    }
+    values
+}
+
+/// Add (or subtract) one histogram to another
+fn add_histogram(hist: &mut [u64; 256], other: &[u32; 256], add: bool) {
+    for (h, o) in hist.iter_mut().zip(other) {
+        if add {
+            *h += u64::from(*o);
+        } else {
+            *h -= u64::from(*o);
+        }
+    }
+}
+
+/// Find the median value of a histogram
+fn histogram_median(hist: &[u64; 256], half: u64) -> u8 {
+    let mut total = 0;
+    for (v, count) in (0..=255).zip(hist) {
+        total += count;
+        if total > half {
+            return v;
+        }
+    }
+    u8::MAX
+}
+
+/// Get squared distance between two Oklab colors
+fn oklab_distance_sq(p0: [f32; 3], p1: [f32; 3]) -> f32 {
+    p0.iter().zip(p1).map(|(c0, c1)| (c0 - c1).powi(2)).sum()
+}
+
+/// Add weighted values to an accumulator
+fn accumulate(acc: &mut [f32], values: &[f32], weight: f32) {
+    for (a, v) in acc.iter_mut().zip(values) {
+        *a += v * weight;
+    }
+}
+
+impl<P: Pixel> Raster<P> {
+    /// Apply a convolution [Kernel] to a region.
+    ///
+    /// * `reg` Region within `self`.  It can be a `Region` struct, tuple of
+    ///   (*x*, *y*, *width*, *height*) or the unit type `()`.  Using
+    ///   `()` has the same result as `Raster::region()`.
+    /// * `kernel` Convolution kernel.
+    /// * `edge` Edge mode, for pixels outside of the `Raster`.
+    ///
+    /// Pixels outside of `reg` (but within the `Raster`) are used as input,
+    /// but are not changed.
+    ///
+    /// [Kernel]: filter/struct.Kernel.html
+    pub fn convolve<R>(&mut self, reg: R, kernel: &Kernel, edge: Edge)
+    where
+        R: Into<Region>,
+    {
+        let reg = self.intersection(reg);
+        let work = Work::new(self);
+        let n = work.n;
+        let cx = (kernel.width / 2) as i32;
+        let cy = (kernel.height / 2) as i32;
+        let mut acc = vec![0.0; n];
+        for (y, row) in (reg.top()..).zip(self.rows_mut(reg)) {
+            for (x, p) in (reg.left()..).zip(row.iter_mut()) {
+                acc.fill(0.0);
+                let rows = kernel.weights.chunks_exact(kernel.width);
+                for (ky, weights) in (y - cy..).zip(rows) {
+                    for (kx, w) in (x - cx..).zip(weights) {
+                        if let Some(v) = work.get(kx, ky, edge) {
+                            accumulate(&mut acc, v, *w);
+                        }
+                    }
+                }
+                *p = from_work(&acc);
+            }
+        }
+    }
+
+    /// Apply a [Separable] convolution kernel to a region.
+    ///
+    /// * `reg` Region within `self`.  It can be a `Region` struct, tuple of
+    ///   (*x*, *y*, *width*, *height*) or the unit type `()`.  Using
+    ///   `()` has the same result as `Raster::region()`.
+    /// * `kernel` Separable convolution kernel.
+    /// * `edge` Edge mode, for pixels outside of the `Raster`.
+    ///
+    /// Pixels outside of `reg` (but within the `Raster`) are used as input,
+    /// but are not changed.
+    ///
+    /// ### Gaussian blur
+    /// ```
+    /// use pix::el::Pixel;
+    /// use pix::filter::Separable;
+    /// use pix::matte::Matte8;
+    /// use pix::{Edge, Raster};
+    ///
+    /// let mut r = Raster::with_clear(9, 9);
+    /// *r.pixel_mut(4, 4) = Matte8::new(0xFF);
+    /// r.convolve_separable((), &Separable::with_gaussian(1.0), Edge::Clear);
+    /// assert!(u8::from(r.pixel(4, 4).alpha()) < 0x80);
+    /// assert!(u8::from(r.pixel(4, 5).alpha()) > 0);
+    /// ```
+    ///
+    /// [Separable]: filter/struct.Separable.html
+    pub fn convolve_separable<R>(
+        &mut self,
+        reg: R,
+        kernel: &Separable,
+        edge: Edge,
+    ) where
+        R: Into<Region>,
+    {
+        let reg = self.intersection(reg);
+        if reg.width() == 0 || reg.height() == 0 {
+            return;
+        }
+        let work = Work::new(self);
+        let n = work.n;
+        let cx = (kernel.row.len() / 2) as i32;
+        let cy = (kernel.column.len() / 2) as i32;
+        // horizontal pass: all rows, only columns within region
+        let rw = reg.width() as usize;
+        let mut tmp = Work {
+            width: reg.width() as i32,
+            height: work.height,
+            n,
+            values: vec![0.0; rw * work.height as usize * n],
+        };
+        let lines = tmp.values.chunks_exact_mut(rw * n);
+        for (y, line) in (0..).zip(lines) {
+            for (x, acc) in (reg.left()..).zip(line.chunks_exact_mut(n)) {
+                for (kx, w) in (x - cx..).zip(&kernel.row) {
+                    if let Some(v) = work.get(kx, y, edge) {
+                        accumulate(acc, v, *w);
+                    }
+                }
+            }
+        }
+        // vertical pass: only region
+        let mut acc = vec![0.0; n];
+        for (y, row) in (reg.top()..).zip(self.rows_mut(reg)) {
+            for (x, p) in (0..).zip(row.iter_mut()) {
+                acc.fill(0.0);
+                for (ky, w) in (y - cy..).zip(&kernel.column) {
+                    if let Some(v) = tmp.get(x, ky, edge) {
+                        accumulate(&mut acc, v, *w);
+                    }
+                }
+                *p = from_work(&acc);
+            }
+        }
+    }
+
+    /// Blur the `Raster` with an approximate Gaussian.
+    ///
+    /// * `sigma` Standard deviation, in pixels.  If it is not positive and
+    ///   finite, the `Raster` is unchanged.
+    ///
+    /// Three box blur passes are used, so the cost does not depend on
+    /// `sigma`.  Pixels outside of the `Raster` are treated as clear.
+    ///
+    /// ### Drop shadow
+    /// ```
+    /// use pix::el::Pixel;
+    /// use pix::matte::Matte8;
+    /// use pix::Raster;
+    ///
+    /// let mut r = Raster::with_clear(32, 32);
+    /// r.copy_color((8, 8, 16, 16), Matte8::new(0xFF));
+    /// r.blur(3.0);
+    /// assert_eq!(r.pixel(16, 16), Matte8::new(0xFF));
+    /// let edge = u8::from(r.pixel(8, 16).alpha());
+    /// assert!(edge > 0x80 && edge < 0xA0);
+    /// ```
+    pub fn blur(&mut self, sigma: f32) {
+        if sigma > 0.0 && sigma.is_finite() {
+            self.box_blur_passes(&gaussian_box_radii(sigma));
+        }
+    }
+
+    /// Blur the `Raster` with a box filter.
+    ///
+    /// * `radius` Number of pixels on each side of the center.
+    ///
+    /// A running sum is used, so the cost does not depend on `radius`.
+    /// Pixels outside of the `Raster` are treated as clear.
+    pub fn box_blur(&mut self, radius: u32) {
+        self.box_blur_passes(&[radius as usize]);
+    }
+
+    /// Apply box blur passes with a list of radii
+    fn box_blur_passes(&mut self, radii: &[usize]) {
+        if radii.iter().all(|r| *r == 0) {
+            return;
+        }
+        let mut work = Work::new(self);
+        work.box_blur(radii, Edge::Clear);
+        *self = raster_from_work(self.width(), self.height(), &work.values);
+    }
+
+    /// Sharpen the `Raster` with an unsharp mask.
+    ///
+    /// * `amount` Strength, where `1.0` adds 100% of the difference from
+    ///   the blurred image.
+    /// * `radius` Blur standard deviation, in pixels.  If it is not positive
+    ///   and finite, the `Raster` is unchanged.
+    /// * `threshold` Minimum difference (from `0.0` to `1.0`) required to
+    ///   sharpen a pixel.  This avoids amplifying noise in smooth areas.
+    /// * `channels` Channels to sharpen.
+    ///
+    /// The blur uses [Edge::Clamp], so edges of the `Raster` are not
+    /// brightened.
+    ///
+    /// [Edge::Clamp]: enum.Edge.html#variant.Clamp
+    ///
+    /// ### Sharpen a thumbnail
+    /// ```
+    /// use pix::filter::Channels;
+    /// use pix::rgb::SRgb8;
+    /// use pix::Raster;
+    ///
+    /// let mut r = Raster::with_clear(8, 8);
+    /// r.copy_color((4, 0, 4, 8), SRgb8::new(0xC0, 0x80, 0x40));
+    /// r.unsharp_mask(0.5, 1.0, 0.0, Channels::Luminance);
+    /// assert_eq!(r.pixel(0, 0), SRgb8::new(0, 0, 0));
+    /// ```
+    pub fn unsharp_mask(
+        &mut self,
+        amount: f32,
+        radius: f32,
+        threshold: f32,
+        channels: Channels,
+    ) where
+        P::Chan: From<Ch32>,
+        Ch32: From<P::Chan>,
+    {
+        if radius > 0.0 && radius.is_finite() {
+            let radii = gaussian_box_radii(radius);
+            self.sharpen_work(channels, (amount, threshold), |work| {
+                let mut blurred = work.clone();
+                blurred.box_blur(&radii, Edge::Clamp);
+                blurred
+            });
+        }
+    }
+
+    /// Sharpen the `Raster` with a 3x3 kernel.
+    ///
+    /// * `channels` Channels to sharpen.
+    ///
+    /// This is equivalent to a convolution [Kernel] of
+    /// `[0, -1, 0, -1, 5, -1, 0, -1, 0]`, with [Edge::Clamp].
+    ///
+    /// [Edge::Clamp]: enum.Edge.html#variant.Clamp
+    /// [Kernel]: filter/struct.Kernel.html
+    pub fn sharpen(&mut self, channels: Channels)
+    where
+        P::Chan: From<Ch32>,
+        Ch32: From<P::Chan>,
+    {
+        self.sharpen_work(channels, (4.0, 0.0), Work::neighbor_mean);
+    }
+
+    /// Sharpen using a blur function on working values
+    fn sharpen_work<F>(
+        &mut self,
+        channels: Channels,
+        params: (f32, f32),
+        blur: F,
+    ) where
+        F: Fn(&Work) -> Work,
+        P::Chan: From<Ch32>,
+        Ch32: From<P::Chan>,
+    {
+        let (width, height) = (self.width(), self.height());
+        match channels {
+            Channels::All => {
+                let mut work = Work::new(self);
+                let alpha = work_index::<P>(P::Model::ALPHA);
+                work.sharpen(&blur(&work), params, None, alpha);
+                *self = raster_from_work(width, height, &work.values);
+            }
+            Channels::Luminance => {
+                let lab: Vec<([f32; 3], f32)> =
+                    self.pixels().iter().map(|p| lab_from_pixel(*p)).collect();
+                // premultiplied lightness
+                let values = lab.iter().map(|(lab, a)| lab[0] * a).collect();
+                let mut work = Work {
+                    width: width as i32,
+                    height: height as i32,
+                    n: 1,
+                    values,
+                };
+                work.sharpen(&blur(&work), params, Some(0), None);
+                let pixels = self.pixels_mut().iter_mut().zip(&work.values);
+                for ((p, l), ([_, a, b], alpha)) in pixels.zip(lab) {
+                    if alpha > 0.0 {
+                        *p = pixel_from_lab([l / alpha, a, b], alpha);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Apply a median filter to a region.
+    ///
+    /// * `reg` Region within `self`.  It can be a `Region` struct, tuple of
+    ///   (*x*, *y*, *width*, *height*) or the unit type `()`.  Using
+    ///   `()` has the same result as `Raster::region()`.
+    /// * `radius` Number of pixels on each side of the center.
+    ///
+    /// Each channel is filtered separately, with pixels outside of the
+    /// `Raster` clamped to the nearest edge.  For 8-bit channels, a
+    /// histogram method is used, so the cost does not depend on `radius`.
+    ///
+    /// ### Remove speckles
+    /// ```
+    /// use pix::gray::Gray8;
+    /// use pix::Raster;
+    ///
+    /// let mut r = Raster::with_color(5, 5, Gray8::new(0x40));
+    /// *r.pixel_mut(2, 2) = Gray8::new(0xFF);
+    /// r.median((), 1);
+    /// assert_eq!(r.pixel(2, 2), Gray8::new(0x40));
+    /// ```
+    pub fn median<R>(&mut self, reg: R, radius: u32)
+    where
+        R: Into<Region>,
+    {
+        let reg = self.intersection(reg);
+        if reg.width() == 0 || reg.height() == 0 || radius == 0 {
+            return;
+        }
+        // larger windows only repeat clamped edge pixels
+        let radius = radius.min(self.width().max(self.height()));
+        let r = radius as i32;
+        let n_chan = P::default().channels().len();
+        if TypeId::of::<P::Chan>() == TypeId::of::<Ch8>() {
+            let size = (self.width() as i32, self.height() as i32);
+            for i in 0..n_chan {
+                let plane: Vec<u8> = self
+                    .pixels()
+                    .iter()
+                    .map(|p| (p.channels()[i].to_f32() * 255.0).round() as u8)
+                    .collect();
+                let values = median_plane(&plane, size, reg, r);
+                let pixels = self.rows_mut(reg).flatten();
+                for (p, v) in pixels.zip(values) {
+                    p.channels_mut()[i] = P::Chan::from(f32::from(v) / 255.0);
+                }
+            }
+        } else {
+            let src = self.clone();
+            let (w, h) = (src.width() as i32, src.height() as i32);
+            let mut values = Vec::with_capacity((r as usize * 2 + 1).pow(2));
+            for (y, row) in (reg.top()..).zip(self.rows_mut(reg)) {
+                for (x, p) in (reg.left()..).zip(row.iter_mut()) {
+                    for (i, c) in p.channels_mut().iter_mut().enumerate() {
+                        values.clear();
+                        for wy in y - r..=y + r {
+                            for wx in x - r..=x + r {
+                                let sp = src.pixel(
+                                    wx.clamp(0, w - 1),
+                                    wy.clamp(0, h - 1),
+                                );
+                                values.push(sp.channels()[i]);
+                            }
+                        }
+                        let mid = values.len() / 2;
+                        *c = *values.select_nth_unstable(mid).1;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Apply a bilateral (edge-preserving) filter to a region.
+    ///
+    /// * `reg` Region within `self`.  It can be a `Region` struct, tuple of
+    ///   (*x*, *y*, *width*, *height*) or the unit type `()`.  Using
+    ///   `()` has the same result as `Raster::region()`.
+    /// * `sigma_space` Spatial standard deviation, in pixels.
+    /// * `sigma_range` Range standard deviation, as a distance in the
+    ///   [Oklab] color model.
+    ///
+    /// Neighboring pixels are weighted by both their distance and their
+    /// difference in color, so edges are not blurred.  A non-positive or
+    /// non-finite sigma leaves the Raster unchanged.
+    ///
+    /// [Oklab]: oklab/struct.Oklab.html
+    pub fn bilateral<R>(&mut self, reg: R, sigma_space: f32, sigma_range: f32)
+    where
+        R: Into<Region>,
+        Ch32: From<P::Chan>,
+    {
+        let reg = self.intersection(reg);
+        let valid = |sigma: f32| sigma > 0.0 && sigma.is_finite();
+        if !(valid(sigma_space) && valid(sigma_range)) {
+            return;
+        }
+        let max = self.width().max(self.height());
+        let r = (sigma_space * 3.0).ceil().min(max as f32) as i32;
+        let size = r as usize * 2 + 1;
+        let space = -0.5 / (sigma_space * sigma_space);
+        let range = -0.5 / (sigma_range * sigma_range);
+        let spatial: Vec<f32> = (-r..=r)
+            .flat_map(|dy| {
+                (-r..=r)
+                    .map(move |dx| ((dx * dx + dy * dy) as f32 * space).exp())
+            })
+            .collect();
+        let work = Work::new(self);
+        let lab: Vec<[f32; 3]> =
+            self.pixels().iter().map(|p| lab_from_pixel(*p).0).collect();
+        let lab_at = |x: i32, y: i32| lab[(y * work.width + x) as usize];
+        let mut acc = vec![0.0; work.n];
+        for (y, row) in (reg.top()..).zip(self.rows_mut(reg)) {
+            for (x, p) in (reg.left()..).zip(row.iter_mut()) {
+                let center = lab_at(x, y);
+                let mut total = 0.0;
+                acc.fill(0.0);
+                for (wy, weights) in (y - r..).zip(spatial.chunks_exact(size)) {
+                    for (wx, ws) in (x - r..).zip(weights) {
+                        if let Some(v) = work.get(wx, wy, Edge::Clear) {
+                            let d = oklab_distance_sq(center, lab_at(wx, wy));
+                            let weight = ws * (d * range).exp();
+                            accumulate(&mut acc, v, weight);
+                            total += weight;
+                        }
+                    }
+                }
+                acc.iter_mut().for_each(|a| *a /= total);
+                *p = from_work(&acc);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gray::*;
+    use crate::hsl::*;
+    use crate::matte::*;
+    use crate::rgb::*;
+
+    #[test]
+    fn identity() {
+        let mut r = Raster::<SRgba8>::with_clear(3, 2);
+        *r.pixel_mut(1, 1) = SRgba8::new(0x12, 0x34, 0x56, 0x78);
+        let r0 = r.clone();
+        let k = Kernel::new(1, 3, &[0.0, 1.0, 0.0]);
+        r.convolve((), &k, Edge::Clear);
+        assert_eq!(r.pixels(), r0.pixels());
+        r.convolve_separable((), &Separable::new(&[1.0], &[1.0]), Edge::Clear);
+        assert_eq!(r.pixels(), r0.pixels());
+    }
+
+    #[test]
+    fn box_edges() {
+        let mut r = Raster::<Gray8>::with_clear(3, 1);
+        *r.pixel_mut(0, 0) = Gray8::new(0x90);
+        let k = Kernel::new(3, 1, &[1.0, 1.0, 1.0]).normalized();
+        let mut c = r.clone();
+        c.convolve((), &k, Edge::Clear);
+        assert_eq!(c.as_u8_slice(), &[0x30, 0x30, 0]);
+        let mut c = r.clone();
+        c.convolve((), &k, Edge::Clamp);
+        assert_eq!(c.as_u8_slice(), &[0x60, 0x30, 0]);
+        let mut c = r.clone();
+        c.convolve((), &k, Edge::Repeat);
+        assert_eq!(c.as_u8_slice(), &[0x30, 0x30, 0x30]);
+        let mut c = r.clone();
+        c.convolve((), &k, Edge::Mirror);
+        assert_eq!(c.as_u8_slice(), &[0x60, 0x30, 0]);
+        let mut c = r.clone();
+        c.convolve((1, 0, 2, 1), &k, Edge::Clamp);
+        assert_eq!(c.as_u8_slice(), &[0x90, 0x30, 0]);
+    }
+
+    #[test]
+    fn separable_matches_2d() {
+        let mut r = Raster::<Gray16>::with_clear(7, 5);
+        for (i, p) in r.pixels_mut().iter_mut().enumerate() {
+            *p = Gray16::new((i * 1723 % 65536) as u16);
+        }
+        let s = Separable::new(&[0.25, 0.5, 0.25], &[0.1, 0.2, 0.4, 0.2, 0.1]);
+        let mut a = r.clone();
+        a.convolve((1, 1, 5, 3), &s.to_kernel(), Edge::Mirror);
+        let mut b = r.clone();
+        b.convolve_separable((1, 1, 5, 3), &s, Edge::Mirror);
+        for (pa, pb) in a.pixels().iter().zip(b.pixels()) {
+            let (va, vb) = (u16::from(pa.one()), u16::from(pb.one()));
+            assert!(va.abs_diff(vb) <= 1);
+        }
+    }
+
+    #[test]
+    fn gaussian() {
+        let s = Separable::with_gaussian(1.0);
+        assert_eq!(s.row().len(), 7);
+        let sum: f32 = s.row().iter().sum();
+        assert!((sum - 1.0).abs() < 1e-6);
+        assert_eq!(Separable::with_gaussian(0.0).row(), &[1.0]);
+    }
+
+    #[test]
+    fn circular_hue() {
+        let mut r = Raster::<Hsl8>::with_clear(2, 1);
+        *r.pixel_mut(0, 0) = Hsl8::new(0xF7, 0xFF, 0x80);
+        *r.pixel_mut(1, 0) = Hsl8::new(0x08, 0xFF, 0x80);
+        let k = Kernel::new(3, 1, &[0.5, 0.5, 0.0]);
+        r.convolve((1, 0, 1, 1), &k, Edge::Clamp);
+        let h = u8::from(r.pixel(1, 0).one());
+        assert!(h == 0 || h == 0xFF);
+    }
+
+    #[test]
+    fn box_blur() {
+        let mut r = Raster::<Matte8>::with_clear(5, 1);
+        *r.pixel_mut(2, 0) = Matte8::new(0xF0);
+        r.box_blur(1);
+        assert_eq!(r.as_u8_slice(), &[0, 0x1B, 0x1B, 0x1B, 0]);
+        let mut r = Raster::<Gray16>::with_color(5, 4, Gray16::new(0x1234));
+        r.box_blur(0);
+        assert_eq!(r.pixel(0, 0), Gray16::new(0x1234));
+        r.box_blur(1);
+        assert_eq!(r.pixel(2, 1), Gray16::new(0x1234));
+        assert_eq!(r.pixel(2, 0), Gray16::new(0x0C23));
+    }
+
+    #[test]
+    fn range_sums() {
+        let values: Vec<f32> = (0..5).map(|i| (i * i) as f32).collect();
+        for edge in [Edge::Clear, Edge::Clamp, Edge::Repeat, Edge::Mirror] {
+            let value = |i: i64| {
+                let i = edge_coord(edge, i, 5)? as usize;
+                Some(&values[i..i + 1])
+            };
+            for (start, end) in [(-3, 3), (-12, 12), (-40, 1), (2, 31)] {
+                let mut sums = [0.0];
+                range_sum(&mut sums, (start, end), 5, edge, value);
+                let mut expected = 0.0;
+                for i in start..end {
+                    let v = edge
+                        .coord(i as i32, 5)
+                        .map_or(0.0, |c| values[c as usize]);
+                    expected += f64::from(v);
+                }
+                assert_eq!(sums[0], expected, "{edge:?} {start}..{end}");
+            }
+        }
+    }
+
+    #[test]
+    fn blur_large_radius() {
+        let mut r = Raster::<Matte8>::with_color(64, 64, Matte8::new(0xFF));
+        r.blur(f32::INFINITY);
+        r.blur(f32::NAN);
+        assert_eq!(r.pixel(0, 0), Matte8::new(0xFF));
+        r.blur(1.0e5);
+        assert_eq!(r.pixel(32, 32), Matte8::new(0));
+        let mut r = Raster::<Matte32>::with_color(64, 64, Matte32::new(1.0));
+        r.box_blur(1_000_000);
+        let expected = (64.0 / 2_000_001.0) * (64.0 / 2_000_001.0);
+        let v = r.pixel(10, 20).alpha().to_f32();
+        assert!((v - expected).abs() < 1e-9);
+        let mut r = Raster::<Matte8>::with_color(8, 8, Matte8::new(0x80));
+        r.unsharp_mask(1.0, f32::INFINITY, 0.0, Channels::All);
+        r.unsharp_mask(1.0, 1.0e6, 0.0, Channels::All);
+        assert_eq!(r.pixel(3, 3), Matte8::new(0x80));
+    }
+
+    #[test]
+    fn blur_matches_gaussian() {
+        let mut r = Raster::<Gray8>::with_clear(41, 41);
+        *r.pixel_mut(20, 20) = Gray8::new(0xFF);
+        r.convolve_separable((), &Separable::with_gaussian(0.5), Edge::Clear);
+        let mut a = r.clone();
+        a.blur(4.0);
+        let mut b = r.clone();
+        b.convolve_separable((), &Separable::with_gaussian(4.0), Edge::Clear);
+        let sa: u32 = a.as_u8_slice().iter().map(|v| u32::from(*v)).sum();
+        let sb: u32 = b.as_u8_slice().iter().map(|v| u32::from(*v)).sum();
+        assert!(sa.abs_diff(sb) < sb / 10);
+        for (pa, pb) in a.as_u8_slice().iter().zip(b.as_u8_slice()) {
+            assert!(pa.abs_diff(*pb) <= 1);
+        }
+    }
+
+    #[test]
+    fn blur_no_halo() {
+        let mut r = Raster::<SRgba8>::with_clear(9, 1);
+        *r.pixel_mut(4, 0) = SRgba8::new(0xFF, 0x40, 0x00, 0xFF);
+        r.blur(1.0);
+        let p = r.pixel(3, 0);
+        assert!(u8::from(p.alpha()) < 0xFF);
+        assert_eq!(u8::from(p.one()), 0xFF);
+        assert_eq!(u8::from(p.two()), 0x40);
+        let mut r = Raster::<Rgba8p>::with_clear(4, 4);
+        r.blur(2.0);
+        assert_eq!(r.pixel(1, 1), Rgba8p::default());
+    }
+
+    #[test]
+    fn sharpen_matches_kernel() {
+        let mut r = Raster::<Gray8>::with_clear(6, 5);
+        for (i, p) in r.pixels_mut().iter_mut().enumerate() {
+            *p = Gray8::new::<u8>((i * 37 % 256) as u8);
+        }
+        #[rustfmt::skip]
+        let k = Kernel::new(3, 3, &[
+             0.0, -1.0,  0.0,
+            -1.0,  5.0, -1.0,
+             0.0, -1.0,  0.0,
+        ]);
+        let mut a = r.clone();
+        a.convolve((), &k, Edge::Clamp);
+        r.sharpen(Channels::All);
+        for (pa, pb) in a.as_u8_slice().iter().zip(r.as_u8_slice()) {
+            assert!(pa.abs_diff(*pb) <= 1);
+        }
+    }
+
+    #[test]
+    fn unsharp_threshold() {
+        let mut r = Raster::<Gray8>::with_clear(8, 1);
+        for (x, v) in [0x40, 0x41, 0x40, 0x41, 0xC0, 0xC0, 0xC0, 0xC0]
+            .iter()
+            .enumerate()
+        {
+            *r.pixel_mut(x as i32, 0) = Gray8::new::<u8>(*v);
+        }
+        let mut a = r.clone();
+        a.unsharp_mask(1.0, 1.0, 0.1, Channels::All);
+        assert_eq!(a.pixel(0, 0), Gray8::new(0x40));
+        assert_eq!(a.pixel(1, 0), Gray8::new(0x41));
+        assert!(u8::from(a.pixel(3, 0).one()) < 0x41);
+        assert!(u8::from(a.pixel(4, 0).one()) > 0xC0);
+        let mut b = r.clone();
+        b.unsharp_mask(1.0, 0.0, 0.0, Channels::All);
+        assert_eq!(b.pixels(), r.pixels());
+    }
+
+    #[test]
+    fn sharpen_luminance() {
+        let mut r =
+            Raster::<SRgb8>::with_color(6, 6, SRgb8::new(0x20, 0x20, 0x20));
+        r.copy_color((3, 0, 3, 6), SRgb8::new(0xB0, 0xB0, 0xB0));
+        r.unsharp_mask(1.0, 1.0, 0.0, Channels::Luminance);
+        for p in r.pixels() {
+            let (red, green, blue) =
+                (u8::from(p.one()), u8::from(p.two()), u8::from(p.three()));
+            assert!(red.abs_diff(green) <= 1 && red.abs_diff(blue) <= 1);
+        }
+        assert!(u8::from(r.pixel(2, 3).one()) < 0x20);
+        assert!(u8::from(r.pixel(3, 3).one()) > 0xB0);
+        let mut r = Raster::<SRgba8>::with_color(4, 4, SRgba8::new(0, 0, 0, 0));
+        r.sharpen(Channels::Luminance);
+        assert_eq!(r.pixel(1, 1), SRgba8::new(0, 0, 0, 0));
+        for c in [SRgb8::new(0x20, 0xC0, 0x40), SRgb8::new(0x20, 0x40, 0xC0)] {
+            let mut r = Raster::<SRgb8>::with_color(4, 4, c);
+            r.sharpen(Channels::Luminance);
+            r.unsharp_mask(1.0, 1.0, 0.0, Channels::Luminance);
+            assert_eq!(r.pixels(), &[c; 16]);
+        }
+    }
+
+    #[test]
+    fn median_histogram() {
+        let mut r = Raster::<Gray8>::with_clear(9, 7);
+        for (i, p) in r.pixels_mut().iter_mut().enumerate() {
+            *p = Gray8::new::<u8>((i * 97 % 256) as u8);
+        }
+        let mut r16 = Raster::<Gray16>::with_raster(&r);
+        r.median((1, 2, 6, 4), 2);
+        r16.median((1, 2, 6, 4), 2);
+        assert_eq!(r.pixels(), Raster::<Gray8>::with_raster(&r16).pixels());
+        assert_eq!(r.pixel(0, 0), Gray8::new(0));
+        assert_eq!(r.pixel(1, 1), Gray8::new(0xCA));
+    }
+
+    #[test]
+    fn median_channels() {
+        let mut r =
+            Raster::<Rgb8>::with_color(3, 3, Rgb8::new(0x10, 0x20, 0x30));
+        *r.pixel_mut(0, 0) = Rgb8::new(0xFF, 0, 0);
+        *r.pixel_mut(2, 2) = Rgb8::new(0, 0xFF, 0);
+        r.median((), 1);
+        for p in r.pixels() {
+            assert_eq!(*p, Rgb8::new(0x10, 0x20, 0x30));
+        }
+    }
+
+    #[test]
+    fn median_large_radius() {
+        let mut r = Raster::<Gray8>::with_clear(3, 2);
+        for (i, p) in r.pixels_mut().iter_mut().enumerate() {
+            *p = Gray8::new::<u8>((i * 40) as u8);
+        }
+        let mut r16 = Raster::<Gray16>::with_raster(&r);
+        let mut small = Raster::<Gray8>::with_raster(&r);
+        small.median((), 3);
+        r.median((), 40000);
+        r16.median((), u32::MAX);
+        assert_eq!(r.pixels(), small.pixels());
+        assert_eq!(r.pixels(), Raster::<Gray8>::with_raster(&r16).pixels());
+    }
+
+    #[test]
+    fn bilateral_edges() {
+        let mut r =
+            Raster::<SRgb8>::with_color(8, 4, SRgb8::new(0x20, 0x20, 0x20));
+        r.copy_color((4, 0, 4, 4), SRgb8::new(0xE0, 0xE0, 0xE0));
+        *r.pixel_mut(1, 1) = SRgb8::new(0x22, 0x20, 0x20);
+        r.bilateral((0, 0, 8, 3), 1.5, 0.05);
+        assert_eq!(r.pixel(3, 1), SRgb8::new(0x20, 0x20, 0x20));
+        assert_eq!(r.pixel(4, 1), SRgb8::new(0xE0, 0xE0, 0xE0));
+        let red = u8::from(r.pixel(1, 1).one());
+        assert!(red == 0x20 || red == 0x21);
+        r.bilateral((), 1.0, 0.0);
+        assert_eq!(r.pixel(4, 1), SRgb8::new(0xE0, 0xE0, 0xE0));
+        let r0 = r.clone();
+        r.bilateral((), f32::INFINITY, 0.05);
+        r.bilateral((), f32::NAN, 0.05);
+        r.bilateral((), 1.0, f32::NAN);
+        assert_eq!(r.pixels(), r0.pixels());
+        r.bilateral((), 1e4, 0.05);
+        assert_eq!(r.pixel(3, 1), SRgb8::new(0x20, 0x20, 0x20));
+        assert_eq!(r.pixel(4, 1), SRgb8::new(0xE0, 0xE0, 0xE0));
+    }
+
+    #[test]
+    fn bilateral_negative_lab() {
+        let teal = SRgb8::new(0x20, 0x60, 0x60);
+        let blue = SRgb8::new(0x20, 0x20, 0xC0);
+        let mut r = Raster::<SRgb8>::with_color(8, 4, teal);
+        r.copy_color((4, 0, 4, 4), blue);
+        r.bilateral((), 1.5, 0.02);
+        assert_eq!(r.pixel(3, 1), teal);
+        assert_eq!(r.pixel(4, 1), blue);
+    }
+
+    #[test]
+    #[should_panic]
+    fn even_kernel() {
+        let _ = Kernel::new(2, 1, &[0.5, 0.5]);
+    }
+}
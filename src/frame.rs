@@ -0,0 +1,692 @@
+// frame.rs     Chroma-subsampled YCbCr frames.
+//
+// Copyright (c) 2023  Douglas P Lau
+//
+use crate::chan::Ch8;
+use crate::el::Pixel;
+use crate::gray::Gray8;
+use crate::raster::Raster;
+use crate::rgb::SRgb8;
+use crate::view::RasterMut;
+use crate::Error;
+
+/// Chroma subsampling of a [YCbCrFrame](struct.YCbCrFrame.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Subsampling {
+    /// 4:4:4 — chroma at full resolution
+    S444,
+    /// 4:2:2 — chroma at half horizontal resolution
+    S422,
+    /// 4:2:0 — chroma at half horizontal and vertical resolution
+    S420,
+}
+
+/// Location of chroma samples relative to luma samples.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChromaSiting {
+    /// Centered between luma samples (JPEG, MPEG-1)
+    #[default]
+    Center,
+    /// Co-sited horizontally, centered vertically (MPEG-2, H.264)
+    Left,
+    /// Co-sited horizontally and vertically (BT.2020)
+    TopLeft,
+}
+
+/// Filter for resampling chroma.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChromaFilter {
+    /// Duplicate / drop chroma samples
+    Nearest,
+    /// Average neighboring samples when subsampling, and interpolate
+    /// linearly when upsampling
+    #[default]
+    Average,
+}
+
+/// Options for resampling chroma.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChromaOptions {
+    /// Location of chroma samples
+    pub siting: ChromaSiting,
+    /// Resampling filter
+    pub filter: ChromaFilter,
+}
+
+/// Byte layout of a YCbCr frame buffer.
+///
+/// These are commonly known as *YUV* formats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameLayout {
+    /// 4:2:0 planar: Y plane, then Cb plane, then Cr plane
+    I420,
+    /// 4:2:0 semi-planar: Y plane, then interleaved Cb / Cr plane
+    Nv12,
+    /// 4:2:2 packed: Y₀ Cb Y₁ Cr
+    Yuyv,
+    /// 4:2:2 packed: Cb Y₀ Cr Y₁
+    Uyvy,
+}
+
+/// Chroma-subsampled 8-bit [YCbCr](struct.YCbCr.html) frame.
+///
+/// The *y*, *Cb* and *Cr* components are each stored in a separate plane.
+/// Chroma planes are smaller than the luma plane, depending on
+/// [Subsampling].  Odd dimensions are rounded up for chroma planes.
+///
+/// Components are full range with chroma centered at 128, computed from
+/// [sRGB](../chan/struct.Srgb.html) gamma-encoded values, as in JPEG.
+///
+/// ### Convert an I420 buffer to a `Raster`
+/// ```
+/// use pix::rgb::SRgb8;
+/// use pix::ycc::{ChromaOptions, FrameLayout, YCbCrFrame};
+/// use pix::Raster;
+///
+/// let mut buf = vec![0x80; 4 * 2 + 2 * 2];
+/// buf[..8].fill(0xFF);
+/// let frame = YCbCrFrame::with_bytes(FrameLayout::I420, 4, 2, &buf)
+///     .unwrap();
+/// let r: Raster<SRgb8> = frame.to_raster(ChromaOptions::default());
+/// assert_eq!(r.pixel(3, 1), SRgb8::new(0xFF, 0xFF, 0xFF));
+/// ```
+///
+/// ### Convert a `Raster` to an NV12 buffer
+/// ```
+/// use pix::rgb::Rgba8;
+/// use pix::ycc::{ChromaOptions, FrameLayout, Subsampling, YCbCrFrame};
+/// use pix::Raster;
+///
+/// let r = Raster::with_color(6, 4, Rgba8::new(0, 0, 0, 0xFF));
+/// let frame = YCbCrFrame::with_raster(
+///     &r,
+///     Subsampling::S420,
+///     ChromaOptions::default(),
+/// );
+/// let buf = frame.to_bytes(FrameLayout::Nv12).unwrap();
+/// assert_eq!(buf.len(), 6 * 4 + 3 * 2 * 2);
+/// ```
+///
+/// [Subsampling]: enum.Subsampling.html
+#[derive(Clone)]
+pub struct YCbCrFrame {
+    subsampling: Subsampling,
+    y: Raster<Gray8>,
+    cb: Raster<Gray8>,
+    cr: Raster<Gray8>,
+}
+
+impl Subsampling {
+    /// Get dimensions of chroma planes.
+    fn chroma_size(self, width: u32, height: u32) -> (u32, u32) {
+        match self {
+            Subsampling::S444 => (width, height),
+            Subsampling::S422 => (width.div_ceil(2), height),
+            Subsampling::S420 => (width.div_ceil(2), height.div_ceil(2)),
+        }
+    }
+}
+
+impl ChromaSiting {
+    /// Check if chroma is co-sited horizontally
+    fn h_cosited(self) -> bool {
+        matches!(self, ChromaSiting::Left | ChromaSiting::TopLeft)
+    }
+
+    /// Check if chroma is co-sited vertically
+    fn v_cosited(self) -> bool {
+        matches!(self, ChromaSiting::TopLeft)
+    }
+}
+
+impl FrameLayout {
+    /// Get the chroma subsampling of a layout.
+    pub fn subsampling(self) -> Subsampling {
+        match self {
+            FrameLayout::I420 | FrameLayout::Nv12 => Subsampling::S420,
+            FrameLayout::Yuyv | FrameLayout::Uyvy => Subsampling::S422,
+        }
+    }
+
+    /// Get the buffer length of a layout, in bytes.
+    fn len(self, width: u32, height: u32) -> Option<usize> {
+        let (cw, ch) = self.subsampling().chroma_size(width, height);
+        let luma = (width as usize).checked_mul(height as usize)?;
+        let chroma = (cw as usize).checked_mul(ch as usize)?;
+        match self {
+            FrameLayout::I420 | FrameLayout::Nv12 => {
+                luma.checked_add(chroma.checked_mul(2)?)
+            }
+            FrameLayout::Yuyv | FrameLayout::Uyvy => chroma.checked_mul(4),
+        }
+    }
+}
+
+/// Convert gamma-encoded *red*, *green* and *blue* to YCbCr
+fn ycc_from_srgb(p: SRgb8) -> [u8; 3] {
+    let red = f32::from(u8::from(p.one()));
+    let green = f32::from(u8::from(p.two()));
+    let blue = f32::from(u8::from(p.three()));
+    let y = (0.299 * red) + (0.587 * green) + (0.114 * blue);
+    let cb = 128.0 - (0.168_736 * red) - (0.331_264 * green) + (0.5 * blue);
+    let cr = 128.0 + (0.5 * red) - (0.418_688 * green) - (0.081_312 * blue);
+    [y, cb, cr].map(|c| c.round().clamp(0.0, 255.0) as u8)
+}
+
+/// Convert YCbCr to gamma-encoded *red*, *green* and *blue*
+fn srgb_from_ycc(y: u8, cb: u8, cr: u8) -> SRgb8 {
+    let y = f32::from(y);
+    let cb = f32::from(cb) - 128.0;
+    let cr = f32::from(cr) - 128.0;
+    let red = y + cr * 1.402;
+    let green = y - cb * 0.344_136 - cr * 0.714_136;
+    let blue = y + cb * 1.772;
+    let [r, g, b] =
+        [red, green, blue].map(|c| c.round().clamp(0.0, 255.0) as u8);
+    SRgb8::new(r, g, b)
+}
+
+/// Subsample one line of chroma by half
+fn downsample_line(
+    src: &[u8],
+    dst: &mut [u8],
+    cosited: bool,
+    filter: ChromaFilter,
+) {
+    let last = src.len() - 1;
+    for (j, d) in dst.iter_mut().enumerate() {
+        let i = j * 2;
+        let c = u16::from(src[i]);
+        let n = u16::from(src[(i + 1).min(last)]);
+        *d = match (filter, cosited) {
+            (ChromaFilter::Nearest, _) => src[i],
+            (ChromaFilter::Average, false) => ((c + n + 1) >> 1) as u8,
+            (ChromaFilter::Average, true) => {
+                let p = u16::from(src[i.saturating_sub(1)]);
+                ((p + c * 2 + n + 2) >> 2) as u8
+            }
+        };
+    }
+}
+
+/// Upsample one line of chroma by double
+fn upsample_line(
+    src: &[u8],
+    dst: &mut [u8],
+    cosited: bool,
+    filter: ChromaFilter,
+) {
+    let last = src.len() - 1;
+    for (i, d) in dst.iter_mut().enumerate() {
+        let j = i / 2;
+        let c = u16::from(src[j]);
+        *d = match (filter, cosited, i & 1) {
+            (ChromaFilter::Nearest, _, _) => src[j],
+            (ChromaFilter::Average, true, 0) => src[j],
+            (ChromaFilter::Average, true, _) => {
+                let n = u16::from(src[(j + 1).min(last)]);
+                ((c + n + 1) >> 1) as u8
+            }
+            (ChromaFilter::Average, false, 0) => {
+                let p = u16::from(src[j.saturating_sub(1)]);
+                ((c * 3 + p + 2) >> 2) as u8
+            }
+            (ChromaFilter::Average, false, _) => {
+                let n = u16::from(src[(j + 1).min(last)]);
+                ((c * 3 + n + 2) >> 2) as u8
+            }
+        };
+    }
+}
+
+/// Resample one chroma plane to new dimensions (half or double)
+fn resample_plane(
+    src: &Raster<Gray8>,
+    width: u32,
+    height: u32,
+    opts: ChromaOptions,
+) -> Raster<Gray8> {
+    let (sw, sh) = (src.width() as usize, src.height() as usize);
+    let (dw, dh) = (width as usize, height as usize);
+    if sw == 0 || dw == 0 {
+        return Raster::with_clear(width, height);
+    }
+    let src = src.as_u8_slice();
+    let cosited = opts.siting.h_cosited();
+    let mut tmp = vec![0; dw * sh];
+    for (s, d) in src.chunks_exact(sw).zip(tmp.chunks_exact_mut(dw)) {
+        if dw < sw {
+            downsample_line(s, d, cosited, opts.filter);
+        } else if dw > sw {
+            upsample_line(s, d, cosited, opts.filter);
+        } else {
+            d.copy_from_slice(s);
+        }
+    }
+    let mut dst = Raster::with_clear(width, height);
+    let buf = dst.as_u8_slice_mut();
+    if dh == sh {
+        buf.copy_from_slice(&tmp);
+        return dst;
+    }
+    let cosited = opts.siting.v_cosited();
+    let mut s = vec![0; sh];
+    let mut d = vec![0; dh];
+    for x in 0..dw {
+        for (y, v) in s.iter_mut().enumerate() {
+            *v = tmp[y * dw + x];
+        }
+        if dh < sh {
+            downsample_line(&s, &mut d, cosited, opts.filter);
+        } else {
+            upsample_line(&s, &mut d, cosited, opts.filter);
+        }
+        for (y, v) in d.iter().enumerate() {
+            buf[y * dw + x] = *v;
+        }
+    }
+    dst
+}
+
+impl YCbCrFrame {
+    /// Construct a black `YCbCrFrame`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` or `height` is greater than `std::i32::MAX`.
+    pub fn with_clear(
+        width: u32,
+        height: u32,
+        subsampling: Subsampling,
+    ) -> Self {
+        let (cw, ch) = subsampling.chroma_size(width, height);
+        YCbCrFrame {
+            subsampling,
+            y: Raster::with_clear(width, height),
+            cb: Raster::with_color(cw, ch, Gray8::new(0x80)),
+            cr: Raster::with_color(cw, ch, Gray8::new(0x80)),
+        }
+    }
+
+    /// Construct a `YCbCrFrame` from a `Raster`.
+    ///
+    /// The *alpha* channel, if any, is discarded.
+    ///
+    /// * `src` Source raster.
+    /// * `subsampling` Chroma subsampling of frame.
+    /// * `opts` Chroma resampling options.
+    pub fn with_raster<P>(
+        src: &Raster<P>,
+        subsampling: Subsampling,
+        opts: ChromaOptions,
+    ) -> Self
+    where
+        P: Pixel,
+        Ch8: From<P::Chan>,
+    {
+        let (width, height) = (src.width(), src.height());
+        let mut y = Raster::with_clear(width, height);
+        let mut cb = Raster::with_clear(width, height);
+        let mut cr = Raster::with_clear(width, height);
+        for (i, p) in src.pixels().iter().enumerate() {
+            let [luma, blue, red] = ycc_from_srgb(p.convert());
+            y.pixels_mut()[i] = Gray8::new::<u8>(luma);
+            cb.pixels_mut()[i] = Gray8::new::<u8>(blue);
+            cr.pixels_mut()[i] = Gray8::new::<u8>(red);
+        }
+        let (cw, ch) = subsampling.chroma_size(width, height);
+        if (cw, ch) != (width, height) {
+            cb = resample_plane(&cb, cw, ch, opts);
+            cr = resample_plane(&cr, cw, ch, opts);
+        }
+        YCbCrFrame {
+            subsampling,
+            y,
+            cb,
+            cr,
+        }
+    }
+
+    /// Construct a `YCbCrFrame` from a byte buffer.
+    ///
+    /// * `layout` Byte layout of buffer.
+    /// * `width` Width of frame.
+    /// * `height` Height of frame.
+    /// * `buf` Buffer of frame data.  For packed layouts, each row contains
+    ///   `width` rounded up to a multiple of 2 pixels.
+    ///
+    /// # Errors
+    ///
+    /// * [Error::WidthTooBig], [Error::HeightTooBig] or [Error::TooBig] if
+    ///   dimensions are too big.
+    /// * [Error::LengthMismatch] if `buf` length does not match `layout`.
+    ///
+    /// [Error::HeightTooBig]: ../enum.Error.html#variant.HeightTooBig
+    /// [Error::LengthMismatch]: ../enum.Error.html#variant.LengthMismatch
+    /// [Error::TooBig]: ../enum.Error.html#variant.TooBig
+    /// [Error::WidthTooBig]: ../enum.Error.html#variant.WidthTooBig
+    pub fn with_bytes(
+        layout: FrameLayout,
+        width: u32,
+        height: u32,
+        buf: &[u8],
+    ) -> Result<Self, Error> {
+        let w = i32::try_from(width).map_err(|_| Error::WidthTooBig)?;
+        let h = i32::try_from(height).map_err(|_| Error::HeightTooBig)?;
+        w.checked_mul(h).ok_or(Error::TooBig)?;
+        if layout.len(width, height) != Some(buf.len()) {
+            return Err(Error::LengthMismatch);
+        }
+        let mut frame = Self::with_clear(width, height, layout.subsampling());
+        let luma = width as usize * height as usize;
+        let y = frame.y.as_u8_slice_mut();
+        let cb = frame.cb.as_u8_slice_mut();
+        let cr = frame.cr.as_u8_slice_mut();
+        match layout {
+            FrameLayout::I420 => {
+                let (ybuf, cbuf) = buf.split_at(luma);
+                let (cbbuf, crbuf) = cbuf.split_at(cb.len());
+                y.copy_from_slice(ybuf);
+                cb.copy_from_slice(cbbuf);
+                cr.copy_from_slice(crbuf);
+            }
+            FrameLayout::Nv12 => {
+                let (ybuf, cbuf) = buf.split_at(luma);
+                y.copy_from_slice(ybuf);
+                for (i, c) in cbuf.chunks_exact(2).enumerate() {
+                    cb[i] = c[0];
+                    cr[i] = c[1];
+                }
+            }
+            FrameLayout::Yuyv | FrameLayout::Uyvy => {
+                let (iy, icb, icr) = packed_offsets(layout);
+                let width = width as usize;
+                let cw = width.div_ceil(2);
+                for row in 0..height as usize {
+                    for col in 0..cw {
+                        let x = col * 2;
+                        let i = row * cw + col;
+                        let c = &buf[i * 4..][..4];
+                        y[row * width + x] = c[iy];
+                        if x + 1 < width {
+                            y[row * width + x + 1] = c[iy + 2];
+                        }
+                        cb[i] = c[icb];
+                        cr[i] = c[icr];
+                    }
+                }
+            }
+        }
+        Ok(frame)
+    }
+
+    /// Convert `YCbCrFrame` to a `Raster`.
+    ///
+    /// * `opts` Chroma resampling options.
+    pub fn to_raster<P>(&self, opts: ChromaOptions) -> Raster<P>
+    where
+        P: Pixel,
+        P::Chan: From<Ch8>,
+    {
+        let (width, height) = (self.width(), self.height());
+        let (cb_full, cr_full);
+        let (cb, cr) = if self.subsampling == Subsampling::S444 {
+            (&self.cb, &self.cr)
+        } else {
+            cb_full = resample_plane(&self.cb, width, height, opts);
+            cr_full = resample_plane(&self.cr, width, height, opts);
+            (&cb_full, &cr_full)
+        };
+        let mut raster = Raster::with_clear(width, height);
+        let planes = self.y.pixels().iter().zip(cb.pixels()).zip(cr.pixels());
+        for (p, ((y, cb), cr)) in raster.pixels_mut().iter_mut().zip(planes) {
+            let rgb =
+                srgb_from_ycc(y.one().into(), cb.one().into(), cr.one().into());
+            *p = rgb.convert();
+        }
+        raster
+    }
+
+    /// Convert `YCbCrFrame` to a byte buffer.
+    ///
+    /// * `layout` Byte layout of buffer.
+    ///
+    /// # Errors
+    ///
+    /// [Error::SubsamplingMismatch] if frame subsampling does not match
+    /// `layout`.
+    ///
+    /// [Error::SubsamplingMismatch]: ../enum.Error.html#variant.SubsamplingMismatch
+    pub fn to_bytes(&self, layout: FrameLayout) -> Result<Vec<u8>, Error> {
+        if layout.subsampling() != self.subsampling {
+            return Err(Error::SubsamplingMismatch);
+        }
+        let y = self.y.as_u8_slice();
+        let cb = self.cb.as_u8_slice();
+        let cr = self.cr.as_u8_slice();
+        let mut buf = Vec::with_capacity(y.len() + cb.len() * 2);
+        match layout {
+            FrameLayout::I420 => {
+                buf.extend_from_slice(y);
+                buf.extend_from_slice(cb);
+                buf.extend_from_slice(cr);
+            }
+            FrameLayout::Nv12 => {
+                buf.extend_from_slice(y);
+                for (b, r) in cb.iter().zip(cr) {
+                    buf.push(*b);
+                    buf.push(*r);
+                }
+            }
+            FrameLayout::Yuyv | FrameLayout::Uyvy => {
+                let (iy, icb, icr) = packed_offsets(layout);
+                let width = self.width() as usize;
+                let cw = self.cb.width() as usize;
+                for row in 0..self.height() as usize {
+                    let ys = &y[row * width..][..width];
+                    for col in 0..cw {
+                        let x = col * 2;
+                        let mut c = [0; 4];
+                        c[iy] = ys[x];
+                        c[iy + 2] = ys[(x + 1).min(width - 1)];
+                        c[icb] = cb[row * cw + col];
+                        c[icr] = cr[row * cw + col];
+                        buf.extend_from_slice(&c);
+                    }
+                }
+            }
+        }
+        Ok(buf)
+    }
+
+    /// Get width of `YCbCrFrame`.
+    pub fn width(&self) -> u32 {
+        self.y.width()
+    }
+
+    /// Get height of `YCbCrFrame`.
+    pub fn height(&self) -> u32 {
+        self.y.height()
+    }
+
+    /// Get chroma subsampling of `YCbCrFrame`.
+    pub fn subsampling(&self) -> Subsampling {
+        self.subsampling
+    }
+
+    /// Get the *y* (luma) plane.
+    pub fn y_plane(&self) -> &Raster<Gray8> {
+        &self.y
+    }
+
+    /// Get a mutable view of the *y* (luma) plane.
+    pub fn y_plane_mut(&mut self) -> RasterMut<'_, Gray8> {
+        RasterMut::from(&mut self.y)
+    }
+
+    /// Get the *Cb* (blue-difference chroma) plane.
+    pub fn cb_plane(&self) -> &Raster<Gray8> {
+        &self.cb
+    }
+
+    /// Get a mutable view of the *Cb* (blue-difference chroma) plane.
+    pub fn cb_plane_mut(&mut self) -> RasterMut<'_, Gray8> {
+        RasterMut::from(&mut self.cb)
+    }
+
+    /// Get the *Cr* (red-difference chroma) plane.
+    pub fn cr_plane(&self) -> &Raster<Gray8> {
+        &self.cr
+    }
+
+    /// Get a mutable view of the *Cr* (red-difference chroma) plane.
+    pub fn cr_plane_mut(&mut self) -> RasterMut<'_, Gray8> {
+        RasterMut::from(&mut self.cr)
+    }
+}
+
+/// Get byte offsets of *y₀*, *Cb* and *Cr* in a packed layout
+fn packed_offsets(layout: FrameLayout) -> (usize, usize, usize) {
+    match layout {
+        FrameLayout::Uyvy => (1, 0, 2),
+        _ => (0, 1, 3),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rgb::*;
+
+    #[test]
+    fn roundtrip_i420() {
+        let mut buf = vec![0; 4 * 4 + 2 * 2 * 2];
+        for (i, b) in buf.iter_mut().enumerate() {
+            *b = i as u8 * 4;
+        }
+        let f = YCbCrFrame::with_bytes(FrameLayout::I420, 4, 4, &buf).unwrap();
+        assert_eq!(f.cr_plane().pixel(1, 1), Gray8::new(92));
+        assert_eq!(f.to_bytes(FrameLayout::I420).unwrap(), buf);
+        assert_eq!(
+            f.to_bytes(FrameLayout::Yuyv),
+            Err(Error::SubsamplingMismatch)
+        );
+        assert_eq!(
+            YCbCrFrame::with_bytes(FrameLayout::I420, 4, 4, &buf[1..]).err(),
+            Some(Error::LengthMismatch)
+        );
+    }
+
+    #[test]
+    fn roundtrip_nv12() {
+        #[rustfmt::skip]
+        let buf = [
+            0x10, 0x20, 0x30,
+            0x40, 0x50, 0x60,
+            0x70, 0x80, 0x90, 0xA0,
+        ];
+        let f = YCbCrFrame::with_bytes(FrameLayout::Nv12, 3, 2, &buf).unwrap();
+        assert_eq!(
+            f.cb_plane().pixels(),
+            &[Gray8::new(0x70), Gray8::new(0x90)]
+        );
+        assert_eq!(
+            f.cr_plane().pixels(),
+            &[Gray8::new(0x80), Gray8::new(0xA0)]
+        );
+        assert_eq!(f.to_bytes(FrameLayout::Nv12).unwrap(), buf);
+        let b = f.to_bytes(FrameLayout::I420).unwrap();
+        assert_eq!(&b[6..], &[0x70, 0x90, 0x80, 0xA0]);
+    }
+
+    #[test]
+    fn packed_422() {
+        #[rustfmt::skip]
+        let yuyv = [
+            0x10, 0x80, 0x20, 0x90, 0x30, 0xA0, 0x40, 0xB0,
+        ];
+        #[rustfmt::skip]
+        let uyvy = [
+            0x80, 0x10, 0x90, 0x20, 0xA0, 0x30, 0xB0, 0x40,
+        ];
+        let f = YCbCrFrame::with_bytes(FrameLayout::Yuyv, 4, 1, &yuyv).unwrap();
+        assert_eq!(f.subsampling(), Subsampling::S422);
+        assert_eq!(f.y_plane().pixel(3, 0), Gray8::new(0x40));
+        assert_eq!(f.cr_plane().pixel(1, 0), Gray8::new(0xB0));
+        assert_eq!(f.to_bytes(FrameLayout::Uyvy).unwrap(), uyvy);
+        let g = YCbCrFrame::with_bytes(FrameLayout::Uyvy, 3, 1, &uyvy).unwrap();
+        assert_eq!(g.y_plane().pixels().len(), 3);
+        assert_eq!(g.to_bytes(FrameLayout::Uyvy).unwrap()[..7], uyvy[..7]);
+    }
+
+    #[test]
+    fn resample() {
+        let nearest = ChromaOptions {
+            filter: ChromaFilter::Nearest,
+            ..Default::default()
+        };
+        let cosited = ChromaOptions {
+            siting: ChromaSiting::Left,
+            ..Default::default()
+        };
+        let mut down = [0; 2];
+        let src = [0x10, 0x30, 0x50, 0x70];
+        downsample_line(&src, &mut down, false, ChromaFilter::Average);
+        assert_eq!(down, [0x20, 0x60]);
+        downsample_line(&src, &mut down, true, ChromaFilter::Average);
+        assert_eq!(down, [0x18, 0x50]);
+        downsample_line(&src, &mut down, false, ChromaFilter::Nearest);
+        assert_eq!(down, [0x10, 0x50]);
+        let mut up = [0; 4];
+        upsample_line(&[0x20, 0x60], &mut up, false, ChromaFilter::Average);
+        assert_eq!(up, [0x20, 0x30, 0x50, 0x60]);
+        upsample_line(&[0x20, 0x60], &mut up, true, ChromaFilter::Average);
+        assert_eq!(up, [0x20, 0x40, 0x60, 0x60]);
+        let mut r = Raster::with_clear(2, 2);
+        r.pixels_mut()[1] = Gray8::new(0xFF);
+        let d = resample_plane(&r, 1, 1, ChromaOptions::default());
+        assert_eq!(d.pixel(0, 0), Gray8::new(0x40));
+        let d = resample_plane(&r, 1, 1, nearest);
+        assert_eq!(d.pixel(0, 0), Gray8::new(0));
+        let d = resample_plane(&r, 1, 1, cosited);
+        assert_eq!(d.pixel(0, 0), Gray8::new(0x20));
+    }
+
+    #[test]
+    fn rgb_frame() {
+        let mut r = Raster::<SRgb8>::with_clear(4, 4);
+        r.copy_color((0, 0, 2, 2), SRgb8::new(0xFE, 0, 0));
+        r.copy_color((2, 2, 2, 2), SRgb8::new(0x80, 0x80, 0x80));
+        for sub in [Subsampling::S444, Subsampling::S422, Subsampling::S420] {
+            let opts = ChromaOptions::default();
+            let f = YCbCrFrame::with_raster(&r, sub, opts);
+            let r2: Raster<SRgb8> = f.to_raster(opts);
+            assert_eq!(r2.pixel(0, 0), SRgb8::new(0xFE, 0, 0));
+            assert_eq!(r2.pixel(3, 3), SRgb8::new(0x80, 0x80, 0x80));
+            assert_eq!(r2.pixel(0, 3), SRgb8::new(0, 0, 0));
+        }
+        let f =
+            YCbCrFrame::with_raster(&r, Subsampling::S420, Default::default());
+        assert_eq!(f.y_plane().pixel(0, 0), Gray8::new(76));
+        assert_eq!(f.cb_plane().pixel(1, 1), Gray8::new(0x80));
+        let r: Raster<Rgba8> = f.to_raster(Default::default());
+        assert_eq!(r.pixel(3, 3), Rgba8::new(0x37, 0x37, 0x37, 0xFF));
+    }
+
+    #[test]
+    fn empty_and_views() {
+        let r = Raster::<SRgb8>::with_clear(0, 4);
+        let opts = ChromaOptions::default();
+        let f = YCbCrFrame::with_raster(&r, Subsampling::S420, opts);
+        assert_eq!((f.width(), f.height()), (0, 4));
+        assert_eq!(f.cb_plane().height(), 2);
+        let r2: Raster<SRgb8> = f.to_raster(opts);
+        assert_eq!((r2.width(), r2.height()), (0, 4));
+        let mut f = YCbCrFrame::with_raster(&r2, Subsampling::S444, opts);
+        assert_eq!(f.y_plane_mut().width(), 0);
+        let mut f = YCbCrFrame::with_clear(4, 2, Subsampling::S420);
+        f.cr_plane_mut().copy_color((), Gray8::new(0x40));
+        assert_eq!(f.cr_plane().pixel(1, 0), Gray8::new(0x40));
+        assert_eq!(f.cr_plane().width(), 2);
+    }
+}
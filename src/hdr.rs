@@ -0,0 +1,394 @@
+// hdr.rs       High dynamic range images and tone mapping
+//
+// Copyright (c) 2023  Douglas P Lau
+//
+#![allow(clippy::excessive_precision)]
+
+use crate::chan::{Ch32, Channel, Linear, Straight};
+use crate::el::{Pix3, Pixel};
+use crate::raster::{check_size, Raster};
+use crate::rgb::{Rgb, SRgb8};
+use crate::Error;
+
+/// Linear `Ch32` RGB pixel, for display values
+type Rgb32 = Pix3<Ch32, Rgb, Straight, Linear>;
+
+/// Luminance weights of linear sRGB
+const LUMA: [f32; 3] = [0.2126, 0.7152, 0.0722];
+
+/// AgX inset matrix
+#[rustfmt::skip]
+const AGX_INSET: [[f32; 3]; 3] = [
+    [0.842479062253094, 0.0784335999999992, 0.0792237451477643],
+    [0.0423282422610123, 0.878468636469772, 0.0791661274605434],
+    [0.0423756549057051, 0.0784336, 0.879142973793104],
+];
+
+/// AgX outset matrix
+#[rustfmt::skip]
+const AGX_OUTSET: [[f32; 3]; 3] = [
+    [1.19687900512017, -0.0980208811401368, -0.0990297440797205],
+    [-0.0528968517574562, 1.15190312990417, -0.0989611768448433],
+    [-0.0529716355144438, -0.0980434501171241, 1.15107367264116],
+];
+
+/// Minimum exposure value of AgX log encoding
+const AGX_MIN_EV: f32 = -12.47393;
+
+/// Maximum exposure value of AgX log encoding
+const AGX_MAX_EV: f32 = 4.026069;
+
+/// Tone mapping operator, for converting an [HdrRaster] to a displayable
+/// `Raster`.
+///
+/// [HdrRaster]: struct.HdrRaster.html
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ToneMap {
+    /// Reinhard global operator, on luminance
+    #[default]
+    Reinhard,
+    /// Reinhard extended operator, on luminance, with the luminance which
+    /// maps to white
+    ReinhardExtended(f32),
+    /// Hable / Uncharted 2 filmic curve
+    Hable,
+    /// ACES filmic curve (Narkowicz fit)
+    Aces,
+    /// AgX, with the default "base" look
+    AgX,
+}
+
+/// High dynamic range image, with unclamped linear *red*, *green* and
+/// *blue* values.
+///
+/// Unlike `Ch32` channels, values are not clamped to `0.0..=1.0`, so they can
+/// represent scene light.  Use [tone_map] to produce a displayable `Raster`.
+///
+/// ### Tone map HDR data
+/// ```
+/// use pix::rgb::{Rgb, SRgb8};
+/// use pix::{HdrRaster, ToneMap};
+///
+/// let mut hdr = HdrRaster::with_clear(4, 4);
+/// *hdr.pixel_mut(0, 0) = [16.0, 8.0, 4.0];
+/// *hdr.pixel_mut(1, 0) = [0.18, 0.18, 0.18];
+/// let r = hdr.tone_map(ToneMap::AgX, 0.0);
+/// assert!(Rgb::red(r.pixel(0, 0)) > Rgb::blue(r.pixel(0, 0)));
+/// assert_eq!(r.pixel(3, 3), SRgb8::new(0, 0, 0));
+/// ```
+///
+/// [tone_map]: #method.tone_map
+#[derive(Clone, Debug, PartialEq)]
+pub struct HdrRaster {
+    width: u32,
+    height: u32,
+    pixels: Vec<[f32; 3]>,
+}
+
+/// Multiply a matrix by a vector
+fn mul_vec(m: &[[f32; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+    m.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+}
+
+/// Hable / Uncharted 2 partial curve
+fn hable_partial(x: f32) -> f32 {
+    const A: f32 = 0.15;
+    const B: f32 = 0.50;
+    const C: f32 = 0.10;
+    const D: f32 = 0.20;
+    const E: f32 = 0.02;
+    const F: f32 = 0.30;
+    ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F
+}
+
+/// AgX sigmoid, as a polynomial approximation
+fn agx_contrast(x: f32) -> f32 {
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x
+        + 0.4298 * x2
+        + 0.1191 * x
+        - 0.00232
+}
+
+impl ToneMap {
+    /// Map scene light to linear display values
+    fn map(self, rgb: [f32; 3]) -> [f32; 3] {
+        match self {
+            ToneMap::Reinhard => Self::luminance(rgb, |l| l / (1.0 + l)),
+            ToneMap::ReinhardExtended(white) => {
+                let w2 = (white * white).max(f32::EPSILON);
+                Self::luminance(rgb, |l| l * (1.0 + l / w2) / (1.0 + l))
+            }
+            ToneMap::Hable => {
+                let white = hable_partial(11.2);
+                rgb.map(|v| hable_partial(v.max(0.0)) / white)
+            }
+            ToneMap::Aces => rgb.map(|v| {
+                let v = v.max(0.0);
+                (v * (2.51 * v + 0.03)) / (v * (2.43 * v + 0.59) + 0.14)
+            }),
+            ToneMap::AgX => {
+                let v = mul_vec(&AGX_INSET, rgb).map(|v| {
+                    let ev = v.max(f32::MIN_POSITIVE).log2();
+                    let ev = ev.clamp(AGX_MIN_EV, AGX_MAX_EV);
+                    agx_contrast((ev - AGX_MIN_EV) / (AGX_MAX_EV - AGX_MIN_EV))
+                });
+                mul_vec(&AGX_OUTSET, v).map(|v| v.max(0.0).powf(2.2))
+            }
+        }
+    }
+
+    /// Map luminance, preserving chromaticity
+    fn luminance<F: Fn(f32) -> f32>(rgb: [f32; 3], f: F) -> [f32; 3] {
+        let l = mul_vec(&[LUMA; 3], rgb)[0];
+        if l > 0.0 {
+            let scale = f(l) / l;
+            rgb.map(|v| v * scale)
+        } else {
+            [0.0; 3]
+        }
+    }
+}
+
+impl HdrRaster {
+    /// Construct an `HdrRaster` with all pixels black.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` or `height` is greater than `i32::MAX`.
+    pub fn with_clear(width: u32, height: u32) -> Self {
+        let (_, _, len) =
+            check_size(width, height).unwrap_or_else(|e| panic!("{e}"));
+        HdrRaster {
+            width,
+            height,
+            pixels: vec![[0.0; 3]; len],
+        }
+    }
+
+    /// Construct an `HdrRaster` with a `Raster`.
+    ///
+    /// * `S` `Pixel` format of source `Raster`.
+    ///
+    /// Pixels are converted to linear *red*, *green* and *blue*; *alpha* is
+    /// ignored.
+    pub fn with_raster<S>(src: &Raster<S>) -> Self
+    where
+        S: Pixel,
+        Ch32: From<S::Chan>,
+    {
+        let pixels = src
+            .pixels()
+            .iter()
+            .map(|p| {
+                let rgb: Rgb32 = p.convert();
+                let ch = rgb.channels();
+                [ch[0].to_f32(), ch[1].to_f32(), ch[2].to_f32()]
+            })
+            .collect();
+        HdrRaster {
+            width: src.width(),
+            height: src.height(),
+            pixels,
+        }
+    }
+
+    /// Construct an `HdrRaster` from an `f32` buffer.
+    ///
+    /// * `B` Owned buffer type (`Vec` or boxed slice).
+    /// * `width` Width of `HdrRaster`.
+    /// * `height` Height of `HdrRaster`.
+    /// * `buffer` Interleaved linear *red*, *green* and *blue* values.
+    ///
+    /// # Panics
+    ///
+    /// * If `width` or `height` is greater than `i32::MAX`
+    /// * If `buffer` length is not equal to `width` * `height` * 3
+    ///
+    /// See [try_with_f32_buffer](#method.try_with_f32_buffer) for a
+    /// non-panicking version.
+    pub fn with_f32_buffer<B>(width: u32, height: u32, buffer: B) -> Self
+    where
+        B: Into<Box<[f32]>>,
+    {
+        Self::try_with_f32_buffer(width, height, buffer)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Try to construct an `HdrRaster` from an `f32` buffer.
+    ///
+    /// * `B` Owned buffer type (`Vec` or boxed slice).
+    /// * `width` Width of `HdrRaster`.
+    /// * `height` Height of `HdrRaster`.
+    /// * `buffer` Interleaved linear *red*, *green* and *blue* values.
+    ///
+    /// # Errors
+    ///
+    /// * [WidthTooBig] / [HeightTooBig] if `width` or `height` is greater
+    ///   than `i32::MAX`
+    /// * [TooBig] if `width` * `height` is greater than `i32::MAX`
+    /// * [LengthMismatch] if `buffer` length is not equal to `width` *
+    ///   `height` * 3
+    ///
+    /// [HeightTooBig]: enum.Error.html#variant.HeightTooBig
+    /// [LengthMismatch]: enum.Error.html#variant.LengthMismatch
+    /// [TooBig]: enum.Error.html#variant.TooBig
+    /// [WidthTooBig]: enum.Error.html#variant.WidthTooBig
+    pub fn try_with_f32_buffer<B>(
+        width: u32,
+        height: u32,
+        buffer: B,
+    ) -> Result<Self, Error>
+    where
+        B: Into<Box<[f32]>>,
+    {
+        let (_, _, len) = check_size(width, height)?;
+        let buffer = buffer.into();
+        if Some(buffer.len()) != len.checked_mul(3) {
+            return Err(Error::LengthMismatch);
+        }
+        let pixels =
+            buffer.chunks_exact(3).map(|c| [c[0], c[1], c[2]]).collect();
+        Ok(HdrRaster {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Get width of `HdrRaster`.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get height of `HdrRaster`.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get one pixel value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` or `y` is out of bounds.
+    pub fn pixel(&self, x: i32, y: i32) -> [f32; 3] {
+        self.pixels[self.index(x, y)]
+    }
+
+    /// Get a mutable pixel value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` or `y` is out of bounds.
+    pub fn pixel_mut(&mut self, x: i32, y: i32) -> &mut [f32; 3] {
+        let i = self.index(x, y);
+        &mut self.pixels[i]
+    }
+
+    /// Get the index of a pixel
+    fn index(&self, x: i32, y: i32) -> usize {
+        let (w, h) = (self.width as i32, self.height as i32);
+        assert!(x >= 0 && x < w && y >= 0 && y < h, "Pixel out of bounds");
+        (y * w + x) as usize
+    }
+
+    /// Get a slice of all pixels.
+    pub fn pixels(&self) -> &[[f32; 3]] {
+        &self.pixels
+    }
+
+    /// Get a mutable slice of all pixels.
+    pub fn pixels_mut(&mut self) -> &mut [[f32; 3]] {
+        &mut self.pixels
+    }
+
+    /// Get pixel data as an interleaved `f32` slice.
+    pub fn as_f32_slice(&self) -> &[f32] {
+        let (prefix, v, suffix) = unsafe { self.pixels.align_to::<f32>() };
+        debug_assert!(prefix.is_empty() && suffix.is_empty());
+        v
+    }
+
+    /// Tone map to a displayable `Raster`.
+    ///
+    /// * `op` Tone mapping operator.
+    /// * `exposure` Exposure adjustment, in stops, applied before the
+    ///   operator.
+    ///
+    /// Mapped values are encoded with [sRGB] gamma.
+    ///
+    /// [sRGB]: chan/struct.Srgb.html
+    pub fn tone_map(&self, op: ToneMap, exposure: f32) -> Raster<SRgb8> {
+        let scale = exposure.exp2();
+        let mut raster = Raster::with_clear(self.width, self.height);
+        for (p, v) in raster.pixels_mut().iter_mut().zip(&self.pixels) {
+            let [red, green, blue] = op.map(v.map(|c| c * scale));
+            *p = Rgb32::new(red, green, blue).convert();
+        }
+        raster
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gray::*;
+
+    const OPS: [ToneMap; 5] = [
+        ToneMap::Reinhard,
+        ToneMap::ReinhardExtended(4.0),
+        ToneMap::Hable,
+        ToneMap::Aces,
+        ToneMap::AgX,
+    ];
+
+    #[test]
+    fn operators() {
+        for op in OPS {
+            let black = op.map([0.0; 3]);
+            assert!(black.iter().all(|v| *v < 0.001), "{op:?}");
+            let bright = op.map([64.0; 3]);
+            assert!(bright.iter().all(|v| *v > 0.9), "{op:?}");
+            let mut prev = -1.0;
+            for i in 0..100 {
+                let v = op.map([i as f32 * 0.1; 3])[1];
+                assert!(v >= prev, "{op:?}");
+                prev = v;
+            }
+        }
+        assert_eq!(ToneMap::Reinhard.map([1.0; 3]), [0.5; 3]);
+        assert_eq!(ToneMap::ReinhardExtended(2.0).map([2.0; 3]), [1.0; 3]);
+        let [r, g, b] = ToneMap::Reinhard.map([3.0, 1.0, 0.0]);
+        assert!((r / g - 3.0).abs() < 1e-5 && b == 0.0);
+    }
+
+    #[test]
+    fn tone_map() {
+        let mut hdr = HdrRaster::with_f32_buffer(
+            2,
+            1,
+            vec![0.5, 0.5, 0.5, 100.0, 50.0, 0.0],
+        );
+        assert_eq!(hdr.pixel(1, 0), [100.0, 50.0, 0.0]);
+        hdr.pixel_mut(0, 0)[1] = 1.0;
+        assert_eq!(hdr.as_f32_slice()[..3], [0.5, 1.0, 0.5]);
+        let r = hdr.tone_map(ToneMap::Reinhard, 0.0);
+        let p = r.pixel(1, 0);
+        assert_eq!((p.one(), p.three()), (0xFF.into(), 0.into()));
+        let dark = hdr.tone_map(ToneMap::Aces, -4.0);
+        let light = hdr.tone_map(ToneMap::Aces, 0.0);
+        assert!(dark.pixel(0, 0).one() < light.pixel(0, 0).one());
+        let err = HdrRaster::try_with_f32_buffer(2, 2, vec![0.0; 11]);
+        assert_eq!(err, Err(Error::LengthMismatch));
+    }
+
+    #[test]
+    fn ldr_round_trip() {
+        let src = Raster::with_color(2, 2, SGray8::new(0x80));
+        let hdr = HdrRaster::with_raster(&src);
+        let v = hdr.pixel(1, 1);
+        assert!(v[0] == v[1] && v[1] == v[2]);
+        assert!((v[0] - 0.2158605).abs() < 1e-5);
+    }
+}
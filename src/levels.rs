@@ -0,0 +1,392 @@
+// levels.rs    Auto-levels and histogram equalization
+//
+// Copyright (c) 2023  Douglas P Lau
+//
+use crate::chan::{Ch32, Channel};
+use crate::el::Pixel;
+use crate::hsl::SHsla32;
+use crate::oklab::{lab_from_pixel, pixel_from_lab};
+use crate::raster::Raster;
+use crate::ColorModel;
+
+/// Number of histogram bins for equalization
+const BINS: usize = 256;
+
+/// Channels affected by a levels adjustment.
+///
+/// ### Equalize lightness
+/// ```
+/// use pix::rgb::SRgb8;
+/// use pix::{LevelsTarget, Raster};
+///
+/// let mut r = Raster::with_color(4, 4, SRgb8::new(0x40, 0x20, 0x20));
+/// r.copy_color((0, 0, 4, 2), SRgb8::new(0x60, 0x30, 0x30));
+/// r.equalize(LevelsTarget::OklabLightness);
+/// assert_eq!(r.pixel(0, 3), SRgb8::new(0, 0, 0));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LevelsTarget {
+    /// Each channel separately, except *alpha* and *hue*
+    #[default]
+    Channels,
+    /// Lightness only, using the [Oklab] *L* component
+    ///
+    /// [Oklab]: oklab/struct.Oklab.html
+    OklabLightness,
+    /// Lightness only, using the [Hsl] *lightness* component, with [sRGB]
+    /// gamma
+    ///
+    /// [Hsl]: hsl/struct.Hsl.html
+    /// [sRGB]: chan/struct.Srgb.html
+    HslLightness,
+}
+
+/// Get values of one channel
+fn plane<P: Pixel>(raster: &Raster<P>, chan: usize) -> Vec<f32> {
+    raster
+        .pixels()
+        .iter()
+        .map(|p| p.channels()[chan].to_f32())
+        .collect()
+}
+
+/// Set values of one channel
+fn set_plane<P: Pixel>(raster: &mut Raster<P>, chan: usize, values: &[f32]) {
+    for (p, v) in raster.pixels_mut().iter_mut().zip(values) {
+        p.channels_mut()[chan] = P::Chan::from(*v);
+    }
+}
+
+/// Get the histogram bin of a value
+fn bin(v: f32) -> usize {
+    ((v * (BINS - 1) as f32).round() as usize).min(BINS - 1)
+}
+
+/// Create a histogram of values
+fn histogram<'a, I>(values: I) -> [f32; BINS]
+where
+    I: IntoIterator<Item = &'a f32>,
+{
+    let mut hist = [0.0; BINS];
+    for v in values {
+        hist[bin(*v)] += 1.0;
+    }
+    hist
+}
+
+/// Lookup table mapping histogram bins to values
+struct Lut([f32; BINS]);
+
+impl Lut {
+    /// Create an equalization table from a histogram.
+    ///
+    /// * `hist` Histogram counts.
+    /// * `stretch` Stretch the lowest bin to zero.
+    fn equalize(hist: &[f32; BINS], stretch: bool) -> Self {
+        let total: f32 = hist.iter().sum();
+        let low = if stretch {
+            hist.iter().copied().find(|c| *c > 0.0).unwrap_or(0.0)
+        } else {
+            0.0
+        };
+        let mut lut = [0.0; BINS];
+        if total - low <= 0.0 {
+            for (b, v) in lut.iter_mut().enumerate() {
+                *v = b as f32 / (BINS - 1) as f32;
+            }
+            return Lut(lut);
+        }
+        let mut cdf = 0.0;
+        for (v, count) in lut.iter_mut().zip(hist) {
+            cdf += count;
+            *v = ((cdf - low) / (total - low)).clamp(0.0, 1.0);
+        }
+        Lut(lut)
+    }
+
+    /// Map a value, interpolating between bins
+    fn map(&self, v: f32) -> f32 {
+        let x = v.clamp(0.0, 1.0) * (BINS - 1) as f32;
+        let b = (x as usize).min(BINS - 2);
+        let f = x - b as f32;
+        self.0[b] + (self.0[b + 1] - self.0[b]) * f
+    }
+}
+
+/// Stretch values between low and high percentiles
+fn auto_levels_plane(values: &mut [f32], low: f32, high: f32) {
+    if values.is_empty() {
+        return;
+    }
+    let mut sorted = values.to_vec();
+    let n = sorted.len();
+    let clip = |pct: f32| ((pct / 100.0).clamp(0.0, 1.0) * n as f32) as usize;
+    let lo = clip(low).min(n - 1);
+    let hi = (n - 1).saturating_sub(clip(high)).max(lo);
+    let vlo = *sorted.select_nth_unstable_by(lo, f32::total_cmp).1;
+    let vhi = *sorted.select_nth_unstable_by(hi, f32::total_cmp).1;
+    if vhi > vlo {
+        let scale = 1.0 / (vhi - vlo);
+        values
+            .iter_mut()
+            .for_each(|v| *v = ((*v - vlo) * scale).clamp(0.0, 1.0));
+    }
+}
+
+/// Equalize histogram of values
+fn equalize_plane(values: &mut [f32]) {
+    let lut = Lut::equalize(&histogram(values.iter()), true);
+    values.iter_mut().for_each(|v| *v = lut.map(*v));
+}
+
+/// Contrast-limited adaptive histogram equalization of values.
+///
+/// See "Contrast Limited Adaptive Histogram Equalization" (Zuiderveld,
+/// Graphics Gems IV, 1994).
+fn clahe_plane(
+    values: &mut [f32],
+    (width, height): (usize, usize),
+    tiles: usize,
+    clip_limit: f32,
+) {
+    if width == 0 || height == 0 {
+        return;
+    }
+    let (tx, ty) = (tiles.clamp(1, width), tiles.clamp(1, height));
+    let tile_x = |i: usize| i * width / tx;
+    let tile_y = |j: usize| j * height / ty;
+    let mut luts = Vec::with_capacity(tx * ty);
+    for j in 0..ty {
+        for i in 0..tx {
+            let rows = values[tile_y(j) * width..tile_y(j + 1) * width]
+                .chunks_exact(width);
+            let mut hist =
+                histogram(rows.flat_map(|row| &row[tile_x(i)..tile_x(i + 1)]));
+            let total: f32 = hist.iter().sum();
+            let limit = (clip_limit * total / BINS as f32).max(1.0);
+            let excess: f32 = hist.iter().map(|c| (c - limit).max(0.0)).sum();
+            let extra = excess / BINS as f32;
+            hist.iter_mut().for_each(|c| *c = c.min(limit) + extra);
+            luts.push(Lut::equalize(&hist, false));
+        }
+    }
+    // grid position of a pixel, relative to tile centers
+    let grid = |p: usize, len: usize, tiles: usize| {
+        let g = (p as f32 + 0.5) * tiles as f32 / len as f32 - 0.5;
+        let g = g.clamp(0.0, (tiles - 1) as f32);
+        let g0 = g as usize;
+        (g0, (g0 + 1).min(tiles - 1), g - g0 as f32)
+    };
+    for (y, row) in values.chunks_exact_mut(width).enumerate() {
+        let (j0, j1, fy) = grid(y, height, ty);
+        for (x, v) in row.iter_mut().enumerate() {
+            let (i0, i1, fx) = grid(x, width, tx);
+            let top = luts[j0 * tx + i0].map(*v) * (1.0 - fx)
+                + luts[j0 * tx + i1].map(*v) * fx;
+            let bottom = luts[j1 * tx + i0].map(*v) * (1.0 - fx)
+                + luts[j1 * tx + i1].map(*v) * fx;
+            *v = top * (1.0 - fy) + bottom * fy;
+        }
+    }
+}
+
+impl<P: Pixel> Raster<P>
+where
+    P::Chan: From<Ch32>,
+    Ch32: From<P::Chan>,
+{
+    /// Apply a function to planes of channel values
+    fn map_planes<F>(&mut self, target: LevelsTarget, mut f: F)
+    where
+        F: FnMut(&mut [f32]),
+    {
+        match target {
+            LevelsTarget::Channels => {
+                for chan in P::Model::LINEAR {
+                    let mut values = plane(self, chan);
+                    f(&mut values);
+                    set_plane(self, chan, &values);
+                }
+            }
+            LevelsTarget::OklabLightness => {
+                let lab: Vec<([f32; 3], f32)> =
+                    self.pixels().iter().map(|p| lab_from_pixel(*p)).collect();
+                let mut values: Vec<f32> =
+                    lab.iter().map(|(lab, _)| lab[0]).collect();
+                f(&mut values);
+                let pixels = self.pixels_mut().iter_mut().zip(values);
+                for ((p, l), ([_, a, b], alpha)) in pixels.zip(lab) {
+                    *p = pixel_from_lab([l, a, b], alpha);
+                }
+            }
+            LevelsTarget::HslLightness => {
+                let mut hsl = Raster::<SHsla32>::with_raster(self);
+                let mut values = plane(&hsl, 2);
+                f(&mut values);
+                set_plane(&mut hsl, 2, &values);
+                *self = Raster::with_raster(&hsl);
+            }
+        }
+    }
+
+    /// Stretch levels to the full range.
+    ///
+    /// * `low` Percentage of values to clip at the low end.
+    /// * `high` Percentage of values to clip at the high end.
+    /// * `target` Channels to adjust.
+    ///
+    /// ### Stretch a dim image
+    /// ```
+    /// use pix::gray::Gray8;
+    /// use pix::{LevelsTarget, Raster};
+    ///
+    /// let mut r = Raster::with_color(4, 1, Gray8::new(0x40));
+    /// *r.pixel_mut(3, 0) = Gray8::new(0x80);
+    /// r.auto_levels(0.0, 0.0, LevelsTarget::Channels);
+    /// assert_eq!(r.pixel(0, 0), Gray8::new(0));
+    /// assert_eq!(r.pixel(3, 0), Gray8::new(0xFF));
+    /// ```
+    pub fn auto_levels(&mut self, low: f32, high: f32, target: LevelsTarget) {
+        self.map_planes(target, |values| auto_levels_plane(values, low, high));
+    }
+
+    /// Equalize the histogram.
+    ///
+    /// * `target` Channels to adjust.
+    pub fn equalize(&mut self, target: LevelsTarget) {
+        self.map_planes(target, equalize_plane);
+    }
+
+    /// Apply contrast-limited adaptive histogram equalization (CLAHE).
+    ///
+    /// * `tiles` Number of tiles in each direction (8 is typical).
+    /// * `clip_limit` Maximum histogram bin height, as a multiple of the
+    ///   mean (2.0 to 4.0 is typical).  Lower values limit the contrast
+    ///   increase.
+    /// * `target` Channels to adjust.
+    ///
+    /// Each tile is equalized separately, and the results are interpolated
+    /// between tile centers.
+    pub fn clahe(&mut self, tiles: u32, clip_limit: f32, target: LevelsTarget) {
+        let size = (self.width() as usize, self.height() as usize);
+        let tiles = tiles as usize;
+        self.map_planes(target, |values| {
+            clahe_plane(values, size, tiles, clip_limit)
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gray::*;
+    use crate::hsl::*;
+    use crate::oklab::{lab_from_rgb, rgb_from_lab};
+    use crate::rgb::*;
+
+    #[test]
+    fn auto_levels_clip() {
+        let mut r = Raster::<Gray16>::with_clear(100, 1);
+        for x in 0..100 {
+            *r.pixel_mut(x, 0) = Gray16::new(0x1000 + x as u16 * 0x100);
+        }
+        *r.pixel_mut(0, 0) = Gray16::new(0);
+        *r.pixel_mut(99, 0) = Gray16::new(0xFFFF);
+        r.auto_levels(1.0, 1.0, LevelsTarget::Channels);
+        assert_eq!(r.pixel(1, 0), Gray16::new(0));
+        assert_eq!(r.pixel(98, 0), Gray16::new(0xFFFF));
+        let mid = u16::from(r.pixel(50, 0).one());
+        assert!(mid.abs_diff(0x8000) < 0x400);
+    }
+
+    #[test]
+    fn equalize_channels() {
+        let mut r = Raster::<Rgb8>::with_clear(4, 1);
+        for x in 0..4 {
+            *r.pixel_mut(x, 0) = Rgb8::new(0x10 + x as u8, 0x80, 0x80);
+        }
+        r.equalize(LevelsTarget::Channels);
+        let red: Vec<u8> =
+            r.pixels().iter().map(|p| u8::from(p.one())).collect();
+        assert_eq!(red, [0, 0x55, 0xAA, 0xFF]);
+        assert_eq!(r.pixel(0, 0).two(), r.pixel(3, 0).two());
+    }
+
+    #[test]
+    fn hsl_channels() {
+        let mut r =
+            Raster::<Hsl8>::with_color(2, 2, Hsl8::new(0x20, 0x80, 0x40));
+        *r.pixel_mut(1, 1) = Hsl8::new(0x20, 0x80, 0x60);
+        r.auto_levels(0.0, 0.0, LevelsTarget::Channels);
+        assert_eq!(r.pixel(0, 0), Hsl8::new(0x20, 0x80, 0));
+        assert_eq!(r.pixel(1, 1), Hsl8::new(0x20, 0x80, 0xFF));
+    }
+
+    #[test]
+    fn hsl_lightness() {
+        let mut hsl = Raster::<SHsl32>::with_clear(3, 1);
+        for (x, l) in [0.2, 0.4, 0.6].iter().enumerate() {
+            *hsl.pixel_mut(x as i32, 0) = SHsl32::new(0.3, 0.6, *l);
+        }
+        let mut r = Raster::<SRgb32>::with_raster(&hsl);
+        r.auto_levels(0.0, 0.0, LevelsTarget::HslLightness);
+        let p = Raster::<SHsl32>::with_raster(&r).pixel(1, 0);
+        assert!((p.one().to_f32() - 0.3).abs() < 1e-4);
+        assert!((p.two().to_f32() - 0.6).abs() < 1e-4);
+        assert!((p.three().to_f32() - 0.5).abs() < 1e-4);
+        let l = Raster::<SHsl32>::with_raster(&r).pixel(2, 0).three();
+        assert_eq!(l, Ch32::new(1.0));
+    }
+
+    #[test]
+    fn oklab_lightness() {
+        // blue, with negative a and b
+        let [_, a, b] = lab_from_rgb([0.1, 0.15, 0.2]);
+        assert!(a < 0.0 && b < 0.0);
+        let mut r = Raster::<Rgb32>::with_clear(3, 1);
+        for (x, l) in [0.4, 0.55, 0.7].iter().enumerate() {
+            let [red, green, blue] = rgb_from_lab([*l, a, b]);
+            *r.pixel_mut(x as i32, 0) = Rgb32::new(red, green, blue);
+        }
+        r.auto_levels(0.0, 0.0, LevelsTarget::OklabLightness);
+        let p = r.pixel(1, 0);
+        let lab = lab_from_rgb([
+            p.one().to_f32(),
+            p.two().to_f32(),
+            p.three().to_f32(),
+        ]);
+        assert!((lab[0] - 0.5).abs() < 1e-3);
+        assert!((lab[1] - a).abs() < 1e-3);
+        assert!((lab[2] - b).abs() < 1e-3);
+        let blue = SRgb8::new(0x20, 0x40, 0xC0);
+        let mut r = Raster::with_color(2, 2, blue);
+        r.auto_levels(0.0, 0.0, LevelsTarget::OklabLightness);
+        r.equalize(LevelsTarget::OklabLightness);
+        assert_eq!(r.pixel(1, 1), blue);
+    }
+
+    #[test]
+    fn clahe_tiles() {
+        let mut r = Raster::<Gray8>::with_clear(32, 32);
+        for y in 0..32 {
+            for x in 0..32 {
+                let v = if x < 16 { 0x20 } else { 0xC0 } + ((x + y) % 4) as u8;
+                *r.pixel_mut(x, y) = Gray8::new(v);
+            }
+        }
+        let mut c = r.clone();
+        c.clahe(2, 4.0, LevelsTarget::Channels);
+        let spread = |r: &Raster<Gray8>, x: i32| {
+            let a = u8::from(r.pixel(x, 0).one());
+            let b = u8::from(r.pixel(x + 3, 0).one());
+            b - a
+        };
+        assert!(spread(&c, 0) > spread(&r, 0));
+        assert!(spread(&c, 24) > spread(&r, 24));
+        let mut c = r.clone();
+        c.clahe(4, 1.0, LevelsTarget::Channels);
+        assert!(spread(&c, 0) >= spread(&r, 0));
+        let mut e = Raster::<SRgb8>::with_clear(0, 0);
+        e.clahe(8, 2.0, LevelsTarget::OklabLightness);
+    }
+}
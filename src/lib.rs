@@ -67,25 +67,54 @@
 //!
 #![warn(missing_docs)]
 
+pub mod adjust;
+pub mod analysis;
 pub mod bgr;
 pub mod chan;
 pub mod cmy;
+mod dither;
 pub mod el;
+mod error;
+pub mod filter;
+mod frame;
 pub mod gray;
+mod hdr;
 pub mod hsl;
 pub mod hsv;
 mod hue;
 pub mod hwb;
+mod levels;
+mod lut;
 pub mod matte;
 mod model;
+mod morph;
 pub mod oklab;
 pub mod ops;
 mod palette;
+mod planar;
 mod private;
 mod raster;
+mod resize;
 pub mod rgb;
+mod sdf;
+mod stats;
+mod tiled;
+mod view;
+mod warp;
 pub mod ycc;
 
+pub use crate::dither::{Dither, Scan};
+pub use crate::error::Error;
+pub use crate::hdr::{HdrRaster, ToneMap};
+pub use crate::levels::LevelsTarget;
+pub use crate::lut::{Curve, Interpolation, Lut1d, Lut3d};
 pub use crate::model::ColorModel;
+pub use crate::morph::Structure;
 pub use crate::palette::Palette;
+pub use crate::planar::{GrayPlane, MattePlane, PlanarRaster};
 pub use crate::raster::{Raster, Region, Rows, RowsMut};
+pub use crate::resize::ResizeFilter;
+pub use crate::stats::{ChannelStats, Histogram};
+pub use crate::tiled::{TiledRaster, Tiles, TilesMut};
+pub use crate::view::{RasterMut, RasterRef};
+pub use crate::warp::{Edge, Sampling, Transform, WarpOptions};
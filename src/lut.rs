@@ -0,0 +1,869 @@
+// lut.rs       Tone curves and look-up tables
+//
+// Copyright (c) 2023  Douglas P Lau
+//
+use crate::chan::{Ch16, Ch32, Ch8, Channel, Gamma, Straight};
+use crate::el::{Pix4, Pixel};
+use crate::raster::{Raster, Region};
+use crate::rgb::{Rgb, SRgb8};
+use crate::{ColorModel, Error};
+use std::any::TypeId;
+use std::marker::PhantomData;
+
+/// Tone curve, mapping channel values from `0.0..=1.0` to `0.0..=1.0`.
+///
+/// ### Create an S-curve
+/// ```
+/// use pix::Curve;
+///
+/// let points = [(0.0, 0.0), (0.25, 0.2), (0.75, 0.8), (1.0, 1.0)];
+/// let c = Curve::with_points(&points);
+/// assert!((c.eval(0.5) - 0.5).abs() < 1e-6);
+/// assert!(c.eval(0.25) < 0.25);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Curve {
+    shape: Shape,
+}
+
+/// Shape of a curve
+#[derive(Clone, Debug, PartialEq)]
+enum Shape {
+    /// Monotone cubic spline through control points
+    Spline {
+        /// Control points (x, y)
+        points: Vec<(f32, f32)>,
+        /// Tangents at control points
+        tangents: Vec<f32>,
+    },
+    /// Levels adjustment
+    Levels {
+        /// Input black / white points
+        input: (f32, f32),
+        /// Gamma correction
+        gamma: f32,
+        /// Output black / white points
+        output: (f32, f32),
+    },
+    /// Table of evenly spaced values
+    Table(Vec<f32>),
+}
+
+/// Per-channel look-up table, built from [Curve]s.
+///
+/// Tables are precomputed for `Ch8` and `Ch16` channels when applied to a
+/// `Raster`, so each value is mapped with a single look-up.
+///
+/// ### Apply a gamma curve
+/// ```
+/// use pix::rgb::Rgb8;
+/// use pix::{Curve, Lut1d, Raster};
+///
+/// let mut r = Raster::with_color(2, 2, Rgb8::new(0x40, 0x80, 0xFF));
+/// let lut = Lut1d::new(Curve::with_gamma(0.5));
+/// r.apply_lut1d((), &lut);
+/// assert_eq!(r.pixel(0, 0), Rgb8::new(0x10, 0x40, 0xFF));
+/// ```
+///
+/// [Curve]: struct.Curve.html
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lut1d {
+    /// Curve for all color channels
+    color: Option<Curve>,
+    /// Curves for each channel
+    channels: Vec<Option<Curve>>,
+}
+
+impl Default for Curve {
+    fn default() -> Self {
+        Self::with_gamma(1.0)
+    }
+}
+
+impl Curve {
+    /// Create a curve from control points, using monotone cubic
+    /// interpolation.
+    ///
+    /// * `points` Control points (*x*, *y*).
+    ///
+    /// Points are sorted by *x*; values outside of the first and last
+    /// points are clamped.  The curve is monotonic between points, so it
+    /// never overshoots.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `points` is empty.
+    pub fn with_points(points: &[(f32, f32)]) -> Self {
+        assert!(!points.is_empty(), "No curve points");
+        let mut points = points.to_vec();
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        points.dedup_by(|a, b| a.0 == b.0);
+        let tangents = monotone_tangents(&points);
+        Curve {
+            shape: Shape::Spline { points, tangents },
+        }
+    }
+
+    /// Create a gamma curve.
+    ///
+    /// * `gamma` Gamma correction; values greater than `1.0` brighten.
+    pub fn with_gamma(gamma: f32) -> Self {
+        Self::with_levels((0.0, 1.0), gamma, (0.0, 1.0))
+    }
+
+    /// Create a levels curve.
+    ///
+    /// * `input` Input black and white points.
+    /// * `gamma` Gamma correction; values greater than `1.0` brighten.
+    /// * `output` Output black and white points.
+    pub fn with_levels(
+        input: (f32, f32),
+        gamma: f32,
+        output: (f32, f32),
+    ) -> Self {
+        Curve {
+            shape: Shape::Levels {
+                input,
+                gamma,
+                output,
+            },
+        }
+    }
+
+    /// Create a curve from a table of evenly spaced values.
+    ///
+    /// * `table` Output values, from input `0.0` to `1.0`.  Values between
+    ///   table entries are linearly interpolated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `table` is empty.
+    pub fn with_table(table: &[f32]) -> Self {
+        assert!(!table.is_empty(), "Empty curve table");
+        Curve {
+            shape: Shape::Table(table.to_vec()),
+        }
+    }
+
+    /// Evaluate the curve.
+    ///
+    /// * `x` Input value, from `0.0` to `1.0`.
+    ///
+    /// Returns the output value, clamped to `0.0..=1.0`.
+    pub fn eval(&self, x: f32) -> f32 {
+        let y = match &self.shape {
+            Shape::Spline { points, tangents } => {
+                eval_spline(points, tangents, x)
+            }
+            Shape::Levels {
+                input,
+                gamma,
+                output,
+            } => {
+                let range = input.1 - input.0;
+                let v = if range > 0.0 {
+                    ((x - input.0) / range).clamp(0.0, 1.0)
+                } else if x < input.0 {
+                    0.0
+                } else {
+                    1.0
+                };
+                let v = v.powf(1.0 / gamma.max(f32::EPSILON));
+                output.0 + (output.1 - output.0) * v
+            }
+            Shape::Table(table) => {
+                let last = table.len() - 1;
+                let pos = x.clamp(0.0, 1.0) * last as f32;
+                let i = (pos as usize).min(last.saturating_sub(1));
+                let j = (i + 1).min(last);
+                table[i] + (table[j] - table[i]) * (pos - i as f32)
+            }
+        };
+        y.clamp(0.0, 1.0)
+    }
+}
+
+/// Calculate tangents for monotone cubic interpolation.
+///
+/// See "Monotone Piecewise Cubic Interpolation" (Fritsch & Carlson, 1980).
+fn monotone_tangents(points: &[(f32, f32)]) -> Vec<f32> {
+    let n = points.len();
+    if n < 2 {
+        return vec![0.0; n];
+    }
+    let secants: Vec<f32> = points
+        .windows(2)
+        .map(|w| (w[1].1 - w[0].1) / (w[1].0 - w[0].0))
+        .collect();
+    let mut tangents = Vec::with_capacity(n);
+    tangents.push(secants[0]);
+    for s in secants.windows(2) {
+        if s[0] * s[1] <= 0.0 {
+            tangents.push(0.0);
+        } else {
+            tangents.push((s[0] + s[1]) * 0.5);
+        }
+    }
+    tangents.push(secants[n - 2]);
+    for (k, s) in secants.iter().enumerate() {
+        if *s == 0.0 {
+            tangents[k] = 0.0;
+            tangents[k + 1] = 0.0;
+        } else {
+            let a = tangents[k] / s;
+            let b = tangents[k + 1] / s;
+            let h = a.hypot(b);
+            if h > 3.0 {
+                tangents[k] = 3.0 / h * a * s;
+                tangents[k + 1] = 3.0 / h * b * s;
+            }
+        }
+    }
+    tangents
+}
+
+/// Evaluate a cubic Hermite spline
+fn eval_spline(points: &[(f32, f32)], tangents: &[f32], x: f32) -> f32 {
+    let first = points[0];
+    let last = points[points.len() - 1];
+    if x.is_nan() || x <= first.0 {
+        return first.1;
+    }
+    if x >= last.0 {
+        return last.1;
+    }
+    let k = points.partition_point(|p| p.0 <= x) - 1;
+    let (x0, y0) = points[k];
+    let (x1, y1) = points[k + 1];
+    let h = x1 - x0;
+    let t = (x - x0) / h;
+    let (t2, t3) = (t * t, t * t * t);
+    let h00 = 2.0 * t3 - 3.0 * t2 + 1.0;
+    let h10 = t3 - 2.0 * t2 + t;
+    let h01 = -2.0 * t3 + 3.0 * t2;
+    let h11 = t3 - t2;
+    h00 * y0 + h10 * h * tangents[k] + h01 * y1 + h11 * h * tangents[k + 1]
+}
+
+impl Lut1d {
+    /// Create a look-up table with one curve for all color channels.
+    ///
+    /// * `curve` Curve for all channels except *alpha* and *hue*.
+    pub fn new(curve: Curve) -> Self {
+        Lut1d {
+            color: Some(curve),
+            channels: Vec::new(),
+        }
+    }
+
+    /// Create a look-up table with a curve for each channel.
+    ///
+    /// * `curves` Curve for each channel (in the same order as
+    ///   [Pixel::channels]), or `None` to leave a channel unchanged.
+    ///
+    /// [Pixel::channels]: el/trait.Pixel.html#method.channels
+    pub fn with_channels(curves: &[Option<Curve>]) -> Self {
+        Lut1d {
+            color: None,
+            channels: curves.to_vec(),
+        }
+    }
+
+    /// Get the curve for a channel
+    fn curve<P: Pixel>(&self, chan: usize) -> Option<&Curve> {
+        match &self.color {
+            Some(curve) if P::Model::LINEAR.contains(&chan) => Some(curve),
+            Some(_) => None,
+            None => self.channels.get(chan).and_then(|c| c.as_ref()),
+        }
+    }
+
+    /// Build a table of channel values for one channel
+    fn table<C: Channel>(curve: &Curve, levels: usize) -> Vec<C> {
+        let max = (levels - 1) as f32;
+        (0..levels)
+            .map(|i| C::from(curve.eval(i as f32 / max)))
+            .collect()
+    }
+}
+
+impl<P: Pixel> Raster<P> {
+    /// Apply a 1D look-up table to a region.
+    ///
+    /// * `reg` Region within `self`.  It can be a `Region` struct, tuple of
+    ///   (*x*, *y*, *width*, *height*) or the unit type `()`.  Using
+    ///   `()` has the same result as `Raster::region()`.
+    /// * `lut` Look-up table.
+    ///
+    /// Channel values are mapped as stored, without converting *gamma* or
+    /// *alpha* mode.
+    pub fn apply_lut1d<R>(&mut self, reg: R, lut: &Lut1d)
+    where
+        R: Into<Region>,
+    {
+        let reg = self.intersection(reg);
+        let levels = if TypeId::of::<P::Chan>() == TypeId::of::<Ch8>() {
+            Some(1 << 8)
+        } else if TypeId::of::<P::Chan>() == TypeId::of::<Ch16>() {
+            Some(1 << 16)
+        } else {
+            None
+        };
+        let n_chan = P::default().channels().len();
+        let curves: Vec<Option<&Curve>> =
+            (0..n_chan).map(|chan| lut.curve::<P>(chan)).collect();
+        match levels {
+            Some(levels) => {
+                // build one table for each distinct curve
+                let mut tables: Vec<(&Curve, Vec<P::Chan>)> = Vec::new();
+                let index: Vec<Option<usize>> = curves
+                    .iter()
+                    .map(|curve| {
+                        let curve = (*curve)?;
+                        let i = tables.iter().position(|(c, _)| *c == curve);
+                        Some(i.unwrap_or_else(|| {
+                            let table = Lut1d::table::<P::Chan>(curve, levels);
+                            tables.push((curve, table));
+                            tables.len() - 1
+                        }))
+                    })
+                    .collect();
+                let max = (levels - 1) as f32;
+                for row in self.rows_mut(reg) {
+                    for p in row.iter_mut() {
+                        for (c, i) in p.channels_mut().iter_mut().zip(&index) {
+                            if let Some(i) = i {
+                                let table = &tables[*i].1;
+                                *c = table[(c.to_f32() * max).round() as usize];
+                            }
+                        }
+                    }
+                }
+            }
+            None => {
+                for row in self.rows_mut(reg) {
+                    for p in row.iter_mut() {
+                        for (c, curve) in
+                            p.channels_mut().iter_mut().zip(&curves)
+                        {
+                            if let Some(curve) = curve {
+                                *c = P::Chan::from(curve.eval(c.to_f32()));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Interpolation method for a [Lut3d].
+///
+/// [Lut3d]: struct.Lut3d.html
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Trilinear interpolation of 8 entries
+    Trilinear,
+    /// Tetrahedral interpolation of 4 entries, which preserves neutral
+    /// grays better
+    #[default]
+    Tetrahedral,
+}
+
+/// 3D color look-up table.
+///
+/// * `G` [Gamma] of input / output values ([Srgb] or [Linear]).
+///
+/// Entries are stored with *red* changing fastest, then *green*, then
+/// *blue*, as in `.cube` files.
+///
+/// ### Load a `.cube` file
+/// ```
+/// use pix::chan::Srgb;
+/// use pix::rgb::SRgb8;
+/// use pix::{Interpolation, Lut3d, Raster};
+///
+/// let cube = "LUT_3D_SIZE 2\n\
+///     0 0 0\n1 0 0\n0 1 0\n1 1 0\n0 0 1\n1 0 1\n0 1 1\n1 1 1\n";
+/// let lut = Lut3d::<Srgb>::with_cube(cube).unwrap();
+/// let mut r = Raster::with_color(2, 2, SRgb8::new(0x20, 0x40, 0x80));
+/// r.apply_lut3d((), &lut, Interpolation::Tetrahedral);
+/// assert_eq!(r.pixel(1, 1), SRgb8::new(0x20, 0x40, 0x80));
+/// ```
+///
+/// [Gamma]: chan/trait.Gamma.html
+/// [Linear]: chan/struct.Linear.html
+/// [Srgb]: chan/struct.Srgb.html
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lut3d<G: Gamma> {
+    size: usize,
+    domain: ([f32; 3], [f32; 3]),
+    entries: Vec<[f32; 3]>,
+    _gamma: PhantomData<G>,
+}
+
+impl<G: Gamma> Lut3d<G> {
+    /// Create an identity look-up table.
+    ///
+    /// * `size` Number of entries on each axis.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is less than 2.
+    pub fn new(size: usize) -> Self {
+        assert!(size >= 2, "Lut3d size too small");
+        let max = (size - 1) as f32;
+        let mut entries = Vec::with_capacity(size * size * size);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    entries.push([
+                        r as f32 / max,
+                        g as f32 / max,
+                        b as f32 / max,
+                    ]);
+                }
+            }
+        }
+        Lut3d {
+            size,
+            domain: ([0.0; 3], [1.0; 3]),
+            entries,
+            _gamma: PhantomData,
+        }
+    }
+
+    /// Create a look-up table from the text of a `.cube` file.
+    ///
+    /// * `text` Contents of an Adobe / Resolve `.cube` file.
+    ///
+    /// # Errors
+    ///
+    /// [Error::InvalidLut] if the file is not a valid 3D `.cube` file.
+    ///
+    /// [Error::InvalidLut]: enum.Error.html#variant.InvalidLut
+    pub fn with_cube(text: &str) -> Result<Self, Error> {
+        let mut size: Option<usize> = None;
+        let mut domain = ([0.0; 3], [1.0; 3]);
+        let mut entries = Vec::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let Some(key) = words.next() else {
+                continue;
+            };
+            match key {
+                "TITLE" => (),
+                "LUT_3D_SIZE" => {
+                    let s = words.next().and_then(|w| w.parse().ok());
+                    size =
+                        Some(s.filter(|s| *s >= 2).ok_or(Error::InvalidLut)?);
+                }
+                "DOMAIN_MIN" => domain.0 = parse_triple(words)?,
+                "DOMAIN_MAX" => domain.1 = parse_triple(words)?,
+                "LUT_3D_INPUT_RANGE" => {
+                    let lo = parse_value(words.next())?;
+                    let hi = parse_value(words.next())?;
+                    domain = ([lo; 3], [hi; 3]);
+                }
+                _ if key.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                    // LUT_1D_SIZE and other keywords are not supported
+                    if key.starts_with("LUT_1D") {
+                        return Err(Error::InvalidLut);
+                    }
+                }
+                _ => entries.push(parse_triple(line.split_whitespace())?),
+            }
+        }
+        let size = size.ok_or(Error::InvalidLut)?;
+        let len = size.checked_mul(size).and_then(|s| s.checked_mul(size));
+        if len != Some(entries.len()) {
+            return Err(Error::InvalidLut);
+        }
+        Ok(Lut3d {
+            size,
+            domain,
+            entries,
+            _gamma: PhantomData,
+        })
+    }
+
+    /// Create a look-up table from a Hald CLUT image.
+    ///
+    /// * `hald` Hald CLUT, with a width and height of *level*³ pixels.
+    ///
+    /// # Errors
+    ///
+    /// [Error::InvalidLut] if the image dimensions are not valid.
+    ///
+    /// [Error::InvalidLut]: enum.Error.html#variant.InvalidLut
+    pub fn with_hald(hald: &Raster<SRgb8>) -> Result<Self, Error> {
+        let width = hald.width() as usize;
+        let level = (1..=16).find(|l| l * l * l == width);
+        let level = match level {
+            Some(level) if level >= 2 && hald.height() as usize == width => {
+                level
+            }
+            _ => return Err(Error::InvalidLut),
+        };
+        let entries = hald
+            .pixels()
+            .iter()
+            .map(|p| [p.one().to_f32(), p.two().to_f32(), p.three().to_f32()])
+            .collect();
+        Ok(Lut3d {
+            size: level * level,
+            domain: ([0.0; 3], [1.0; 3]),
+            entries,
+            _gamma: PhantomData,
+        })
+    }
+
+    /// Write the look-up table as the text of a `.cube` file.
+    pub fn to_cube(&self) -> String {
+        let mut text = format!("LUT_3D_SIZE {}\n", self.size);
+        let (lo, hi) = self.domain;
+        text.push_str(&format!("DOMAIN_MIN {} {} {}\n", lo[0], lo[1], lo[2]));
+        text.push_str(&format!("DOMAIN_MAX {} {} {}\n", hi[0], hi[1], hi[2]));
+        for e in &self.entries {
+            text.push_str(&format!("{:.6} {:.6} {:.6}\n", e[0], e[1], e[2]));
+        }
+        text
+    }
+
+    /// Get the number of entries on each axis.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Get index of an entry
+    fn index(&self, r: usize, g: usize, b: usize) -> usize {
+        assert!(r < self.size && g < self.size && b < self.size);
+        (b * self.size + g) * self.size + r
+    }
+
+    /// Get an entry.
+    ///
+    /// * `r` Red index.
+    /// * `g` Green index.
+    /// * `b` Blue index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any index is not less than `size`.
+    pub fn entry(&self, r: usize, g: usize, b: usize) -> [f32; 3] {
+        self.entries[self.index(r, g, b)]
+    }
+
+    /// Set an entry.
+    ///
+    /// * `r` Red index.
+    /// * `g` Green index.
+    /// * `b` Blue index.
+    /// * `value` New *red*, *green* and *blue* value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any index is not less than `size`.
+    pub fn set_entry(&mut self, r: usize, g: usize, b: usize, value: [f32; 3]) {
+        let i = self.index(r, g, b);
+        self.entries[i] = value;
+    }
+
+    /// Look up a color.
+    ///
+    /// * `rgb` Input *red*, *green* and *blue* values.
+    /// * `interp` Interpolation method.
+    pub fn lookup(&self, rgb: [f32; 3], interp: Interpolation) -> [f32; 3] {
+        let (lo, hi) = self.domain;
+        let max = (self.size - 1) as f32;
+        let mut base = [0; 3];
+        let mut frac = [0.0; 3];
+        for i in 0..3 {
+            let range = hi[i] - lo[i];
+            let v = if range > 0.0 {
+                (rgb[i] - lo[i]) / range
+            } else {
+                0.0
+            };
+            let pos = v.clamp(0.0, 1.0) * max;
+            base[i] = (pos as usize).min(self.size - 2);
+            frac[i] = pos - base[i] as f32;
+        }
+        let [r, g, b] = base;
+        let c = |dr: usize, dg: usize, db: usize| {
+            self.entries[self.index(r + dr, g + dg, b + db)]
+        };
+        let [fr, fg, fb] = frac;
+        let mut out = [0.0; 3];
+        match interp {
+            Interpolation::Trilinear => {
+                for (i, o) in out.iter_mut().enumerate() {
+                    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+                    let c00 = lerp(c(0, 0, 0)[i], c(1, 0, 0)[i], fr);
+                    let c10 = lerp(c(0, 1, 0)[i], c(1, 1, 0)[i], fr);
+                    let c01 = lerp(c(0, 0, 1)[i], c(1, 0, 1)[i], fr);
+                    let c11 = lerp(c(0, 1, 1)[i], c(1, 1, 1)[i], fr);
+                    *o = lerp(lerp(c00, c10, fg), lerp(c01, c11, fg), fb);
+                }
+            }
+            Interpolation::Tetrahedral => {
+                // vertices along path from (0, 0, 0) to (1, 1, 1)
+                let (v1, v2, w) = if fr > fg {
+                    if fg > fb {
+                        ((1, 0, 0), (1, 1, 0), [fr, fg, fb])
+                    } else if fr > fb {
+                        ((1, 0, 0), (1, 0, 1), [fr, fb, fg])
+                    } else {
+                        ((0, 0, 1), (1, 0, 1), [fb, fr, fg])
+                    }
+                } else if fb > fg {
+                    ((0, 0, 1), (0, 1, 1), [fb, fg, fr])
+                } else if fb > fr {
+                    ((0, 1, 0), (0, 1, 1), [fg, fb, fr])
+                } else {
+                    ((0, 1, 0), (1, 1, 0), [fg, fr, fb])
+                };
+                let c0 = c(0, 0, 0);
+                let c1 = c(v1.0, v1.1, v1.2);
+                let c2 = c(v2.0, v2.1, v2.2);
+                let c3 = c(1, 1, 1);
+                for (i, o) in out.iter_mut().enumerate() {
+                    *o = c0[i]
+                        + w[0] * (c1[i] - c0[i])
+                        + w[1] * (c2[i] - c1[i])
+                        + w[2] * (c3[i] - c2[i]);
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Parse one value of a `.cube` file
+fn parse_value(word: Option<&str>) -> Result<f32, Error> {
+    word.and_then(|w| w.parse().ok()).ok_or(Error::InvalidLut)
+}
+
+/// Parse three values of a `.cube` file
+fn parse_triple<'a, I>(mut words: I) -> Result<[f32; 3], Error>
+where
+    I: Iterator<Item = &'a str>,
+{
+    let triple = [
+        parse_value(words.next())?,
+        parse_value(words.next())?,
+        parse_value(words.next())?,
+    ];
+    match words.next() {
+        Some(_) => Err(Error::InvalidLut),
+        None => Ok(triple),
+    }
+}
+
+impl<P: Pixel> Raster<P>
+where
+    P::Chan: From<Ch32>,
+    Ch32: From<P::Chan>,
+{
+    /// Apply a 3D look-up table to a region.
+    ///
+    /// * `reg` Region within `self`.  It can be a `Region` struct, tuple of
+    ///   (*x*, *y*, *width*, *height*) or the unit type `()`.  Using
+    ///   `()` has the same result as `Raster::region()`.
+    /// * `lut` Look-up table.
+    /// * `interp` Interpolation method.
+    ///
+    /// Pixels are converted to *red*, *green* and *blue* with the [Gamma]
+    /// of the look-up table, and straight *alpha* (which is unchanged).
+    ///
+    /// [Gamma]: chan/trait.Gamma.html
+    pub fn apply_lut3d<R, G>(
+        &mut self,
+        reg: R,
+        lut: &Lut3d<G>,
+        interp: Interpolation,
+    ) where
+        R: Into<Region>,
+        G: Gamma,
+    {
+        let reg = self.intersection(reg);
+        for row in self.rows_mut(reg) {
+            for p in row.iter_mut() {
+                let mut rgba: Pix4<Ch32, Rgb, Straight, G> = p.convert();
+                let ch = rgba.channels_mut();
+                let rgb = [ch[0].to_f32(), ch[1].to_f32(), ch[2].to_f32()];
+                for (c, v) in ch.iter_mut().zip(lut.lookup(rgb, interp)) {
+                    *c = Ch32::new(v);
+                }
+                *p = rgba.convert();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chan::{Linear, Srgb};
+    use crate::gray::*;
+    use crate::hsl::*;
+    use crate::rgb::*;
+
+    #[test]
+    fn monotone() {
+        let c = Curve::with_points(&[
+            (0.0, 0.0),
+            (0.1, 0.5),
+            (0.2, 0.52),
+            (0.6, 0.52),
+            (1.0, 1.0),
+        ]);
+        let mut prev = 0.0;
+        for i in 0..=100 {
+            let y = c.eval(i as f32 / 100.0);
+            assert!(y >= prev);
+            prev = y;
+        }
+        assert_eq!(c.eval(0.4), 0.52);
+        assert_eq!(c.eval(0.1), 0.5);
+        assert_eq!(c.eval(f32::NAN), 0.0);
+        let c = Curve::with_points(&[(0.5, 0.25)]);
+        assert_eq!(c.eval(0.0), 0.25);
+        assert_eq!(c.eval(1.0), 0.25);
+        assert_eq!(c.eval(f32::NAN), 0.25);
+    }
+
+    #[test]
+    fn levels_table() {
+        let c = Curve::with_levels((0.25, 0.75), 1.0, (0.0, 0.5));
+        assert_eq!(c.eval(0.0), 0.0);
+        assert_eq!(c.eval(0.5), 0.25);
+        assert_eq!(c.eval(1.0), 0.5);
+        let c = Curve::with_table(&[1.0, 0.0, 1.0]);
+        assert_eq!(c.eval(0.25), 0.5);
+        assert_eq!(c.eval(0.5), 0.0);
+        assert_eq!(Curve::with_table(&[0.3]).eval(0.7), 0.3);
+        assert_eq!(Curve::default().eval(0.3), 0.3);
+    }
+
+    #[test]
+    fn per_channel() {
+        let mut r = Raster::<SRgba16>::with_color(
+            2,
+            2,
+            SRgba16::new(0x1000, 0x2000, 0x3000, 0x8000),
+        );
+        let invert = Curve::with_table(&[1.0, 0.0]);
+        let lut = Lut1d::with_channels(&[None, Some(invert.clone()), None]);
+        r.apply_lut1d((0, 0, 1, 2), &lut);
+        assert_eq!(r.pixel(0, 1), SRgba16::new(0x1000, 0xDFFF, 0x3000, 0x8000));
+        assert_eq!(r.pixel(1, 1), SRgba16::new(0x1000, 0x2000, 0x3000, 0x8000));
+        let mut r =
+            Raster::<Hsl32>::with_color(1, 1, Hsl32::new(0.5, 0.5, 0.25));
+        r.apply_lut1d((), &Lut1d::new(invert.clone()));
+        assert_eq!(r.pixel(0, 0), Hsl32::new(0.5, 0.5, 0.75));
+        let mut r = Raster::<Gray8>::with_color(1, 1, Gray8::new(0x40));
+        r.apply_lut1d((), &Lut1d::new(invert.clone()));
+        assert_eq!(r.pixel(0, 0), Gray8::new(0xBF));
+        let half = Curve::with_table(&[0.0, 0.5]);
+        let lut = Lut1d::with_channels(&[
+            Some(invert.clone()),
+            Some(half),
+            Some(invert),
+        ]);
+        let mut r =
+            Raster::<Rgb8>::with_color(1, 1, Rgb8::new(0x40, 0x80, 0x20));
+        r.apply_lut1d((), &lut);
+        assert_eq!(r.pixel(0, 0), Rgb8::new(0xBF, 0x40, 0xDF));
+    }
+
+    #[test]
+    fn identity_3d() {
+        let lut = Lut3d::<Linear>::new(5);
+        for interp in [Interpolation::Trilinear, Interpolation::Tetrahedral] {
+            let v = lut.lookup([0.1, 0.7, 0.33], interp);
+            for (a, b) in v.iter().zip([0.1, 0.7, 0.33]) {
+                assert!((a - b).abs() < 1e-6);
+            }
+        }
+        let mut r = Raster::<Rgba16>::with_color(
+            2,
+            1,
+            Rgba16::new(0x1234, 0x5678, 0x9ABC, 0x8000),
+        );
+        let r0 = r.clone();
+        r.apply_lut3d((), &lut, Interpolation::Tetrahedral);
+        assert_eq!(r.pixels(), r0.pixels());
+    }
+
+    #[test]
+    fn gamma_3d() {
+        let mut lut = Lut3d::<Linear>::new(2);
+        lut.set_entry(1, 1, 1, [0.5, 0.5, 0.5]);
+        let mut r =
+            Raster::<SRgb8>::with_color(1, 1, SRgb8::new(0xFF, 0xFF, 0xFF));
+        r.apply_lut3d((), &lut, Interpolation::Trilinear);
+        assert_eq!(r.pixel(0, 0), SRgb8::new(0xBC, 0xBC, 0xBC));
+        let mut lut = Lut3d::<Srgb>::new(2);
+        lut.set_entry(1, 1, 1, [0.5, 0.5, 0.5]);
+        let mut r =
+            Raster::<SRgb8>::with_color(1, 1, SRgb8::new(0xFF, 0xFF, 0xFF));
+        r.apply_lut3d((), &lut, Interpolation::Trilinear);
+        assert_eq!(r.pixel(0, 0), SRgb8::new(0x80, 0x80, 0x80));
+    }
+
+    #[test]
+    fn tetrahedral_neutral() {
+        let mut lut = Lut3d::<Srgb>::new(2);
+        lut.set_entry(1, 0, 0, [0.0, 0.0, 0.0]);
+        lut.set_entry(0, 1, 1, [0.0, 0.0, 0.0]);
+        let gray = [0.4, 0.4, 0.4];
+        assert_eq!(lut.lookup(gray, Interpolation::Tetrahedral), gray);
+        assert_ne!(lut.lookup(gray, Interpolation::Trilinear), gray);
+    }
+
+    #[test]
+    fn cube_files() {
+        let mut lut = Lut3d::<Srgb>::new(3);
+        lut.set_entry(2, 0, 1, [0.25, 0.5, 0.75]);
+        let text = lut.to_cube();
+        assert!(text.starts_with("LUT_3D_SIZE 3\n"));
+        assert_eq!(Lut3d::<Srgb>::with_cube(&text), Ok(lut));
+        let text = "# comment\nTITLE \"x\"\nLUT_3D_SIZE 2\n\
+            LUT_3D_INPUT_RANGE 0 2\n\
+            0 0 0\n1 0 0\n0 1 0\n1 1 0\n0 0 1\n1 0 1\n0 1 1\n1 1 1\n";
+        let lut = Lut3d::<Srgb>::with_cube(text).unwrap();
+        let v = lut.lookup([1.0, 0.5, 2.0], Interpolation::Trilinear);
+        assert_eq!(v, [0.5, 0.25, 1.0]);
+        let bad = Err(Error::InvalidLut);
+        assert_eq!(Lut3d::<Srgb>::with_cube("LUT_3D_SIZE 2\n0 0 0\n"), bad);
+        assert_eq!(Lut3d::<Srgb>::with_cube("LUT_1D_SIZE 2\n0 0 0\n"), bad);
+        assert_eq!(Lut3d::<Srgb>::with_cube("0 0 0\n"), bad);
+        let huge = "LUT_3D_SIZE 4294967296\n0 0 0\n";
+        assert_eq!(Lut3d::<Srgb>::with_cube(huge), bad);
+        assert_eq!(Lut3d::<Srgb>::with_cube("LUT_3D_SIZE 2\n0 x 0\n"), bad);
+    }
+
+    #[test]
+    fn hald() {
+        // level 2: 8x8 image, 4 entries per axis
+        let mut hald = Raster::<SRgb8>::with_clear(8, 8);
+        for (i, p) in hald.pixels_mut().iter_mut().enumerate() {
+            let v = |n: usize| (n * 0xFF / 3) as u8;
+            *p = SRgb8::new(v(i % 4), v(i / 4 % 4), v(i / 16));
+        }
+        let lut = Lut3d::<Srgb>::with_hald(&hald).unwrap();
+        assert_eq!(lut.size(), 4);
+        assert_eq!(lut.entry(3, 0, 2), [1.0, 0.0, 0.6666667]);
+        let mut r =
+            Raster::<SRgb8>::with_color(1, 1, SRgb8::new(0x12, 0x9A, 0xFE));
+        r.apply_lut3d((), &lut, Interpolation::Tetrahedral);
+        assert_eq!(r.pixel(0, 0), SRgb8::new(0x12, 0x9A, 0xFE));
+        let bad = Raster::<SRgb8>::with_clear(8, 4);
+        assert_eq!(Lut3d::<Srgb>::with_hald(&bad), Err(Error::InvalidLut));
+    }
+}
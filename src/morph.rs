@@ -0,0 +1,391 @@
+// morph.rs     Morphological operations
+//
+// Copyright (c) 2023  Douglas P Lau
+//
+use crate::chan::Channel;
+use crate::el::Pixel;
+use crate::matte::Matte;
+use crate::raster::Raster;
+
+/// Structuring element for morphological operations.
+///
+/// ### Grow a mask
+/// ```
+/// use pix::matte::Matte8;
+/// use pix::{Raster, Structure};
+///
+/// let mut r = Raster::with_clear(5, 5);
+/// *r.pixel_mut(2, 2) = Matte8::new(0xFF);
+/// r.dilate(Structure::Cross(1));
+/// assert_eq!(r.pixel(2, 1), Matte8::new(0xFF));
+/// assert_eq!(r.pixel(1, 1), Matte8::new(0));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Structure {
+    /// Rectangle, with horizontal and vertical radius
+    Rect(u32, u32),
+    /// Cross, with radius
+    Cross(u32),
+    /// Disk, with radius
+    Disk(u32),
+}
+
+/// Combining operation (`min` or `max`) with its identity value
+#[derive(Clone, Copy)]
+struct MorphOp<C> {
+    identity: C,
+    op: fn(C, C) -> C,
+}
+
+/// Scratch buffers for van Herk / Gil-Werman algorithm
+struct Scratch<C> {
+    prefix: Vec<C>,
+    suffix: Vec<C>,
+}
+
+impl<C: Channel> MorphOp<C> {
+    /// Dilation (maximum)
+    fn dilate() -> Self {
+        MorphOp {
+            identity: C::MIN,
+            op: Ord::max,
+        }
+    }
+
+    /// Erosion (minimum)
+    fn erode() -> Self {
+        MorphOp {
+            identity: C::MAX,
+            op: Ord::min,
+        }
+    }
+}
+
+impl<C: Copy> MorphOp<C> {
+    /// Combine two lines of values
+    fn combine(self, acc: &mut [C], values: &[C]) {
+        for (a, v) in acc.iter_mut().zip(values) {
+            *a = (self.op)(*a, *v);
+        }
+    }
+
+    /// Apply operation to a line, within a window of `radius`.
+    ///
+    /// See "A fast algorithm for local minimum and maximum filters on
+    /// rectangular and octagonal kernels" (van Herk, 1992).  The cost does
+    /// not depend on `radius`.
+    fn line(self, line: &mut [C], radius: usize, scratch: &mut Scratch<C>) {
+        if radius == 0 || line.is_empty() {
+            return;
+        }
+        let len = line.len();
+        // larger windows cover the whole line anyway
+        let radius = radius.min(len);
+        let size = radius * 2 + 1;
+        // padded line, rounded up to a multiple of the window size
+        let padded = (len + radius * 2).div_ceil(size) * size;
+        let value = |i: usize| {
+            if i >= radius && i - radius < len {
+                line[i - radius]
+            } else {
+                self.identity
+            }
+        };
+        let Scratch { prefix, suffix } = scratch;
+        prefix.clear();
+        prefix.resize(padded, self.identity);
+        suffix.clear();
+        suffix.resize(padded, self.identity);
+        for start in (0..padded).step_by(size) {
+            let mut acc = self.identity;
+            for (i, p) in prefix[start..start + size].iter_mut().enumerate() {
+                acc = (self.op)(acc, value(start + i));
+                *p = acc;
+            }
+            let mut acc = self.identity;
+            for (i, s) in
+                suffix[start..start + size].iter_mut().enumerate().rev()
+            {
+                acc = (self.op)(acc, value(start + i));
+                *s = acc;
+            }
+        }
+        for (x, v) in line.iter_mut().enumerate() {
+            *v = (self.op)(suffix[x], prefix[x + size - 1]);
+        }
+    }
+
+    /// Apply operation to all rows
+    fn rows(self, values: &mut [C], width: usize, radius: usize) {
+        let mut scratch = Scratch::default();
+        for row in values.chunks_exact_mut(width) {
+            self.line(row, radius, &mut scratch);
+        }
+    }
+
+    /// Apply operation to all columns
+    fn columns(self, values: &mut [C], width: usize, radius: usize) {
+        if radius == 0 {
+            return;
+        }
+        let mut scratch = Scratch::default();
+        let mut col = Vec::with_capacity(values.len() / width);
+        for x in 0..width {
+            col.clear();
+            col.extend(values.iter().skip(x).step_by(width));
+            self.line(&mut col, radius, &mut scratch);
+            for (v, c) in values.iter_mut().skip(x).step_by(width).zip(&col) {
+                *v = *c;
+            }
+        }
+    }
+
+    /// Apply operation with a disk, as a union of horizontal lines
+    fn disk(self, values: &mut [C], width: usize, radius: usize) {
+        let src = values.to_vec();
+        let height = values.len() / width;
+        // larger disks cover the whole raster anyway
+        let r = radius.min(width + height) as isize;
+        let mut scratch = Scratch::default();
+        let mut line = Vec::with_capacity(width);
+        for (y, row) in values.chunks_exact_mut(width).enumerate() {
+            row.fill(self.identity);
+            for dy in -r..=r {
+                let sy = y as isize + dy;
+                if sy < 0 || sy >= height as isize {
+                    continue;
+                }
+                let sy = sy as usize;
+                let half = ((r * r - dy * dy) as f32).sqrt() as usize;
+                line.clear();
+                line.extend_from_slice(&src[sy * width..(sy + 1) * width]);
+                self.line(&mut line, half, &mut scratch);
+                self.combine(row, &line);
+            }
+        }
+    }
+
+    /// Apply operation with a structuring element
+    fn apply(self, values: &mut [C], width: usize, se: Structure) {
+        match se {
+            Structure::Rect(rx, ry) => {
+                self.rows(values, width, rx as usize);
+                self.columns(values, width, ry as usize);
+            }
+            Structure::Cross(r) => {
+                let mut vert = values.to_vec();
+                self.rows(values, width, r as usize);
+                self.columns(&mut vert, width, r as usize);
+                self.combine(values, &vert);
+            }
+            Structure::Disk(r) => self.disk(values, width, r as usize),
+        }
+    }
+}
+
+impl<C> Default for Scratch<C> {
+    fn default() -> Self {
+        Scratch {
+            prefix: Vec::new(),
+            suffix: Vec::new(),
+        }
+    }
+}
+
+impl<P> Raster<P>
+where
+    P: Pixel<Model = Matte>,
+{
+    /// Apply a morphological operation
+    fn morph(&mut self, se: Structure, op: MorphOp<P::Chan>) {
+        let width = self.width() as usize;
+        if width == 0 || self.height() == 0 {
+            return;
+        }
+        let mut values: Vec<P::Chan> =
+            self.pixels().iter().map(|p| p.one()).collect();
+        op.apply(&mut values, width, se);
+        for (p, v) in self.pixels_mut().iter_mut().zip(values) {
+            *p.one_mut() = v;
+        }
+    }
+
+    /// Dilate (grow) a mask.
+    ///
+    /// * `se` Structuring element.
+    ///
+    /// Each pixel is set to the maximum value within the structuring
+    /// element.  Rectangles and crosses use the van Herk / Gil-Werman
+    /// algorithm, so the cost does not depend on the radius.
+    pub fn dilate(&mut self, se: Structure) {
+        self.morph(se, MorphOp::dilate());
+    }
+
+    /// Erode (shrink) a mask.
+    ///
+    /// * `se` Structuring element.
+    ///
+    /// Each pixel is set to the minimum value within the structuring
+    /// element.  Pixels outside of the `Raster` are ignored.
+    pub fn erode(&mut self, se: Structure) {
+        self.morph(se, MorphOp::erode());
+    }
+
+    /// Open a mask (erode, then dilate).
+    ///
+    /// * `se` Structuring element.
+    ///
+    /// This removes areas smaller than the structuring element.
+    pub fn open(&mut self, se: Structure) {
+        self.erode(se);
+        self.dilate(se);
+    }
+
+    /// Close a mask (dilate, then erode).
+    ///
+    /// * `se` Structuring element.
+    ///
+    /// This fills holes smaller than the structuring element.
+    pub fn close(&mut self, se: Structure) {
+        self.dilate(se);
+        self.erode(se);
+    }
+
+    /// Get the morphological gradient of a mask (dilation minus erosion).
+    ///
+    /// * `se` Structuring element.
+    ///
+    /// This produces an outline around the edges of a mask.
+    pub fn morph_gradient(&mut self, se: Structure) {
+        let mut eroded = self.clone();
+        eroded.erode(se);
+        self.dilate(se);
+        for (p, e) in self.pixels_mut().iter_mut().zip(eroded.pixels()) {
+            *p.one_mut() = p.one() - e.one();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matte::*;
+
+    fn brute(line: &[u8], radius: usize) -> Vec<u8> {
+        (0..line.len())
+            .map(|x| {
+                let lo = x.saturating_sub(radius);
+                let hi = (x + radius + 1).min(line.len());
+                *line[lo..hi].iter().max().unwrap()
+            })
+            .collect()
+    }
+
+    fn matte(width: u32, rows: &[&str]) -> Raster<Matte8> {
+        let mut r = Raster::with_clear(width, rows.len() as u32);
+        for (p, c) in r.pixels_mut().iter_mut().zip(rows.concat().chars()) {
+            if c == '#' {
+                *p = Matte8::new(0xFF);
+            }
+        }
+        r
+    }
+
+    #[test]
+    fn van_herk() {
+        let line: Vec<u8> = (0..37).map(|i| (i * 89 % 251) as u8).collect();
+        let mut scratch = Scratch::default();
+        for radius in 0..20 {
+            let mut v = line.clone();
+            let op = MorphOp {
+                identity: 0,
+                op: Ord::max,
+            };
+            op.line(&mut v, radius, &mut scratch);
+            assert_eq!(v, brute(&line, radius), "radius {radius}");
+        }
+    }
+
+    #[test]
+    fn dilate_erode() {
+        let mut r = matte(7, &["       ", "       ", "   #   ", "       "]);
+        r.dilate(Structure::Rect(2, 1));
+        #[rustfmt::skip]
+        let rect = matte(7, &[
+            "       ",
+            " ##### ",
+            " ##### ",
+            " ##### ",
+        ]);
+        assert_eq!(r.pixels(), rect.pixels());
+        r.erode(Structure::Rect(2, 1));
+        #[rustfmt::skip]
+        let eroded = matte(7, &[
+            "       ",
+            "       ",
+            "   #   ",
+            "   #   ",
+        ]);
+        assert_eq!(r.pixels(), eroded.pixels());
+    }
+
+    #[test]
+    fn disk() {
+        let mut r = matte(7, &["       "; 7]);
+        *r.pixel_mut(3, 3) = Matte8::new(0xFF);
+        r.dilate(Structure::Disk(3));
+        #[rustfmt::skip]
+        let disk = matte(7, &[
+            "   #   ",
+            " ##### ",
+            " ##### ",
+            "#######",
+            " ##### ",
+            " ##### ",
+            "   #   ",
+        ]);
+        assert_eq!(r.pixels(), disk.pixels());
+    }
+
+    #[test]
+    fn large_radius() {
+        let full = matte(4, &["####"; 4]);
+        for se in [
+            Structure::Rect(1 << 26, 1 << 26),
+            Structure::Cross(u32::MAX),
+            Structure::Disk(u32::MAX),
+        ] {
+            let mut r = matte(4, &["    ", "    ", "    ", "   #"]);
+            r.dilate(se);
+            if se == Structure::Cross(u32::MAX) {
+                assert_eq!(r.pixel(0, 0), Matte8::new(0));
+                assert_eq!(r.pixel(0, 3), Matte8::new(0xFF));
+            } else {
+                assert_eq!(r.pixels(), full.pixels());
+            }
+        }
+    }
+
+    #[test]
+    fn open_close_gradient() {
+        #[rustfmt::skip]
+        let src = matte(6, &[
+            "#     ",
+            "  ### ",
+            "  # # ",
+            "  ### ",
+        ]);
+        let mut r = src.clone();
+        r.open(Structure::Cross(1));
+        assert!(r.pixels().iter().all(|p| *p == Matte8::new(0)));
+        let mut r = src.clone();
+        r.close(Structure::Rect(1, 1));
+        assert_eq!(r.pixel(3, 2), Matte8::new(0xFF));
+        assert_eq!(r.pixel(0, 0), Matte8::new(0xFF));
+        let mut r = Raster::<Matte16>::with_clear(5, 1);
+        r.copy_color((1, 0, 3, 1), Matte16::new(0x8000));
+        r.morph_gradient(Structure::Rect(1, 0));
+        let g: Vec<u16> = r.pixels().iter().map(|p| p.one().into()).collect();
+        assert_eq!(g, [0x8000, 0x8000, 0, 0x8000, 0x8000]);
+    }
+}
@@ -9,6 +9,7 @@
 
 use crate::chan::{Ch16, Ch32, Ch8, Channel, Linear, Premultiplied, Straight};
 use crate::el::{Pix3, Pix4, PixRgba, Pixel};
+use crate::rgb::{Rgb, Rgba32};
 use crate::ColorModel;
 use std::ops::Range;
 
@@ -142,21 +143,12 @@ impl ColorModel for Oklab {
     where
         P: Pixel<Model = Self>,
     {
-        let pl = Self::l(p).to_f32();
-        let pa = Self::a(p).to_f32();
-        let pb = Self::b(p).to_f32();
-
-        let l_ = pl + 0.3963377774 * pa + 0.2158037573 * pb;
-        let m_ = pl - 0.1055613458 * pa - 0.0638541728 * pb;
-        let s_ = pl - 0.0894841775 * pa - 1.2914855480 * pb;
-
-        let l = l_ * l_ * l_;
-        let m = m_ * m_ * m_;
-        let s = s_ * s_ * s_;
-
-        let red = 4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s;
-        let green = -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s;
-        let blue = -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s;
+        let lab = [
+            Self::l(p).to_f32(),
+            Self::a(p).to_f32(),
+            Self::b(p).to_f32(),
+        ];
+        let [red, green, blue] = rgb_from_lab(lab);
         PixRgba::<P>::new(red, green, blue, p.alpha().to_f32())
     }
 
@@ -166,25 +158,70 @@ impl ColorModel for Oklab {
         P: Pixel<Model = Self>,
     {
         let chan = rgba.channels();
-        let red = chan[0].to_f32();
-        let green = chan[1].to_f32();
-        let blue = chan[2].to_f32();
+        let rgb = [chan[0].to_f32(), chan[1].to_f32(), chan[2].to_f32()];
         let alpha = chan[3];
+        let [pl, pa, pb] = lab_from_rgb(rgb);
+        P::from_channels(&[pl.into(), pa.into(), pb.into(), alpha])
+    }
+}
 
-        let l = 0.4122214708 * red + 0.5363325363 * green + 0.0514459929 * blue;
-        let m = 0.2119034982 * red + 0.6806995451 * green + 0.1073969566 * blue;
-        let s = 0.0883024619 * red + 0.2817188376 * green + 0.6299787005 * blue;
+/// Convert linear *red*, *green* and *blue* to *L*, *a* and *b*
+pub(crate) fn lab_from_rgb([red, green, blue]: [f32; 3]) -> [f32; 3] {
+    let l = 0.4122214708 * red + 0.5363325363 * green + 0.0514459929 * blue;
+    let m = 0.2119034982 * red + 0.6806995451 * green + 0.1073969566 * blue;
+    let s = 0.0883024619 * red + 0.2817188376 * green + 0.6299787005 * blue;
 
-        let l_ = l.cbrt();
-        let m_ = m.cbrt();
-        let s_ = s.cbrt();
+    let l_ = l.cbrt();
+    let m_ = m.cbrt();
+    let s_ = s.cbrt();
 
-        let pl = 0.2104542553 * l_ + 0.7936177850 * m_ - 0.0040720468 * s_;
-        let pa = 1.9779984951 * l_ - 2.4285922050 * m_ + 0.4505937099 * s_;
-        let pb = 0.0259040371 * l_ + 0.7827717662 * m_ - 0.8086757660 * s_;
+    [
+        0.2104542553 * l_ + 0.7936177850 * m_ - 0.0040720468 * s_,
+        1.9779984951 * l_ - 2.4285922050 * m_ + 0.4505937099 * s_,
+        0.0259040371 * l_ + 0.7827717662 * m_ - 0.8086757660 * s_,
+    ]
+}
 
-        P::from_channels(&[pl.into(), pa.into(), pb.into(), alpha])
-    }
+/// Convert *L*, *a* and *b* to linear *red*, *green* and *blue*
+pub(crate) fn rgb_from_lab([pl, pa, pb]: [f32; 3]) -> [f32; 3] {
+    let l_ = pl + 0.3963377774 * pa + 0.2158037573 * pb;
+    let m_ = pl - 0.1055613458 * pa - 0.0638541728 * pb;
+    let s_ = pl - 0.0894841775 * pa - 1.2914855480 * pb;
+
+    let l = l_ * l_ * l_;
+    let m = m_ * m_ * m_;
+    let s = s_ * s_ * s_;
+
+    [
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+    ]
+}
+
+/// Convert a pixel to unclamped *L*, *a*, *b* and straight *alpha*
+pub(crate) fn lab_from_pixel<P>(p: P) -> ([f32; 3], f32)
+where
+    P: Pixel,
+    Ch32: From<P::Chan>,
+{
+    let rgba: Rgba32 = p.convert();
+    let ch = rgba.channels();
+    let rgb = [ch[0].to_f32(), ch[1].to_f32(), ch[2].to_f32()];
+    (lab_from_rgb(rgb), ch[3].to_f32())
+}
+
+/// Convert *L*, *a*, *b* and straight *alpha* to a pixel
+pub(crate) fn pixel_from_lab<P>(lab: [f32; 3], alpha: f32) -> P
+where
+    P: Pixel,
+    P::Chan: From<Ch32>,
+{
+    let [red, green, blue] = rgb_from_lab(lab);
+    // convert gamma before bit depth, to avoid losing precision
+    let rgba: Pix4<Ch32, Rgb, P::Alpha, P::Gamma> =
+        Rgba32::new(red, green, blue, alpha).convert();
+    rgba.convert()
 }
 
 /// [Oklab](struct.Oklab.html) 8-bit opaque (no *alpha* channel)
@@ -0,0 +1,258 @@
+// planar.rs    Planar raster images.
+//
+// Copyright (c) 2023  Douglas P Lau
+//
+use crate::chan::{Channel, Linear, Premultiplied, Straight};
+use crate::el::{Pix1, Pixel};
+use crate::gray::Gray;
+use crate::matte::Matte;
+use crate::raster::{Raster, Region};
+use crate::view::RasterMut;
+use crate::ColorModel;
+
+/// [Gray](gray/struct.Gray.html) [pixel](el/trait.Pixel.html) format of
+/// one color channel plane in a [PlanarRaster](struct.PlanarRaster.html).
+pub type GrayPlane<P> =
+    Pix1<<P as Pixel>::Chan, Gray, Straight, <P as Pixel>::Gamma>;
+
+/// [Matte](matte/struct.Matte.html) [pixel](el/trait.Pixel.html) format of
+/// the *alpha* channel plane in a [PlanarRaster](struct.PlanarRaster.html).
+pub type MattePlane<P> = Pix1<<P as Pixel>::Chan, Matte, Premultiplied, Linear>;
+
+/// Image with each channel stored in its own contiguous plane.
+///
+/// Each color channel of `P` is stored as a `Raster` of [GrayPlane] pixels, in
+/// channel order.  The *alpha* channel, if any, is stored as a `Raster` of
+/// [MattePlane] pixels.
+///
+/// A `PlanarRaster` can be constructed using one of the *with_* methods:
+/// * [with_clear](#method.with_clear)
+/// * [with_raster](#method.with_raster)
+///
+/// It can be converted to and from a `Raster<P>` using `into()`.
+///
+/// ### Split an image into planes
+/// ```
+/// use pix::rgb::{SRgb8, SRgba8};
+/// use pix::{PlanarRaster, Raster};
+///
+/// let r = Raster::with_color(4, 4, SRgb8::new(0x10, 0x20, 0x30));
+/// let mut planar = PlanarRaster::<SRgba8>::with_raster(&r);
+/// assert_eq!(planar.planes().len(), 3);
+/// // upload each plane separately
+/// let green: &[u8] = planar.planes()[1].as_u8_slice();
+/// assert_eq!(green, &[0x20; 16]);
+/// // edit alpha plane
+/// if let Some(mut alpha) = planar.alpha_mut() {
+///     alpha.clear();
+/// }
+/// let r = Raster::<SRgba8>::from(&planar);
+/// assert_eq!(r.pixel(1, 1), SRgba8::new(0x10, 0x20, 0x30, 0));
+/// ```
+///
+/// [GrayPlane]: type.GrayPlane.html
+/// [MattePlane]: type.MattePlane.html
+#[derive(Clone)]
+pub struct PlanarRaster<P: Pixel> {
+    width: u32,
+    height: u32,
+    planes: Vec<Raster<GrayPlane<P>>>,
+    alpha: Option<Raster<MattePlane<P>>>,
+}
+
+impl<P: Pixel> From<&Raster<P>> for PlanarRaster<P> {
+    /// Split a `Raster` into planes.
+    fn from(raster: &Raster<P>) -> Self {
+        let mut planar = Self::with_clear(raster.width(), raster.height());
+        for (i, p) in raster.pixels().iter().enumerate() {
+            planar.set_pixel_index(i, *p);
+        }
+        planar
+    }
+}
+
+impl<P: Pixel> From<&PlanarRaster<P>> for Raster<P> {
+    /// Interleave planes into a `Raster`.
+    fn from(planar: &PlanarRaster<P>) -> Self {
+        let mut raster = Raster::with_clear(planar.width(), planar.height());
+        for (i, p) in raster.pixels_mut().iter_mut().enumerate() {
+            *p = planar.pixel_index(i);
+        }
+        raster
+    }
+}
+
+impl<P: Pixel> PlanarRaster<P> {
+    /// Construct a `PlanarRaster` with all pixels set to the default value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` or `height` is greater than `i32::MAX`.
+    pub fn with_clear(width: u32, height: u32) -> Self {
+        let n_chan = P::default().channels().len();
+        let has_alpha = P::Model::ALPHA < n_chan;
+        let n_planes = n_chan - usize::from(has_alpha);
+        let planes = (0..n_planes)
+            .map(|_| Raster::with_clear(width, height))
+            .collect();
+        let alpha = if has_alpha {
+            Some(Raster::with_clear(width, height))
+        } else {
+            None
+        };
+        PlanarRaster {
+            width,
+            height,
+            planes,
+            alpha,
+        }
+    }
+
+    /// Construct a `PlanarRaster` with a `Raster`.
+    ///
+    /// The pixel format can be converted using this method.
+    ///
+    /// * `S` `Pixel` format of source `Raster`.
+    pub fn with_raster<S>(src: &Raster<S>) -> Self
+    where
+        S: Pixel,
+        P::Chan: From<S::Chan>,
+    {
+        let mut planar = Self::with_clear(src.width(), src.height());
+        for (i, s) in src.pixels().iter().enumerate() {
+            planar.set_pixel_index(i, s.convert());
+        }
+        planar
+    }
+
+    /// Get width of `PlanarRaster`.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get height of `PlanarRaster`.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get `Region` of entire `PlanarRaster`.
+    pub fn region(&self) -> Region {
+        Region::new(0, 0, self.width, self.height)
+    }
+
+    /// Get a slice of color channel planes.
+    pub fn planes(&self) -> &[Raster<GrayPlane<P>>] {
+        &self.planes
+    }
+
+    /// Get mutable views of color channel planes.
+    pub fn planes_mut(&mut self) -> Vec<RasterMut<'_, GrayPlane<P>>> {
+        self.planes.iter_mut().map(RasterMut::from).collect()
+    }
+
+    /// Get the *alpha* channel plane, if any.
+    pub fn alpha(&self) -> Option<&Raster<MattePlane<P>>> {
+        self.alpha.as_ref()
+    }
+
+    /// Get a mutable view of the *alpha* channel plane, if any.
+    pub fn alpha_mut(&mut self) -> Option<RasterMut<'_, MattePlane<P>>> {
+        self.alpha.as_mut().map(RasterMut::from)
+    }
+
+    /// Get one pixel.
+    pub fn pixel(&self, x: i32, y: i32) -> P {
+        assert!(x >= 0 && (x as u32) < self.width);
+        assert!(y >= 0 && (y as u32) < self.height);
+        self.pixel_index(y as usize * self.width as usize + x as usize)
+    }
+
+    /// Get a pixel by index into planes
+    fn pixel_index(&self, i: usize) -> P {
+        let mut chan = [P::Chan::MIN; 4];
+        let mut planes = self.planes.iter();
+        let n_chan = self.planes.len() + usize::from(self.alpha.is_some());
+        for (c, ch) in chan[..n_chan].iter_mut().enumerate() {
+            *ch = match &self.alpha {
+                Some(alpha) if c == P::Model::ALPHA => alpha.pixels()[i].one(),
+                _ => match planes.next() {
+                    Some(plane) => plane.pixels()[i].one(),
+                    None => P::Chan::MIN,
+                },
+            };
+        }
+        P::from_channels(&chan[..n_chan])
+    }
+
+    /// Set a pixel by index into planes
+    fn set_pixel_index(&mut self, i: usize, p: P) {
+        let mut planes = self.planes.iter_mut();
+        for (c, ch) in p.channels().iter().enumerate() {
+            match &mut self.alpha {
+                Some(alpha) if c == P::Model::ALPHA => {
+                    *alpha.pixels_mut()[i].one_mut() = *ch;
+                }
+                _ => {
+                    if let Some(plane) = planes.next() {
+                        *plane.pixels_mut()[i].one_mut() = *ch;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::gray::*;
+    use crate::hsv::*;
+    use crate::matte::*;
+    use crate::rgb::*;
+    use crate::*;
+
+    #[test]
+    fn rgba_planes() {
+        let mut r = Raster::<Rgba16>::with_clear(2, 2);
+        *r.pixel_mut(1, 0) = Rgba16::new(0x1000, 0x2000, 0x3000, 0x4000);
+        let mut p = PlanarRaster::from(&r);
+        assert_eq!(p.planes().len(), 3);
+        assert_eq!(p.planes()[2].pixel(1, 0), Gray16::new(0x3000));
+        assert_eq!(p.alpha().unwrap().pixel(1, 0), Matte16::new(0x4000));
+        p.planes_mut()[0].copy_color((0, 1, 2, 1), Gray16::new(0xFFFF));
+        let r = Raster::from(&p);
+        let v = [
+            Rgba16::new(0, 0, 0, 0),
+            Rgba16::new(0x1000, 0x2000, 0x3000, 0x4000),
+            Rgba16::new(0xFFFF, 0, 0, 0),
+            Rgba16::new(0xFFFF, 0, 0, 0),
+        ];
+        assert_eq!(r.pixels(), &v);
+    }
+
+    #[test]
+    fn gray_planes() {
+        let r = Raster::with_color(3, 1, SGray8::new(0x80));
+        let p = PlanarRaster::<SGray8>::from(&r);
+        assert_eq!(p.planes().len(), 1);
+        assert!(p.alpha().is_none());
+        assert_eq!(p.pixel(2, 0), SGray8::new(0x80));
+    }
+
+    #[test]
+    fn matte_planes() {
+        let r = Raster::with_color(1, 3, Matte8::new(0x40));
+        let p = PlanarRaster::<Matte8>::from(&r);
+        assert!(p.planes().is_empty());
+        assert_eq!(p.alpha().unwrap().pixel(0, 2), Matte8::new(0x40));
+        assert_eq!(Raster::from(&p).pixels(), r.pixels());
+    }
+
+    #[test]
+    fn convert_planes() {
+        let r = Raster::with_color(2, 2, SRgb8::new(0xFF, 0, 0));
+        let p = PlanarRaster::<Hsva8>::with_raster(&r);
+        assert_eq!(p.planes().len(), 3);
+        assert_eq!(p.planes()[1].pixel(0, 0), Gray8::new(0xFF));
+        assert_eq!(p.alpha().unwrap().pixel(1, 1), Matte8::new(0xFF));
+    }
+}
@@ -5,11 +5,13 @@
 //
 use crate::chan::{Ch16, Ch8, Linear, Premultiplied};
 use crate::el::Pixel;
+use crate::error::Error;
 use crate::matte::Matte;
 use crate::ops::Blend;
+use crate::view::{RasterMut, RasterRef};
 use std::convert::TryFrom;
+use std::marker::PhantomData;
 use std::ops::Range;
-use std::slice::{from_raw_parts_mut, ChunksExact, ChunksExactMut};
 
 /// Message for width too big
 const WIDTH_TOO_BIG: &str = "Raster width too big";
@@ -17,8 +19,8 @@ const WIDTH_TOO_BIG: &str = "Raster width too big";
 /// Message for height too big
 const HEIGHT_TOO_BIG: &str = "Raster height too big";
 
-/// Message for raster too big
-const TOO_BIG: &str = "Raster too big";
+/// Block size for cache-friendly transposition
+const BLOCK: usize = 16;
 
 /// Image arranged as a rectangular array of pixels.  Rows are ordered top to
 /// bottom, and pixels within rows are left to right.
@@ -31,6 +33,9 @@ const TOO_BIG: &str = "Raster too big";
 /// * [with_u8_buffer](#method.with_u8_buffer)
 /// * [with_u16_buffer](#method.with_u16_buffer)
 ///
+/// When dimensions come from untrusted input, the *try_with_* methods return
+/// an [Error](enum.Error.html) instead of panicking.
+///
 /// ### Working with byte buffers
 ///
 /// To allow interoperability with other crates, images can be created from
@@ -56,30 +61,45 @@ pub struct Raster<P: Pixel> {
 
 /// `Iterator` of *rows* in a [raster], as slices of [pixel]s.
 ///
-/// This struct is created by the [rows] method of [Raster].
+/// This struct is created by the [rows] method of [Raster], [RasterRef] or
+/// [RasterMut].
 ///
 /// [pixel]: el/trait.Pixel.html
 /// [raster]: struct.Raster.html
+/// [RasterMut]: struct.RasterMut.html
+/// [RasterRef]: struct.RasterRef.html
 /// [rows]: struct.Raster.html#method.rows
 pub struct Rows<'a, P: Pixel> {
-    /// Chunks iterator of full rows
-    chunks: ChunksExact<'a, P>,
-    /// Range of requested columns
+    /// Pixel data, starting at the first requested row
+    buf: &'a [u8],
+    /// Distance between rows (bytes)
+    stride: usize,
+    /// Range of requested columns (bytes)
     columns: Range<usize>,
+    /// Number of remaining rows
+    height: usize,
+    _pixel: PhantomData<P>,
 }
 
 /// `Iterator` of *rows* in a [raster], as mutable slices of [pixel]s.
 ///
-/// This struct is created by the [rows_mut] method of [Raster].
+/// This struct is created by the [rows_mut] method of [Raster] or
+/// [RasterMut].
 ///
 /// [pixel]: el/trait.Pixel.html
 /// [raster]: struct.Raster.html
+/// [RasterMut]: struct.RasterMut.html
 /// [rows_mut]: struct.Raster.html#method.rows_mut
 pub struct RowsMut<'a, P: Pixel> {
-    /// Chunks iterator of full rows
-    chunks: ChunksExactMut<'a, P>,
-    /// Range of requested columns
+    /// Pixel data, starting at the first requested row
+    buf: &'a mut [u8],
+    /// Distance between rows (bytes)
+    stride: usize,
+    /// Range of requested columns (bytes)
     columns: Range<usize>,
+    /// Number of remaining rows
+    height: usize,
+    _pixel: PhantomData<P>,
 }
 
 /// Location / dimensions of pixels relative to a [Raster](struct.Raster.html).
@@ -132,7 +152,7 @@ where
         let slice = Box::<[P]>::into_raw(pixels);
         let buffer: Box<[u8]> = unsafe {
             let ptr = (*slice).as_mut_ptr() as *mut u8;
-            Box::from_raw(from_raw_parts_mut(ptr, capacity))
+            Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, capacity))
         };
         buffer
     }
@@ -149,7 +169,7 @@ where
         let slice = Box::<[P]>::into_raw(pixels);
         let buffer: Box<[u16]> = unsafe {
             let ptr = (*slice).as_mut_ptr() as *mut u16;
-            Box::from_raw(from_raw_parts_mut(ptr, capacity))
+            Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, capacity))
         };
         buffer
     }
@@ -193,9 +213,8 @@ impl<P: Pixel> Raster<P> {
     /// let r = Raster::<SRgb8>::with_color(15, 15, clr);
     /// ```
     pub fn with_color(width: u32, height: u32, clr: P) -> Self {
-        let width = i32::try_from(width).expect(WIDTH_TOO_BIG);
-        let height = i32::try_from(height).expect(HEIGHT_TOO_BIG);
-        let len = (width * height) as usize;
+        let (width, height, len) =
+            check_size(width, height).unwrap_or_else(|e| panic!("{e}"));
         let pixels = vec![clr; len].into_boxed_slice();
         Raster {
             width,
@@ -249,6 +268,9 @@ impl<P: Pixel> Raster<P> {
     /// * If `width` or `height` is greater than `std::i32::MAX`
     /// * If `pixels` length is not equal to `width` * `height`
     ///
+    /// See [try_with_pixels](#method.try_with_pixels) for a non-panicking
+    /// version.
+    ///
     /// ## Example
     /// ```
     /// use pix::ops::Src;
@@ -265,17 +287,57 @@ impl<P: Pixel> Raster<P> {
     where
         B: Into<Box<[P]>>,
     {
-        let width = i32::try_from(width).expect(WIDTH_TOO_BIG);
-        let height = i32::try_from(height).expect(HEIGHT_TOO_BIG);
-        let len = usize::try_from(width.checked_mul(height).expect(TOO_BIG))
-            .expect(TOO_BIG);
+        Self::try_with_pixels(width, height, pixels)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Try to construct a `Raster` with owned pixel data.
+    ///
+    /// * `B` Owned pixed type (`Vec` or boxed slice).
+    /// * `width` Width of `Raster`.
+    /// * `height` Height of `Raster`.
+    /// * `pixels` Pixel data.
+    ///
+    /// # Errors
+    ///
+    /// * [WidthTooBig] / [HeightTooBig] if `width` or `height` is greater
+    ///   than `std::i32::MAX`
+    /// * [TooBig] if `width` * `height` is greater than `std::i32::MAX`
+    /// * [LengthMismatch] if `pixels` length is not equal to `width` *
+    ///   `height`
+    ///
+    /// ## Example
+    /// ```
+    /// use pix::rgb::Rgb8;
+    /// use pix::{Error, Raster};
+    ///
+    /// let p = vec![Rgb8::new(255, 0, 255); 15];
+    /// let r = Raster::try_with_pixels(4, 4, p);
+    /// assert_eq!(r.err(), Some(Error::LengthMismatch));
+    /// ```
+    ///
+    /// [HeightTooBig]: enum.Error.html#variant.HeightTooBig
+    /// [LengthMismatch]: enum.Error.html#variant.LengthMismatch
+    /// [TooBig]: enum.Error.html#variant.TooBig
+    /// [WidthTooBig]: enum.Error.html#variant.WidthTooBig
+    pub fn try_with_pixels<B>(
+        width: u32,
+        height: u32,
+        pixels: B,
+    ) -> Result<Self, Error>
+    where
+        B: Into<Box<[P]>>,
+    {
+        let (width, height, len) = check_size(width, height)?;
         let pixels = pixels.into();
-        assert_eq!(len, pixels.len());
-        Raster {
+        if len != pixels.len() {
+            return Err(Error::LengthMismatch);
+        }
+        Ok(Raster {
             width,
             height,
             pixels,
-        }
+        })
     }
 
     /// Construct a `Raster` from a `u8` buffer.
@@ -287,35 +349,69 @@ impl<P: Pixel> Raster<P> {
     ///
     /// # Panics
     ///
-    /// * If `width` or `height` is greater than `std::i32::MAX`
+    /// * If `width` or `height` is greater than `std::i32::MAX`, or zero
     /// * If `buffer` length is not equal to `width` * `height` *
     ///   `std::mem::size_of::<P>()`
+    ///
+    /// See [try_with_u8_buffer](#method.try_with_u8_buffer) for a
+    /// non-panicking version.
     pub fn with_u8_buffer<B>(width: u32, height: u32, buffer: B) -> Self
     where
         B: Into<Box<[u8]>>,
         P: Pixel<Chan = Ch8>,
     {
-        let width = i32::try_from(width).expect(WIDTH_TOO_BIG);
-        let height = i32::try_from(height).expect(HEIGHT_TOO_BIG);
-        let len = usize::try_from(width.checked_mul(height).expect(TOO_BIG))
-            .expect(TOO_BIG);
-        assert!(len > 0);
-        let buffer: Box<[u8]> = buffer.into();
-        let capacity = buffer.len();
-        assert_eq!(
-            len * std::mem::size_of::<P>(),
-            capacity * std::mem::size_of::<u8>()
-        );
-        let slice = Box::<[u8]>::into_raw(buffer);
-        let pixels: Box<[P]> = unsafe {
-            let ptr = (*slice).as_mut_ptr() as *mut P;
-            Box::from_raw(from_raw_parts_mut(ptr, len))
-        };
-        Raster {
+        Self::try_with_u8_buffer(width, height, buffer)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Try to construct a `Raster` from a `u8` buffer.
+    ///
+    /// * `B` Owned pixed type (`Vec` or boxed slice).
+    /// * `width` Width of `Raster`.
+    /// * `height` Height of `Raster`.
+    /// * `buffer` Buffer of pixel data.
+    ///
+    /// # Errors
+    ///
+    /// * [WidthTooBig] / [HeightTooBig] if `width` or `height` is greater
+    ///   than `std::i32::MAX`
+    /// * [TooBig] if `width` * `height` is greater than `std::i32::MAX`
+    /// * [ZeroSize] if `width` or `height` is zero
+    /// * [LengthMismatch] if `buffer` length is not equal to `width` *
+    ///   `height` * `std::mem::size_of::<P>()`
+    ///
+    /// ## Example
+    /// ```
+    /// use pix::rgb::SRgb8;
+    /// use pix::{Error, Raster};
+    ///
+    /// // Dimensions from an untrusted file header
+    /// let (width, height) = (0x8000_0000, 1);
+    /// let r = Raster::<SRgb8>::try_with_u8_buffer(width, height, vec![]);
+    /// assert_eq!(r.err(), Some(Error::WidthTooBig));
+    /// ```
+    ///
+    /// [HeightTooBig]: enum.Error.html#variant.HeightTooBig
+    /// [LengthMismatch]: enum.Error.html#variant.LengthMismatch
+    /// [TooBig]: enum.Error.html#variant.TooBig
+    /// [WidthTooBig]: enum.Error.html#variant.WidthTooBig
+    /// [ZeroSize]: enum.Error.html#variant.ZeroSize
+    pub fn try_with_u8_buffer<B>(
+        width: u32,
+        height: u32,
+        buffer: B,
+    ) -> Result<Self, Error>
+    where
+        B: Into<Box<[u8]>>,
+        P: Pixel<Chan = Ch8>,
+    {
+        let (width, height, len) = check_size(width, height)?;
+        let pixels = boxed_pixels(len, buffer.into())?;
+        Ok(Raster {
             width,
             height,
             pixels,
-        }
+        })
     }
 
     /// Construct a `Raster` from a `u16` buffer.
@@ -327,35 +423,58 @@ impl<P: Pixel> Raster<P> {
     ///
     /// # Panics
     ///
-    /// * If `width` or `height` is greater than `std::i32::MAX`
+    /// * If `width` or `height` is greater than `std::i32::MAX`, or zero
     /// * If `buffer` length is not equal to `width` * `height` *
     ///   `std::mem::size_of::<P>()`
+    ///
+    /// See [try_with_u16_buffer](#method.try_with_u16_buffer) for a
+    /// non-panicking version.
     pub fn with_u16_buffer<B>(width: u32, height: u32, buffer: B) -> Self
     where
         B: Into<Box<[u16]>>,
         P: Pixel<Chan = Ch16>,
     {
-        let width = i32::try_from(width).expect(WIDTH_TOO_BIG);
-        let height = i32::try_from(height).expect(HEIGHT_TOO_BIG);
-        let len = usize::try_from(width.checked_mul(height).expect(TOO_BIG))
-            .expect(TOO_BIG);
-        assert!(len > 0);
-        let buffer: Box<[u16]> = buffer.into();
-        let capacity = buffer.len();
-        assert_eq!(
-            len * std::mem::size_of::<P>(),
-            capacity * std::mem::size_of::<u16>()
-        );
-        let slice = Box::<[u16]>::into_raw(buffer);
-        let pixels: Box<[P]> = unsafe {
-            let ptr = (*slice).as_mut_ptr() as *mut P;
-            Box::from_raw(from_raw_parts_mut(ptr, len))
-        };
-        Raster {
+        Self::try_with_u16_buffer(width, height, buffer)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Try to construct a `Raster` from a `u16` buffer.
+    ///
+    /// * `B` Owned pixed type (`Vec` or boxed slice).
+    /// * `width` Width of `Raster`.
+    /// * `height` Height of `Raster`.
+    /// * `buffer` Buffer of pixel data (in native-endian byte order).
+    ///
+    /// # Errors
+    ///
+    /// * [WidthTooBig] / [HeightTooBig] if `width` or `height` is greater
+    ///   than `std::i32::MAX`
+    /// * [TooBig] if `width` * `height` is greater than `std::i32::MAX`
+    /// * [ZeroSize] if `width` or `height` is zero
+    /// * [LengthMismatch] if `buffer` length is not equal to `width` *
+    ///   `height` * `std::mem::size_of::<P>()`
+    ///
+    /// [HeightTooBig]: enum.Error.html#variant.HeightTooBig
+    /// [LengthMismatch]: enum.Error.html#variant.LengthMismatch
+    /// [TooBig]: enum.Error.html#variant.TooBig
+    /// [WidthTooBig]: enum.Error.html#variant.WidthTooBig
+    /// [ZeroSize]: enum.Error.html#variant.ZeroSize
+    pub fn try_with_u16_buffer<B>(
+        width: u32,
+        height: u32,
+        buffer: B,
+    ) -> Result<Self, Error>
+    where
+        B: Into<Box<[u16]>>,
+        P: Pixel<Chan = Ch16>,
+    {
+        let (width, height, len) = check_size(width, height)?;
+        let pixels = boxed_pixels(len, buffer.into())?;
+        Ok(Raster {
             width,
             height,
             pixels,
-        }
+        })
     }
 
     /// Get width of `Raster`.
@@ -376,19 +495,52 @@ impl<P: Pixel> Raster<P> {
     }
 
     /// Get one pixel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` or `y` is out of bounds.
     pub fn pixel(&self, x: i32, y: i32) -> P {
-        assert!(x >= 0 && x < self.width);
-        assert!(y >= 0 && y < self.height);
-        let i = (self.width * y + x) as usize;
-        self.pixels[i]
+        self.try_pixel(x, y).unwrap_or_else(|e| panic!("{e}"))
     }
 
     /// Get a mutable pixel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` or `y` is out of bounds.
     pub fn pixel_mut(&mut self, x: i32, y: i32) -> &mut P {
-        assert!(x >= 0 && x < self.width);
-        assert!(y >= 0 && y < self.height);
-        let i = (self.width * y + x) as usize;
-        &mut self.pixels[i]
+        self.try_pixel_mut(x, y).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Try to get one pixel.
+    ///
+    /// # Errors
+    ///
+    /// [OutOfBounds](enum.Error.html#variant.OutOfBounds) if `x` or `y` is
+    /// out of bounds.
+    pub fn try_pixel(&self, x: i32, y: i32) -> Result<P, Error> {
+        let i = self.pixel_index(x, y)?;
+        Ok(self.pixels[i])
+    }
+
+    /// Try to get a mutable pixel.
+    ///
+    /// # Errors
+    ///
+    /// [OutOfBounds](enum.Error.html#variant.OutOfBounds) if `x` or `y` is
+    /// out of bounds.
+    pub fn try_pixel_mut(&mut self, x: i32, y: i32) -> Result<&mut P, Error> {
+        let i = self.pixel_index(x, y)?;
+        Ok(&mut self.pixels[i])
+    }
+
+    /// Get index of a pixel
+    fn pixel_index(&self, x: i32, y: i32) -> Result<usize, Error> {
+        if x >= 0 && x < self.width && y >= 0 && y < self.height {
+            Ok((self.width * y + x) as usize)
+        } else {
+            Err(Error::OutOfBounds)
+        }
     }
 
     /// Get a slice of all pixels.
@@ -404,21 +556,29 @@ impl<P: Pixel> Raster<P> {
     /// Get an `Iterator` of rows within a `Raster`.
     ///
     /// * `reg` Region of the Raster to iterate.
-    pub fn rows<R>(&self, reg: R) -> Rows<P>
+    pub fn rows<R>(&self, reg: R) -> Rows<'_, P>
     where
         R: Into<Region>,
     {
-        Rows::new(self, self.intersection(reg.into()))
+        let reg = self.intersection(reg.into());
+        Rows::new(self.as_u8_slice(), self.stride(), reg)
     }
 
     /// Get an `Iterator` of mutable rows within a `Raster`.
     ///
     /// * `reg` Region of the Raster to iterate.
-    pub fn rows_mut<R>(&mut self, reg: R) -> RowsMut<P>
+    pub fn rows_mut<R>(&mut self, reg: R) -> RowsMut<'_, P>
     where
         R: Into<Region>,
     {
-        RowsMut::new(self, self.intersection(reg.into()))
+        let reg = self.intersection(reg.into());
+        let stride = self.stride();
+        RowsMut::new(self.as_u8_slice_mut(), stride, reg)
+    }
+
+    /// Get distance between rows (bytes).
+    pub fn stride(&self) -> usize {
+        self.width() as usize * std::mem::size_of::<P>()
     }
 
     /// Get `Region` of entire `Raster`.
@@ -435,6 +595,60 @@ impl<P: Pixel> Raster<P> {
         reg.intersection(self.region())
     }
 
+    /// Get a view of a region within the `Raster`.
+    ///
+    /// * `reg` Region of the Raster.  The view's origin is at the top-left
+    ///   corner of the region, after clipping to the `Raster`.
+    ///
+    /// The view can be used as a source with [copy_raster] or
+    /// [composite_raster], without copying pixels.
+    ///
+    /// ### Copy a tile from a `Raster`
+    /// ```
+    /// use pix::rgb::SRgb8;
+    /// use pix::Raster;
+    ///
+    /// let mut r0 = Raster::with_clear(100, 100);
+    /// r0.copy_color((40, 40, 20, 20), SRgb8::new(0x80, 0x40, 0x20));
+    /// let tile = r0.view((32, 32, 32, 32));
+    /// assert_eq!(tile.pixel(8, 8), SRgb8::new(0x80, 0x40, 0x20));
+    /// let mut r1 = Raster::with_clear(32, 32);
+    /// r1.copy_raster((), tile, ());
+    /// ```
+    ///
+    /// [composite_raster]: #method.composite_raster
+    /// [copy_raster]: #method.copy_raster
+    pub fn view<R>(&self, reg: R) -> RasterRef<'_, P>
+    where
+        R: Into<Region>,
+    {
+        RasterRef::from(self).view(reg)
+    }
+
+    /// Get a mutable view of a region within the `Raster`.
+    ///
+    /// * `reg` Region of the Raster.  The view's origin is at the top-left
+    ///   corner of the region, after clipping to the `Raster`.
+    ///
+    /// ### Composite onto a tile
+    /// ```
+    /// use pix::ops::SrcOver;
+    /// use pix::rgb::Rgba8p;
+    /// use pix::Raster;
+    ///
+    /// let mut r0 = Raster::with_clear(64, 64);
+    /// let r1 = Raster::with_color(8, 8, Rgba8p::new(80, 0, 80, 200));
+    /// let mut tile = r0.view_mut((32, 0, 32, 32));
+    /// tile.composite_raster((4, 4), &r1, (), SrcOver);
+    /// assert_eq!(r0.pixel(36, 4), Rgba8p::new(80, 0, 80, 200));
+    /// ```
+    pub fn view_mut<R>(&mut self, reg: R) -> RasterMut<'_, P>
+    where
+        R: Into<Region>,
+    {
+        RasterMut::from(self).into_view(reg)
+    }
+
     /// Copy a color to a region of the `Raster`.
     ///
     /// * `reg` Region within `self`.  It can be a `Region` struct, tuple of
@@ -455,21 +669,13 @@ impl<P: Pixel> Raster<P> {
     where
         R: Into<Region>,
     {
-        let reg = self.intersection(reg.into());
-        let width = reg.width();
-        let height = reg.height();
-        if width > 0 && height > 0 {
-            let drows = self.rows_mut(reg);
-            for drow in drows {
-                P::copy_color(drow, &clr);
-            }
-        }
+        RasterMut::from(self).copy_color(reg, clr);
     }
 
     /// Copy from a source `Raster`.
     ///
     /// * `to` Region within `self` (destination).
-    /// * `src` Source `Raster`.
+    /// * `src` Source `Raster`, `RasterRef` or `RasterMut`.
     /// * `from` Region within source `Raster`.
     ///
     /// `to` / `from` can be `Region` structs, tuples of (*x*, *y*, *width*,
@@ -501,41 +707,182 @@ impl<P: Pixel> Raster<P> {
     /// // ... load image data
     /// r0.copy_raster((40, 40, 5, 5), &r1, ());
     /// ```
-    pub fn copy_raster<R0, R1>(&mut self, to: R0, src: &Raster<P>, from: R1)
+    pub fn copy_raster<'b, R0, R1, S>(&mut self, to: R0, src: S, from: R1)
     where
         R0: Into<Region>,
         R1: Into<Region>,
+        S: Into<RasterRef<'b, P>>,
+        P: 'b,
     {
-        let (to, from) = self.clip_regions(to, src, from);
-        let srows = src.rows(from);
-        let drows = self.rows_mut(to);
-        for (drow, srow) in drows.zip(srows) {
-            P::copy_slice(drow, srow);
+        RasterMut::from(self).copy_raster(to, src, from);
+    }
+
+    /// Flip a region horizontally (mirror left to right).
+    ///
+    /// * `reg` Region within `self`.  It can be a `Region` struct, tuple of
+    ///   (*x*, *y*, *width*, *height*) or the unit type `()`.  Using
+    ///   `()` has the same result as `Raster::region()`.
+    ///
+    /// ### Mirror an image
+    /// ```
+    /// use pix::gray::Gray8;
+    /// use pix::Raster;
+    ///
+    /// let mut r = Raster::<Gray8>::with_u8_buffer(3, 1, [1, 2, 3]);
+    /// r.flip_horizontal(());
+    /// assert_eq!(r.as_u8_slice(), &[3, 2, 1]);
+    /// ```
+    pub fn flip_horizontal<R>(&mut self, reg: R)
+    where
+        R: Into<Region>,
+    {
+        for row in self.rows_mut(reg) {
+            row.reverse();
         }
     }
 
-    /// Clip `to` / `from` regions for source / destination rasters
-    fn clip_regions<R0, R1, Q>(
-        &self,
-        to: R0,
-        src: &Raster<Q>,
-        from: R1,
-    ) -> (Region, Region)
+    /// Flip a region vertically (mirror top to bottom).
+    ///
+    /// * `reg` Region within `self`.  It can be a `Region` struct, tuple of
+    ///   (*x*, *y*, *width*, *height*) or the unit type `()`.  Using
+    ///   `()` has the same result as `Raster::region()`.
+    pub fn flip_vertical<R>(&mut self, reg: R)
     where
-        R0: Into<Region>,
-        R1: Into<Region>,
-        Q: Pixel,
+        R: Into<Region>,
     {
-        let (to, from) = (to.into(), from.into());
-        let (tx, ty) = (to.x.min(0).abs(), to.y.min(0).abs());
-        let (fx, fy) = (from.x.min(0).abs(), from.y.min(0).abs());
-        let to = self.intersection(to);
-        let from = src.intersection(from);
-        let width = to.width().min(from.width());
-        let height = to.height().min(from.height());
-        let to = Region::new(to.x + fx, to.y + fy, width, height);
-        let from = Region::new(from.x + tx, from.y + ty, width, height);
-        (to, from)
+        let mut rows = self.rows_mut(reg);
+        while let (Some(top), Some(bottom)) = (rows.next(), rows.next_back()) {
+            top.swap_with_slice(bottom);
+        }
+    }
+
+    /// Rotate a region by 180 degrees.
+    ///
+    /// * `reg` Region within `self`.  It can be a `Region` struct, tuple of
+    ///   (*x*, *y*, *width*, *height*) or the unit type `()`.  Using
+    ///   `()` has the same result as `Raster::region()`.
+    pub fn rotate_180<R>(&mut self, reg: R)
+    where
+        R: Into<Region>,
+    {
+        let reg = self.intersection(reg);
+        self.flip_vertical(reg);
+        self.flip_horizontal(reg);
+    }
+
+    /// Transpose a region (mirror across the main diagonal).
+    ///
+    /// * `reg` Region within `self`.  It can be a `Region` struct, tuple of
+    ///   (*x*, *y*, *width*, *height*) or the unit type `()`.  Using
+    ///   `()` has the same result as `Raster::region()`.
+    ///
+    /// When `reg` covers the entire `Raster`, the width and height are
+    /// swapped.  Square regions are transposed in place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `reg` is not square and does not cover the entire `Raster`.
+    ///
+    /// ### Transpose an image
+    /// ```
+    /// use pix::gray::Gray8;
+    /// use pix::Raster;
+    ///
+    /// let mut r = Raster::<Gray8>::with_u8_buffer(3, 2, [1, 2, 3, 4, 5, 6]);
+    /// r.transpose(());
+    /// assert_eq!((r.width(), r.height()), (2, 3));
+    /// assert_eq!(r.as_u8_slice(), &[1, 4, 2, 5, 3, 6]);
+    /// ```
+    pub fn transpose<R>(&mut self, reg: R)
+    where
+        R: Into<Region>,
+    {
+        let reg = self.intersection(reg);
+        if reg.width == reg.height {
+            self.transpose_square(reg);
+        } else if reg == self.region() {
+            self.transpose_all();
+        } else {
+            panic!("Region not square");
+        }
+    }
+
+    /// Rotate a region by 90 degrees clockwise.
+    ///
+    /// * `reg` Region within `self`.  It can be a `Region` struct, tuple of
+    ///   (*x*, *y*, *width*, *height*) or the unit type `()`.  Using
+    ///   `()` has the same result as `Raster::region()`.
+    ///
+    /// When `reg` covers the entire `Raster`, the width and height are
+    /// swapped.  Square regions are rotated in place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `reg` is not square and does not cover the entire `Raster`.
+    pub fn rotate_90<R>(&mut self, reg: R)
+    where
+        R: Into<Region>,
+    {
+        let reg = self.intersection(reg);
+        self.transpose(reg);
+        let reg = Region::new(reg.x, reg.y, reg.height(), reg.width());
+        self.flip_horizontal(reg);
+    }
+
+    /// Rotate a region by 270 degrees clockwise (90 counter-clockwise).
+    ///
+    /// * `reg` Region within `self`.  It can be a `Region` struct, tuple of
+    ///   (*x*, *y*, *width*, *height*) or the unit type `()`.  Using
+    ///   `()` has the same result as `Raster::region()`.
+    ///
+    /// When `reg` covers the entire `Raster`, the width and height are
+    /// swapped.  Square regions are rotated in place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `reg` is not square and does not cover the entire `Raster`.
+    pub fn rotate_270<R>(&mut self, reg: R)
+    where
+        R: Into<Region>,
+    {
+        let reg = self.intersection(reg);
+        self.transpose(reg);
+        let reg = Region::new(reg.x, reg.y, reg.height(), reg.width());
+        self.flip_vertical(reg);
+    }
+
+    /// Transpose a square region in place, swapping blocks
+    fn transpose_square(&mut self, reg: Region) {
+        let n = reg.width as usize;
+        let stride = self.width as usize;
+        let base = reg.y as usize * stride + reg.x as usize;
+        for by in (0..n).step_by(BLOCK) {
+            for bx in (by..n).step_by(BLOCK) {
+                for y in by..n.min(by + BLOCK) {
+                    for x in bx.max(y + 1)..n.min(bx + BLOCK) {
+                        self.pixels
+                            .swap(base + y * stride + x, base + x * stride + y);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Transpose entire raster into a new buffer, swapping dimensions
+    fn transpose_all(&mut self) {
+        let (w, h) = (self.width as usize, self.height as usize);
+        let mut pixels = vec![P::default(); w * h].into_boxed_slice();
+        for by in (0..h).step_by(BLOCK) {
+            for bx in (0..w).step_by(BLOCK) {
+                for y in by..h.min(by + BLOCK) {
+                    for x in bx..w.min(bx + BLOCK) {
+                        pixels[x * h + y] = self.pixels[y * w + x];
+                    }
+                }
+            }
+        }
+        self.pixels = pixels;
+        std::mem::swap(&mut self.width, &mut self.height);
     }
 
     /// Get view of pixels as a `u8` slice.
@@ -586,21 +933,13 @@ where
         R: Into<Region>,
         O: Blend,
     {
-        let reg = self.intersection(reg.into());
-        let width = reg.width();
-        let height = reg.height();
-        if width > 0 && height > 0 {
-            let drows = self.rows_mut(reg);
-            for drow in drows {
-                P::composite_color(drow, &clr, op);
-            }
-        }
+        RasterMut::from(self).composite_color(reg, clr, op);
     }
 
     /// Composite from a matte `Raster` and color.
     ///
     /// * `to` Region within `self` (destination).
-    /// * `src` Source `Raster` matte.
+    /// * `src` Source `Raster` matte, `RasterRef` or `RasterMut`.
     /// * `from` Region within source `Raster`.
     /// * `clr` Color to apply to the matte.
     /// * `op` Compositing operation.
@@ -622,31 +961,27 @@ where
     /// let clr = Rgba8p::new(50, 100, 150, 200);
     /// r0.composite_matte((30, 50, 10, 10), &r1, (), clr, SrcOver);
     /// ```
-    pub fn composite_matte<R0, R1, M, O>(
+    pub fn composite_matte<'b, R0, R1, S, M, O>(
         &mut self,
         to: R0,
-        src: &Raster<M>,
+        src: S,
         from: R1,
         clr: P,
         op: O,
     ) where
         R0: Into<Region>,
         R1: Into<Region>,
-        M: Pixel<Chan = P::Chan, Model = Matte, Gamma = P::Gamma>,
+        S: Into<RasterRef<'b, M>>,
+        M: Pixel<Chan = P::Chan, Model = Matte, Gamma = P::Gamma> + 'b,
         O: Blend,
     {
-        let (to, from) = self.clip_regions(to, src, from);
-        let srows = src.rows(from);
-        let drows = self.rows_mut(to);
-        for (drow, srow) in drows.zip(srows) {
-            P::composite_matte(drow, srow, &clr, op);
-        }
+        RasterMut::from(self).composite_matte(to, src, from, clr, op);
     }
 
     /// Composite from a source `Raster`.
     ///
     /// * `to` Region within `self` (destination).
-    /// * `src` Source `Raster`.
+    /// * `src` Source `Raster`, `RasterRef` or `RasterMut`.
     /// * `from` Region within source `Raster`.
     /// * `op` Compositing operation.
     ///
@@ -680,38 +1015,79 @@ where
     /// // ... load image data
     /// r0.composite_raster((40, 40), &r1, (), SrcOver);
     /// ```
-    pub fn composite_raster<R0, R1, O>(
+    pub fn composite_raster<'b, R0, R1, S, O>(
         &mut self,
         to: R0,
-        src: &Raster<P>,
+        src: S,
         from: R1,
         op: O,
     ) where
         R0: Into<Region>,
         R1: Into<Region>,
+        S: Into<RasterRef<'b, P>>,
+        P: 'b,
         O: Blend,
     {
-        let (to, from) = self.clip_regions(to, src, from);
-        let srows = src.rows(from);
-        let drows = self.rows_mut(to);
-        for (drow, srow) in drows.zip(srows) {
-            P::composite_slice(drow, srow, op);
-        }
+        RasterMut::from(self).composite_raster(to, src, from, op);
     }
 }
 
+/// Check `Raster` dimensions.
+///
+/// # Returns
+/// Width, height and number of pixels.
+pub(crate) fn check_size(
+    width: u32,
+    height: u32,
+) -> Result<(i32, i32, usize), Error> {
+    let width = i32::try_from(width).map_err(|_| Error::WidthTooBig)?;
+    let height = i32::try_from(height).map_err(|_| Error::HeightTooBig)?;
+    let len = width.checked_mul(height).ok_or(Error::TooBig)?;
+    Ok((width, height, len as usize))
+}
+
+/// Convert a boxed buffer into boxed pixels.
+///
+/// * `len` Number of pixels.
+/// * `buffer` Buffer of pixel data (must have same alignment as `P`).
+fn boxed_pixels<P, T>(len: usize, buffer: Box<[T]>) -> Result<Box<[P]>, Error>
+where
+    P: Pixel,
+{
+    debug_assert_eq!(std::mem::align_of::<T>(), std::mem::align_of::<P>());
+    if len == 0 {
+        return Err(Error::ZeroSize);
+    }
+    let bytes = len
+        .checked_mul(std::mem::size_of::<P>())
+        .ok_or(Error::TooBig)?;
+    if bytes != buffer.len() * std::mem::size_of::<T>() {
+        return Err(Error::LengthMismatch);
+    }
+    let slice = Box::<[T]>::into_raw(buffer);
+    let pixels: Box<[P]> = unsafe {
+        let ptr = (*slice).as_mut_ptr() as *mut P;
+        Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, len))
+    };
+    Ok(pixels)
+}
+
 impl<'a, P: Pixel> Rows<'a, P> {
     /// Create a new row `Iterator`.
-    fn new(raster: &'a Raster<P>, reg: Region) -> Self {
-        let width = raster.width() as usize;
-        let start = reg.y as usize * width;
-        let end = reg.bottom() as usize * width;
-        let slice = &raster.pixels[start..end];
-        let chunks = slice.chunks_exact(width);
-        let x = reg.x as usize;
-        let w = reg.width as usize;
-        let columns = x..x + w;
-        Rows { chunks, columns }
+    ///
+    /// * `buf` Pixel data, starting at the top-left pixel.
+    /// * `stride` Distance between rows (bytes).
+    /// * `reg` Region to iterate (must be clipped to the pixel data).
+    pub(crate) fn new(buf: &'a [u8], stride: usize, reg: Region) -> Self {
+        let (start, columns, height) = row_span::<P>(stride, reg);
+        let buf = if height > 0 { &buf[start..] } else { &[] };
+        Rows {
+            buf,
+            stride,
+            columns,
+            height,
+            _pixel: PhantomData,
+        }
     }
 }
 
@@ -719,22 +1095,51 @@ impl<'a, P: Pixel> Iterator for Rows<'a, P> {
     type Item = &'a [P];
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.chunks.next().map(|s| &s[self.columns.clone()])
+        if self.height == 0 {
+            return None;
+        }
+        self.height -= 1;
+        let buf = self.buf;
+        let row = &buf[self.columns.clone()];
+        if self.height > 0 {
+            self.buf = &buf[self.stride..];
+        }
+        Some(pixel_slice(row))
+    }
+}
+
+impl<P: Pixel> DoubleEndedIterator for Rows<'_, P> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.height == 0 {
+            return None;
+        }
+        self.height -= 1;
+        let (rest, row) = self.buf.split_at(self.height * self.stride);
+        self.buf = rest;
+        Some(pixel_slice(&row[self.columns.clone()]))
     }
 }
 
 impl<'a, P: Pixel> RowsMut<'a, P> {
     /// Create a new mutable row `Iterator`.
-    fn new(raster: &'a mut Raster<P>, reg: Region) -> Self {
-        let width = raster.width() as usize;
-        let start = reg.y as usize * width;
-        let end = reg.bottom() as usize * width;
-        let slice = &mut raster.pixels[start..end];
-        let chunks = slice.chunks_exact_mut(width);
-        let x = reg.x as usize;
-        let w = reg.width as usize;
-        let columns = x..x + w;
-        RowsMut { chunks, columns }
+    ///
+    /// * `buf` Pixel data, starting at the top-left pixel.
+    /// * `stride` Distance between rows (bytes).
+    /// * `reg` Region to iterate (must be clipped to the pixel data).
+    pub(crate) fn new(buf: &'a mut [u8], stride: usize, reg: Region) -> Self {
+        let (start, columns, height) = row_span::<P>(stride, reg);
+        let buf = if height > 0 {
+            &mut buf[start..]
+        } else {
+            &mut []
+        };
+        RowsMut {
+            buf,
+            stride,
+            columns,
+            height,
+            _pixel: PhantomData,
+        }
     }
 }
 
@@ -742,10 +1147,61 @@ impl<'a, P: Pixel> Iterator for RowsMut<'a, P> {
     type Item = &'a mut [P];
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.chunks.next().map(|s| &mut s[self.columns.clone()])
+        if self.height == 0 {
+            return None;
+        }
+        self.height -= 1;
+        let buf = std::mem::take(&mut self.buf);
+        let (row, rest) = if self.height > 0 {
+            buf.split_at_mut(self.stride)
+        } else {
+            (buf, &mut [][..])
+        };
+        self.buf = rest;
+        Some(pixel_slice_mut(&mut row[self.columns.clone()]))
+    }
+}
+
+impl<P: Pixel> DoubleEndedIterator for RowsMut<'_, P> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.height == 0 {
+            return None;
+        }
+        self.height -= 1;
+        let buf = std::mem::take(&mut self.buf);
+        let (rest, row) = buf.split_at_mut(self.height * self.stride);
+        self.buf = rest;
+        Some(pixel_slice_mut(&mut row[self.columns.clone()]))
     }
 }
 
+/// Get byte offset, column range and height of rows within a region
+fn row_span<P: Pixel>(
+    stride: usize,
+    reg: Region,
+) -> (usize, Range<usize>, usize) {
+    let sz = std::mem::size_of::<P>();
+    let start = reg.y as usize * stride;
+    let x = reg.x as usize * sz;
+    let w = reg.width as usize * sz;
+    let height = if w > 0 { reg.height as usize } else { 0 };
+    (start, x..x + w, height)
+}
+
+/// Reinterpret a slice of bytes as pixels
+pub(crate) fn pixel_slice<P: Pixel>(buf: &[u8]) -> &[P] {
+    let (prefix, v, suffix) = unsafe { buf.align_to::<P>() };
+    assert!(prefix.is_empty() && suffix.is_empty());
+    v
+}
+
+/// Reinterpret a mutable slice of bytes as pixels
+pub(crate) fn pixel_slice_mut<P: Pixel>(buf: &mut [u8]) -> &mut [P] {
+    let (prefix, v, suffix) = unsafe { buf.align_to_mut::<P>() };
+    assert!(prefix.is_empty() && suffix.is_empty());
+    v
+}
+
 impl From<(i32, i32, u32, u32)> for Region {
     fn from(r: (i32, i32, u32, u32)) -> Self {
         Region::new(r.0, r.1, r.2, r.3)
@@ -879,6 +1335,34 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn try_with_errors() {
+        let r = Raster::<Gray8>::try_with_pixels(0x8000_0000, 1, vec![]);
+        assert_eq!(r.err(), Some(Error::WidthTooBig));
+        let r = Raster::<Gray8>::try_with_pixels(1, 0x8000_0000, vec![]);
+        assert_eq!(r.err(), Some(Error::HeightTooBig));
+        let r = Raster::<Gray8>::try_with_pixels(0x10000, 0x10000, vec![]);
+        assert_eq!(r.err(), Some(Error::TooBig));
+        let r = Raster::<Rgb8>::try_with_u8_buffer(0, 4, vec![]);
+        assert_eq!(r.err(), Some(Error::ZeroSize));
+        let r = Raster::<Rgb8>::try_with_u8_buffer(2, 2, vec![0; 11]);
+        assert_eq!(r.err(), Some(Error::LengthMismatch));
+        let r = Raster::<Rgb16>::try_with_u16_buffer(2, 2, vec![0; 13]);
+        assert_eq!(r.err(), Some(Error::LengthMismatch));
+        let r = Raster::<Rgb16>::try_with_u16_buffer(2, 2, vec![0; 12]);
+        assert!(r.is_ok());
+    }
+
+    #[test]
+    fn try_pixel() {
+        let mut r = Raster::<Gray8>::with_clear(2, 2);
+        *r.try_pixel_mut(1, 1).unwrap() = Gray8::new(0x40);
+        assert_eq!(r.try_pixel(1, 1), Ok(Gray8::new(0x40)));
+        assert_eq!(r.try_pixel(2, 1), Err(Error::OutOfBounds));
+        assert_eq!(r.try_pixel(0, -1), Err(Error::OutOfBounds));
+        assert!(r.try_pixel_mut(-1, 0).is_err());
+    }
+
     #[test]
     fn with_buffer_rgb8() {
         let b = vec![
@@ -1145,4 +1629,81 @@ mod test {
         let _ = Raster::<Matte16>::with_raster(&r);
         let _ = Raster::<Matte32>::with_raster(&r);
     }
+
+    #[test]
+    fn flip() {
+        let mut r = Raster::<Gray8>::with_clear(3, 3);
+        for (i, p) in r.pixels_mut().iter_mut().enumerate() {
+            *p = Gray8::new(i as u8);
+        }
+        r.flip_horizontal((0, 0, 2, 3));
+        r.flip_vertical((1, 0, 2, 3));
+        let v = [
+            1, 6, 8,
+            4, 3, 5,
+            7, 0, 2,
+        ];
+        assert_eq!(r.as_u8_slice(), &v);
+        r.rotate_180(());
+        let v = [
+            2, 0, 7,
+            5, 3, 4,
+            8, 6, 1,
+        ];
+        assert_eq!(r.as_u8_slice(), &v);
+    }
+
+    #[test]
+    fn transpose_square() {
+        let mut r = Raster::<Gray16>::with_clear(40, 40);
+        for (i, p) in r.pixels_mut().iter_mut().enumerate() {
+            *p = Gray16::new(i as u16);
+        }
+        let r0 = r.clone();
+        r.transpose(());
+        for y in 0..40 {
+            for x in 0..40 {
+                assert_eq!(r.pixel(x, y), r0.pixel(y, x));
+            }
+        }
+        r.transpose((1, 2, 30, 30));
+        assert_eq!(r.pixel(0, 0), r0.pixel(0, 0));
+        assert_eq!(r.pixel(1, 2), r0.pixel(2, 1));
+        assert_eq!(r.pixel(30, 2), r0.pixel(31, 1));
+        assert_eq!(r.pixel(31, 2), r0.pixel(2, 31));
+    }
+
+    #[test]
+    fn rotate() {
+        let mut r = Raster::<Gray8>::with_u8_buffer(3, 2, [
+            1, 2, 3,
+            4, 5, 6,
+        ]);
+        r.rotate_90(());
+        assert_eq!((r.width(), r.height()), (2, 3));
+        let v = [
+            4, 1,
+            5, 2,
+            6, 3,
+        ];
+        assert_eq!(r.as_u8_slice(), &v);
+        r.rotate_270(());
+        assert_eq!(r.as_u8_slice(), &[1, 2, 3, 4, 5, 6]);
+        r.rotate_270(());
+        let v = [
+            3, 6,
+            2, 5,
+            1, 4,
+        ];
+        assert_eq!(r.as_u8_slice(), &v);
+        r.rotate_90((0, 1, 2, 2));
+        assert_eq!(r.as_u8_slice(), &[3, 6, 1, 2, 4, 5]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rotate_not_square() {
+        let mut r = Raster::<Gray8>::with_clear(4, 4);
+        r.rotate_90((0, 0, 2, 3));
+    }
 }
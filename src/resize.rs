@@ -0,0 +1,560 @@
+// resize.rs    Image resampling.
+//
+// Copyright (c) 2023  Douglas P Lau
+//
+use crate::chan::{Alpha, Ch32, Channel, Gamma, Premultiplied};
+use crate::el::Pixel;
+use crate::raster::Raster;
+use crate::ColorModel;
+use std::f32::consts::{PI, TAU};
+
+/// Resampling filter for [Raster::resize].
+///
+/// [Raster::resize]: struct.Raster.html#method.resize
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResizeFilter {
+    /// Nearest neighbor (no filtering)
+    Nearest,
+    /// Box filter (area average when reducing)
+    Box,
+    /// Bilinear (triangle) filter
+    Bilinear,
+    /// Catmull-Rom bicubic filter (sharp)
+    CatmullRom,
+    /// Mitchell-Netravali bicubic filter (B = C = ⅓)
+    Mitchell,
+    /// Lanczos filter with 3 lobes
+    Lanczos3,
+}
+
+/// Filter taps for one destination sample
+struct Taps {
+    /// First source sample
+    start: usize,
+    /// Normalized weights
+    weights: Vec<f32>,
+}
+
+impl ResizeFilter {
+    /// Get the support radius (unscaled)
+    fn support(self) -> f32 {
+        match self {
+            ResizeFilter::Nearest | ResizeFilter::Box => 0.5,
+            ResizeFilter::Bilinear => 1.0,
+            ResizeFilter::CatmullRom | ResizeFilter::Mitchell => 2.0,
+            ResizeFilter::Lanczos3 => 3.0,
+        }
+    }
+
+    /// Get the filter weight at a distance from the center
+    fn weight(self, x: f32) -> f32 {
+        match self {
+            ResizeFilter::Nearest | ResizeFilter::Box => {
+                if (-0.5..0.5).contains(&x) {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            ResizeFilter::Bilinear => (1.0 - x.abs()).max(0.0),
+            ResizeFilter::CatmullRom => cubic(0.0, 0.5, x),
+            ResizeFilter::Mitchell => cubic(1.0 / 3.0, 1.0 / 3.0, x),
+            ResizeFilter::Lanczos3 => {
+                if x.abs() < 3.0 {
+                    sinc(x) * sinc(x / 3.0)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    /// Calculate taps for resampling one dimension
+    fn taps(self, src_len: usize, dst_len: usize) -> Vec<Taps> {
+        let scale = src_len as f32 / dst_len as f32;
+        let fscale = scale.max(1.0);
+        let support = self.support() * fscale;
+        (0..dst_len)
+            .map(|i| {
+                let center = (i as f32 + 0.5) * scale;
+                let nearest = (center as usize).min(src_len - 1);
+                if self == ResizeFilter::Nearest {
+                    return Taps {
+                        start: nearest,
+                        weights: vec![1.0],
+                    };
+                }
+                if self == ResizeFilter::Box && scale > 1.0 {
+                    return area_taps(i, scale, src_len);
+                }
+                let start = (center - support).floor().max(0.0) as usize;
+                let end = ((center + support).ceil() as usize).min(src_len);
+                let mut weights: Vec<f32> = (start..end)
+                    .map(|j| self.weight((j as f32 + 0.5 - center) / fscale))
+                    .collect();
+                let sum: f32 = weights.iter().sum();
+                if sum.abs() < f32::EPSILON {
+                    return Taps {
+                        start: nearest,
+                        weights: vec![1.0],
+                    };
+                }
+                weights.iter_mut().for_each(|w| *w /= sum);
+                Taps { start, weights }
+            })
+            .collect()
+    }
+}
+
+/// Calculate area-weighted box filter taps for reducing one dimension
+fn area_taps(i: usize, scale: f32, src_len: usize) -> Taps {
+    let lo = i as f32 * scale;
+    let hi = lo + scale;
+    let start = lo.floor() as usize;
+    let end = (hi.ceil() as usize).min(src_len);
+    let weights = (start..end)
+        .map(|j| {
+            let j = j as f32;
+            (hi.min(j + 1.0) - lo.max(j)) / scale
+        })
+        .collect();
+    Taps { start, weights }
+}
+
+/// Cubic filter with parameters `b` and `c` (Mitchell-Netravali)
+fn cubic(b: f32, c: f32, x: f32) -> f32 {
+    let x = x.abs();
+    let v = if x < 1.0 {
+        (12.0 - 9.0 * b - 6.0 * c) * x * x * x
+            + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+            + (6.0 - 2.0 * b)
+    } else if x < 2.0 {
+        (-b - 6.0 * c) * x * x * x
+            + (6.0 * b + 30.0 * c) * x * x
+            + (-12.0 * b - 48.0 * c) * x
+            + (8.0 * b + 24.0 * c)
+    } else {
+        0.0
+    };
+    v / 6.0
+}
+
+/// Normalized sinc function
+fn sinc(x: f32) -> f32 {
+    if x == 0.0 {
+        1.0
+    } else {
+        let x = x * PI;
+        x.sin() / x
+    }
+}
+
+/// Get number of working values for one pixel.
+///
+/// Circular channels use two values (cosine and sine).
+pub(crate) fn work_len<P: Pixel>() -> usize {
+    P::default().channels().len() + P::Model::CIRCULAR.len()
+}
+
+/// Convert a pixel to working values (linear, premultiplied `f32`)
+pub(crate) fn to_work<P: Pixel>(p: P, work: &mut [f32]) {
+    let alpha = Ch32::new(p.alpha().to_f32());
+    let mut w = work.iter_mut();
+    for (i, c) in p.channels().iter().enumerate() {
+        if P::Model::CIRCULAR.contains(&i) {
+            let angle = c.to_f32() * TAU;
+            if let Some(v) = w.next() {
+                *v = angle.cos() * alpha.to_f32();
+            }
+            if let Some(v) = w.next() {
+                *v = angle.sin() * alpha.to_f32();
+            }
+        } else if let Some(v) = w.next() {
+            *v = if P::Model::LINEAR.contains(&i) {
+                let c = P::Gamma::to_linear(Ch32::new(c.to_f32()));
+                let c = P::Alpha::decode(c, alpha);
+                Premultiplied::encode(c, alpha).to_f32()
+            } else {
+                c.to_f32()
+            };
+        }
+    }
+}
+
+/// Convert working values to a pixel
+pub(crate) fn from_work<P: Pixel>(work: &[f32]) -> P {
+    let mut p = P::default();
+    let alpha = match work_index::<P>(P::Model::ALPHA) {
+        Some(i) => Ch32::new(work[i]),
+        None => Ch32::MAX,
+    };
+    let mut w = work.iter();
+    for (i, c) in p.channels_mut().iter_mut().enumerate() {
+        if P::Model::CIRCULAR.contains(&i) {
+            let x = w.next().copied().unwrap_or_default();
+            let y = w.next().copied().unwrap_or_default();
+            *c = P::Chan::from(y.atan2(x).rem_euclid(TAU) / TAU);
+        } else if let Some(v) = w.next() {
+            *c = if P::Model::LINEAR.contains(&i) {
+                let v = Premultiplied::decode(Ch32::new(*v), alpha);
+                let v = P::Alpha::encode(v, alpha);
+                P::Chan::from(P::Gamma::from_linear(v).to_f32())
+            } else {
+                P::Chan::from(*v)
+            };
+        }
+    }
+    p
+}
+
+/// Get the working value index of a channel
+pub(crate) fn work_index<P: Pixel>(chan: usize) -> Option<usize> {
+    let n_chan = P::default().channels().len();
+    if chan < n_chan {
+        let circ = P::Model::CIRCULAR.filter(|c| *c < chan).count();
+        Some(chan + circ)
+    } else {
+        None
+    }
+}
+
+/// Resample one dimension of a working buffer.
+///
+/// * `src` Source values, with `count` lines of samples, each sample
+///   `n` values.  Consecutive samples are `step` values apart and
+///   consecutive lines are `line` values apart.
+/// * `dst` Destination values, with the same layout (`dst_line` /
+///   `dst_step`).
+fn resample_lines(
+    src: &[f32],
+    (count, line, step): (usize, usize, usize),
+    taps: &[Taps],
+    n: usize,
+    dst: &mut [f32],
+    (dst_line, dst_step): (usize, usize),
+) {
+    for l in 0..count {
+        for (i, tap) in taps.iter().enumerate() {
+            let d = l * dst_line + i * dst_step;
+            let out = &mut dst[d..d + n];
+            out.fill(0.0);
+            for (j, w) in tap.weights.iter().enumerate() {
+                let s = l * line + (tap.start + j) * step;
+                for (o, v) in out.iter_mut().zip(&src[s..s + n]) {
+                    *o += v * w;
+                }
+            }
+        }
+    }
+}
+
+/// Convert all pixels of a `Raster` to working values
+pub(crate) fn raster_to_work<P: Pixel>(raster: &Raster<P>) -> Vec<f32> {
+    let n = work_len::<P>();
+    let mut work = vec![0.0; raster.pixels().len() * n];
+    for (p, w) in raster.pixels().iter().zip(work.chunks_exact_mut(n)) {
+        to_work(*p, w);
+    }
+    work
+}
+
+/// Convert working values to a `Raster`
+pub(crate) fn raster_from_work<P: Pixel>(
+    width: u32,
+    height: u32,
+    work: &[f32],
+) -> Raster<P> {
+    let n = work_len::<P>();
+    let mut raster = Raster::with_clear(width, height);
+    for (p, w) in raster.pixels_mut().iter_mut().zip(work.chunks_exact(n)) {
+        *p = from_work(w);
+    }
+    raster
+}
+
+/// Resample working values to new dimensions
+fn resample_work(
+    src: &[f32],
+    (sw, sh): (usize, usize),
+    (dw, dh): (usize, usize),
+    n: usize,
+    filter: ResizeFilter,
+) -> Vec<f32> {
+    // horizontal pass: sh rows of dw samples
+    let taps = filter.taps(sw, dw);
+    let mut tmp = vec![0.0; dw * sh * n];
+    resample_lines(src, (sh, sw * n, n), &taps, n, &mut tmp, (dw * n, n));
+    // vertical pass: dw columns of dh samples
+    let taps = filter.taps(sh, dh);
+    let mut work = vec![0.0; dw * dh * n];
+    resample_lines(&tmp, (dw, n, dw * n), &taps, n, &mut work, (n, dw * n));
+    work
+}
+
+/// Get fraction of pixels with *alpha* above a cutoff after scaling
+fn alpha_coverage(
+    work: &[f32],
+    n: usize,
+    a: usize,
+    scale: f32,
+    cutoff: f32,
+) -> f32 {
+    let len = work.len() / n;
+    let count = work
+        .chunks_exact(n)
+        .filter(|w| w[a] * scale > cutoff)
+        .count();
+    count as f32 / len as f32
+}
+
+/// Scale *alpha* of working values to match a coverage
+fn scale_coverage(
+    work: &mut [f32],
+    n: usize,
+    a: usize,
+    cutoff: f32,
+    target: f32,
+) {
+    let (mut lo, mut hi) = (0.0, 1.0);
+    while alpha_coverage(work, n, a, hi, cutoff) < target && hi < 256.0 {
+        hi *= 2.0;
+    }
+    let mut scale = 1.0;
+    for _ in 0..16 {
+        let coverage = alpha_coverage(work, n, a, scale, cutoff);
+        if coverage < target {
+            lo = scale;
+        } else if coverage > target {
+            hi = scale;
+        } else {
+            break;
+        }
+        scale = (lo + hi) * 0.5;
+    }
+    if alpha_coverage(work, n, a, scale, cutoff) < target {
+        scale = hi;
+    }
+    for w in work.chunks_exact_mut(n) {
+        if w[a] > 0.0 {
+            // scale premultiplied channels along with alpha
+            let factor = (w[a] * scale).min(1.0) / w[a];
+            w.iter_mut().for_each(|v| *v *= factor);
+        }
+    }
+}
+
+impl<P: Pixel> Raster<P> {
+    /// Resize to new dimensions using a resampling filter.
+    ///
+    /// Filtering is separable, with weights precomputed for each row and
+    /// column.  Channels are resampled with linear gamma and premultiplied
+    /// *alpha*, then converted back to the pixel format.  Circular channels
+    /// (*hue*) are resampled as angles.
+    ///
+    /// * `width` Width of resized `Raster`.
+    /// * `height` Height of resized `Raster`.
+    /// * `filter` Resampling filter.
+    ///
+    /// ### Reduce an image
+    /// ```
+    /// use pix::rgb::SRgba8;
+    /// use pix::{Raster, ResizeFilter};
+    ///
+    /// let mut r = Raster::with_clear(4, 4);
+    /// r.copy_color((0, 0, 2, 4), SRgba8::new(0xFF, 0xFF, 0xFF, 0xFF));
+    /// r.copy_color((2, 0, 2, 4), SRgba8::new(0, 0, 0, 0xFF));
+    /// let r = r.resize(1, 1, ResizeFilter::Box);
+    /// // averaged in linear light, not 0x80
+    /// assert_eq!(r.pixel(0, 0), SRgba8::new(0xBC, 0xBC, 0xBC, 0xFF));
+    /// ```
+    pub fn resize(
+        &self,
+        width: u32,
+        height: u32,
+        filter: ResizeFilter,
+    ) -> Self {
+        let (sw, sh) = (self.width() as usize, self.height() as usize);
+        let (dw, dh) = (width as usize, height as usize);
+        if sw == 0 || sh == 0 || dw == 0 || dh == 0 {
+            return Raster::with_clear(width, height);
+        }
+        let n = work_len::<P>();
+        let src = raster_to_work(self);
+        let work = resample_work(&src, (sw, sh), (dw, dh), n, filter);
+        raster_from_work(width, height, &work)
+    }
+
+    /// Make a chain of mipmap levels.
+    ///
+    /// The first level is a copy of `self`, and each following level has
+    /// half the width and height of the previous level (rounded down), until
+    /// reaching 1x1.  Levels are filtered with an area-weighted box filter,
+    /// with linear gamma and premultiplied *alpha*.
+    ///
+    /// ### Make mipmaps for a texture
+    /// ```
+    /// use pix::rgb::SRgba8;
+    /// use pix::Raster;
+    ///
+    /// let r = Raster::with_color(5, 3, SRgba8::new(0x80, 0x40, 0x20, 0xFF));
+    /// let mips = r.mip_chain();
+    /// assert_eq!(mips.len(), 3);
+    /// assert_eq!((mips[1].width(), mips[1].height()), (2, 1));
+    /// assert_eq!(mips[2].pixel(0, 0), SRgba8::new(0x80, 0x40, 0x20, 0xFF));
+    /// ```
+    pub fn mip_chain(&self) -> Vec<Self> {
+        self.make_mip_chain(None)
+    }
+
+    /// Make a chain of mipmap levels, preserving *alpha* coverage.
+    ///
+    /// This is the same as [mip_chain](#method.mip_chain), except that the
+    /// *alpha* of each level is scaled so that the fraction of pixels
+    /// passing an alpha test is the same as in the first level.  This keeps
+    /// alpha-tested geometry, such as foliage, from thinning out at a
+    /// distance.
+    ///
+    /// * `cutoff` *Alpha* test reference value.
+    pub fn mip_chain_coverage(&self, cutoff: P::Chan) -> Vec<Self> {
+        self.make_mip_chain(Some(cutoff.to_f32()))
+    }
+
+    /// Make a chain of mipmap levels
+    fn make_mip_chain(&self, cutoff: Option<f32>) -> Vec<Self> {
+        let mut levels = vec![self.clone()];
+        let (mut w, mut h) = (self.width() as usize, self.height() as usize);
+        if w == 0 || h == 0 {
+            return levels;
+        }
+        let n = work_len::<P>();
+        let alpha = work_index::<P>(P::Model::ALPHA);
+        let mut work = raster_to_work(self);
+        let coverage = match (cutoff, alpha) {
+            (Some(cutoff), Some(a)) => {
+                Some((cutoff, a, alpha_coverage(&work, n, a, 1.0, cutoff)))
+            }
+            _ => None,
+        };
+        while w > 1 || h > 1 {
+            let (dw, dh) = ((w / 2).max(1), (h / 2).max(1));
+            work = resample_work(&work, (w, h), (dw, dh), n, ResizeFilter::Box);
+            (w, h) = (dw, dh);
+            let level = match coverage {
+                Some((cutoff, a, target)) => {
+                    let mut scaled = work.clone();
+                    scale_coverage(&mut scaled, n, a, cutoff, target);
+                    raster_from_work(w as u32, h as u32, &scaled)
+                }
+                None => raster_from_work(w as u32, h as u32, &work),
+            };
+            levels.push(level);
+        }
+        levels
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gray::*;
+    use crate::hsv::*;
+    use crate::rgb::*;
+
+    #[test]
+    fn taps() {
+        let t = ResizeFilter::Bilinear.taps(2, 4);
+        assert_eq!(t[0].start, 0);
+        assert_eq!(t[0].weights, [1.0, 0.0]);
+        assert_eq!(t[1].weights, [0.75, 0.25]);
+        let t = ResizeFilter::Box.taps(4, 2);
+        assert_eq!(t[1].start, 2);
+        assert_eq!(t[1].weights, [0.5, 0.5]);
+        let t = ResizeFilter::Nearest.taps(3, 2);
+        assert_eq!((t[0].start, t[1].start), (0, 2));
+        for f in [
+            ResizeFilter::CatmullRom,
+            ResizeFilter::Mitchell,
+            ResizeFilter::Lanczos3,
+        ] {
+            for t in f.taps(17, 5).iter().chain(&f.taps(5, 17)) {
+                let sum: f32 = t.weights.iter().sum();
+                assert!((sum - 1.0).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn resize_gray() {
+        let mut r = Raster::<Gray8>::with_clear(4, 1);
+        r.copy_color((0, 0, 2, 1), Gray8::new(0xFF));
+        let r2 = r.resize(2, 1, ResizeFilter::Box);
+        assert_eq!(r2.as_u8_slice(), &[0xFF, 0]);
+        let r2 = r.resize(1, 1, ResizeFilter::Box);
+        assert_eq!(r2.as_u8_slice(), &[0x80]);
+        let r2 = r.resize(8, 2, ResizeFilter::Nearest);
+        assert_eq!(r2.as_u8_slice()[..8], [0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0]);
+        for f in [
+            ResizeFilter::CatmullRom,
+            ResizeFilter::Mitchell,
+            ResizeFilter::Lanczos3,
+        ] {
+            let r = Raster::with_color(5, 3, Gray16::new(0x1234));
+            let r2 = r.resize(9, 11, f);
+            assert!(r2.pixels().iter().all(|p| *p == Gray16::new(0x1234)));
+        }
+    }
+
+    #[test]
+    fn resize_straight_alpha() {
+        let mut r = Raster::<SRgba8>::with_clear(2, 1);
+        *r.pixel_mut(0, 0) = SRgba8::new(0xFF, 0, 0, 0xFF);
+        *r.pixel_mut(1, 0) = SRgba8::new(0, 0xFF, 0, 0);
+        let r = r.resize(1, 1, ResizeFilter::Bilinear);
+        // transparent green does not bleed into result
+        assert_eq!(r.pixel(0, 0), SRgba8::new(0xFF, 0, 0, 0x80));
+    }
+
+    #[test]
+    fn area_taps() {
+        let t = ResizeFilter::Box.taps(5, 2);
+        assert_eq!(t[0].start, 0);
+        assert_eq!(t[0].weights, [0.4, 0.4, 0.2]);
+        assert_eq!(t[1].start, 2);
+        assert_eq!(t[1].weights, [0.2, 0.4, 0.4]);
+    }
+
+    #[test]
+    fn mip_chain() {
+        let r = Raster::<Gray8>::with_clear(7, 2);
+        let mips = r.mip_chain();
+        let dims: Vec<_> =
+            mips.iter().map(|m| (m.width(), m.height())).collect();
+        assert_eq!(dims, [(7, 2), (3, 1), (1, 1)]);
+        let mut r = Raster::<Rgba8p>::with_clear(2, 2);
+        *r.pixel_mut(0, 0) = Rgba8p::new(0xFF, 0, 0, 0xFF);
+        let mips = r.mip_chain();
+        assert_eq!(mips[1].pixel(0, 0), Rgba8p::new(0x40, 0, 0, 0x40));
+    }
+
+    #[test]
+    fn mip_coverage() {
+        let mut r = Raster::<SRgba8>::with_clear(4, 4);
+        r.copy_color((0, 0, 4, 2), SRgba8::new(0, 0xFF, 0, 0xC0));
+        let mips = r.mip_chain();
+        assert_eq!(mips[2].pixel(0, 0), SRgba8::new(0, 0xFF, 0, 0x60));
+        let mips = r.mip_chain_coverage(0x80.into());
+        assert_eq!(mips[1].pixel(1, 0), SRgba8::new(0, 0xFF, 0, 0xC0));
+        let a = mips[2].pixel(0, 0).alpha();
+        assert!(a >= 0x80.into() && a < 0x84.into());
+    }
+
+    #[test]
+    fn resize_hue() {
+        let mut r = Raster::<Hsv8>::with_clear(2, 1);
+        *r.pixel_mut(0, 0) = Hsv8::new(0xF7, 0xFF, 0xFF);
+        *r.pixel_mut(1, 0) = Hsv8::new(0x08, 0xFF, 0xFF);
+        let r = r.resize(1, 1, ResizeFilter::Box);
+        let h = r.pixel(0, 0).one();
+        assert!(h == 0.into() || h == 0xFF.into());
+    }
+}
@@ -0,0 +1,322 @@
+// sdf.rs       Signed distance fields
+//
+// Copyright (c) 2023  Douglas P Lau
+//
+use crate::chan::Channel;
+use crate::el::Pixel;
+use crate::matte::Matte;
+use crate::raster::Raster;
+
+/// Squared distance for pixels with no seed
+const FAR: f32 = 1.0e20;
+
+/// Scratch buffers for distance transform
+struct Scratch {
+    /// Input squared distances
+    f: Vec<f32>,
+    /// Output squared distances
+    d: Vec<f32>,
+    /// Locations of parabolas in lower envelope
+    v: Vec<usize>,
+    /// Boundaries between parabolas
+    z: Vec<f32>,
+}
+
+impl Scratch {
+    /// Create scratch buffers for lines of a given length
+    fn new(len: usize) -> Self {
+        Scratch {
+            f: vec![0.0; len],
+            d: vec![0.0; len],
+            v: vec![0; len],
+            z: vec![0.0; len + 1],
+        }
+    }
+
+    /// Transform one line of squared distances.
+    ///
+    /// See "Distance Transforms of Sampled Functions" (Felzenszwalb &
+    /// Huttenlocher, 2012).
+    fn transform(&mut self, len: usize) {
+        let Scratch { f, d, v, z } = self;
+        let intersect = |q: usize, p: usize| {
+            let (qf, pf) = (q as f32, p as f32);
+            ((f[q] + qf * qf) - (f[p] + pf * pf)) / (2.0 * (qf - pf))
+        };
+        let mut k = 0;
+        v[0] = 0;
+        z[0] = f32::NEG_INFINITY;
+        z[1] = f32::INFINITY;
+        for q in 1..len {
+            let mut s = intersect(q, v[k]);
+            while s <= z[k] {
+                k -= 1;
+                s = intersect(q, v[k]);
+            }
+            k += 1;
+            v[k] = q;
+            z[k] = s;
+            z[k + 1] = f32::INFINITY;
+        }
+        k = 0;
+        for (q, dq) in d.iter_mut().enumerate().take(len) {
+            while z[k + 1] < q as f32 {
+                k += 1;
+            }
+            let dist = q as f32 - v[k] as f32;
+            *dq = dist * dist + f[v[k]];
+        }
+    }
+}
+
+/// Exact Euclidean distance transform.
+///
+/// * `seeds` Seed flags for all pixels.
+/// * `width` Width in pixels.
+///
+/// Returns the distance from each pixel to the nearest seed.
+fn distance_transform(seeds: &[bool], width: usize) -> Vec<f32> {
+    let height = seeds.len() / width;
+    let mut dist: Vec<f32> =
+        seeds.iter().map(|s| if *s { 0.0 } else { FAR }).collect();
+    let mut scratch = Scratch::new(width.max(height));
+    for x in 0..width {
+        for (f, v) in
+            scratch.f.iter_mut().zip(dist.iter().skip(x).step_by(width))
+        {
+            *f = *v;
+        }
+        scratch.transform(height);
+        let col = dist.iter_mut().skip(x).step_by(width);
+        for (v, d) in col.zip(&scratch.d) {
+            *v = *d;
+        }
+    }
+    for row in dist.chunks_exact_mut(width) {
+        scratch.f[..width].copy_from_slice(row);
+        scratch.transform(width);
+        row.copy_from_slice(&scratch.d[..width]);
+    }
+    dist.iter_mut().for_each(|d| *d = d.sqrt());
+    dist
+}
+
+impl<P> Raster<P>
+where
+    P: Pixel<Model = Matte>,
+{
+    /// Get signed distances to the edge of a coverage mask.
+    ///
+    /// Pixels with *alpha* of at least half are inside.  The distance to
+    /// the nearest edge is exact (Euclidean), and partially covered pixels
+    /// are refined using their coverage.
+    ///
+    /// Returns one distance per pixel, in row-major order.  Distances are in
+    /// pixels, positive inside and negative outside, and are not clamped.  If
+    /// the mask has no edge, all distances are infinite.
+    ///
+    /// Unlike [with_sdf], which encodes clamped distances into channel
+    /// values, this is suitable for measuring, or custom encodings.
+    ///
+    /// ### Measure distances
+    /// ```
+    /// use pix::matte::Matte8;
+    /// use pix::Raster;
+    ///
+    /// let mut mask = Raster::with_clear(16, 16);
+    /// mask.copy_color((0, 0, 8, 16), Matte8::new(0xFF));
+    /// let dist = mask.signed_distances();
+    /// assert_eq!(dist[8 * 16], 7.5);
+    /// assert_eq!(dist[8 * 16 + 15], -7.5);
+    /// ```
+    ///
+    /// [with_sdf]: #method.with_sdf
+    pub fn signed_distances(&self) -> Vec<f32> {
+        let coverage: Vec<f32> =
+            self.pixels().iter().map(|p| p.alpha().to_f32()).collect();
+        let inside: Vec<bool> = coverage.iter().map(|c| *c >= 0.5).collect();
+        if inside.iter().all(|i| *i) {
+            return vec![f32::INFINITY; inside.len()];
+        }
+        if !inside.iter().any(|i| *i) {
+            return vec![f32::NEG_INFINITY; inside.len()];
+        }
+        let outside: Vec<bool> = inside.iter().map(|i| !i).collect();
+        let to_inside = distance_transform(&inside, self.width() as usize);
+        let to_outside = distance_transform(&outside, self.width() as usize);
+        coverage
+            .iter()
+            .zip(to_inside.iter().zip(&to_outside))
+            .map(|(c, (di, dout))| {
+                if *c > 0.0 && *c < 1.0 {
+                    c - 0.5
+                } else if *c >= 0.5 {
+                    dout - 0.5
+                } else {
+                    0.5 - di
+                }
+            })
+            .collect()
+    }
+
+    /// Create a signed distance field from a coverage mask.
+    ///
+    /// * `mask` Coverage mask.  Pixels with *alpha* of at least half are
+    ///   inside.
+    /// * `spread` Maximum distance to encode, in pixels.
+    ///
+    /// Distances are computed as by [signed_distances], then encoded with
+    /// `0.5` on the edge, increasing to `1.0` at `spread` pixels inside and
+    /// decreasing to `0.0` at `spread` pixels outside.  Distances beyond
+    /// `spread` are clamped.
+    ///
+    /// ### Create an SDF
+    /// ```
+    /// use pix::matte::{Matte32, Matte8};
+    /// use pix::Raster;
+    ///
+    /// let mut mask = Raster::with_clear(16, 16);
+    /// mask.copy_color((0, 0, 8, 16), Matte8::new(0xFF));
+    /// let sdf = Raster::<Matte32>::with_sdf(&mask, 4.0);
+    /// assert_eq!(sdf.pixel(7, 8), Matte32::new(0.5625));
+    /// assert_eq!(sdf.pixel(8, 8), Matte32::new(0.4375));
+    /// assert_eq!(sdf.pixel(15, 8), Matte32::new(0.0));
+    /// ```
+    ///
+    /// [signed_distances]: #method.signed_distances
+    pub fn with_sdf<S>(mask: &Raster<S>, spread: f32) -> Self
+    where
+        S: Pixel<Model = Matte>,
+    {
+        let mut sdf: Self = Raster::with_clear(mask.width(), mask.height());
+        let scale = 0.5 / spread.max(f32::EPSILON);
+        let dist = mask.signed_distances();
+        for (p, d) in sdf.pixels_mut().iter_mut().zip(dist) {
+            *p.one_mut() = P::Chan::from((0.5 + d * scale).clamp(0.0, 1.0));
+        }
+        sdf
+    }
+
+    /// Render a signed distance field to coverage.
+    ///
+    /// * `sdf` Signed distance field, encoded as by [with_sdf].
+    /// * `threshold` Encoded distance of the edge; `0.5` is the original
+    ///   edge, with lower values growing and higher values shrinking.
+    /// * `softness` Width of the anti-aliased transition, in encoded
+    ///   distance.  With `0.0`, the edge is hard.
+    ///
+    /// ### Render an outline
+    /// ```
+    /// use pix::matte::{Matte32, Matte8};
+    /// use pix::Raster;
+    ///
+    /// let mut mask = Raster::with_clear(16, 16);
+    /// mask.copy_color((4, 4, 8, 8), Matte8::new(0xFF));
+    /// let sdf = Raster::<Matte32>::with_sdf(&mask, 4.0);
+    /// let bold = Raster::<Matte8>::with_sdf_coverage(&sdf, 0.25, 0.1);
+    /// assert_eq!(bold.pixel(2, 8), Matte8::new(0xFF));
+    /// assert_eq!(bold.pixel(0, 8), Matte8::new(0));
+    /// ```
+    ///
+    /// [with_sdf]: #method.with_sdf
+    pub fn with_sdf_coverage<S>(
+        sdf: &Raster<S>,
+        threshold: f32,
+        softness: f32,
+    ) -> Self
+    where
+        S: Pixel<Model = Matte>,
+    {
+        let mut cov: Raster<P> = Raster::with_clear(sdf.width(), sdf.height());
+        let half = softness.max(0.0) * 0.5;
+        for (p, s) in cov.pixels_mut().iter_mut().zip(sdf.pixels()) {
+            let v = s.alpha().to_f32();
+            let c = if half > 0.0 {
+                let t = ((v - threshold + half) / (half * 2.0)).clamp(0.0, 1.0);
+                t * t * (3.0 - 2.0 * t)
+            } else if v >= threshold {
+                1.0
+            } else {
+                0.0
+            };
+            *p.one_mut() = P::Chan::from(c);
+        }
+        cov
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matte::*;
+
+    #[test]
+    fn distance() {
+        #[rustfmt::skip]
+        let seeds = [
+            false, false, false, false,
+            false, true,  false, false,
+            false, false, false, false,
+        ];
+        let d = distance_transform(&seeds, 4);
+        assert_eq!(d[5], 0.0);
+        assert_eq!(d[4], 1.0);
+        assert_eq!(d[0], 2.0f32.sqrt());
+        assert_eq!(d[11], 5.0f32.sqrt());
+        let d = distance_transform(&[false; 6], 3);
+        assert!(d.iter().all(|v| *v > 1.0e9));
+    }
+
+    #[test]
+    fn disk_sdf() {
+        let mut mask = Raster::<Matte8>::with_clear(21, 21);
+        for y in 0..21 {
+            for x in 0..21 {
+                let (dx, dy) = (x as f32 - 10.0, y as f32 - 10.0);
+                if dx.hypot(dy) < 6.0 {
+                    *mask.pixel_mut(x, y) = Matte8::new(0xFF);
+                }
+            }
+        }
+        let sdf = Raster::<Matte32>::with_sdf(&mask, 8.0);
+        let center = sdf.pixel(10, 10).alpha().to_f32();
+        assert!((center - (0.5 + 5.5 / 16.0)).abs() < 0.01);
+        let sdf8 = Raster::<Matte8>::with_sdf(&mask, 8.0);
+        let cov = Raster::<Matte8>::with_sdf_coverage(&sdf8, 0.5, 0.0);
+        assert_eq!(cov.pixels(), mask.pixels());
+    }
+
+    #[test]
+    fn antialiased() {
+        let mut mask = Raster::<Matte8>::with_clear(4, 1);
+        *mask.pixel_mut(0, 0) = Matte8::new(0xFF);
+        *mask.pixel_mut(1, 0) = Matte8::new(0xBF);
+        *mask.pixel_mut(2, 0) = Matte8::new(0x40);
+        let sdf = Raster::<Matte32>::with_sdf(&mask, 2.0);
+        let v: Vec<f32> =
+            sdf.pixels().iter().map(|p| p.alpha().to_f32()).collect();
+        assert!(v.windows(2).all(|w| w[0] > w[1]));
+        let cov = Raster::<Matte32>::with_sdf_coverage(&sdf, 0.5, 0.5);
+        let c = cov.pixel(1, 0).alpha().to_f32();
+        assert!(c > 0.5 && c < 1.0);
+    }
+
+    #[test]
+    fn unclamped() {
+        let mut mask = Raster::<Matte8>::with_clear(40, 1);
+        mask.copy_color((0, 0, 10, 1), Matte8::new(0xFF));
+        let dist = mask.signed_distances();
+        assert_eq!(dist[0], 9.5);
+        assert_eq!(dist[10], -0.5);
+        assert_eq!(dist[39], -29.5);
+        let sdf = Raster::<Matte32>::with_sdf(&mask, 4.0);
+        assert_eq!(sdf.pixel(39, 0), Matte32::new(0.0));
+        let empty = Raster::<Matte8>::with_clear(3, 2).signed_distances();
+        assert_eq!(empty, vec![f32::NEG_INFINITY; 6]);
+        let full = Raster::<Matte8>::with_color(2, 2, Matte8::new(0xFF));
+        assert_eq!(full.signed_distances(), vec![f32::INFINITY; 4]);
+        assert!(Raster::<Matte8>::with_clear(0, 0)
+            .signed_distances()
+            .is_empty());
+    }
+}
@@ -0,0 +1,289 @@
+// stats.rs     Histograms and statistics
+//
+// Copyright (c) 2023  Douglas P Lau
+//
+use crate::chan::{Ch16, Ch8, Channel};
+use crate::el::Pixel;
+use crate::raster::{Raster, Region};
+use std::any::{Any, TypeId};
+
+/// Default number of bins for `Ch32` channels
+const CH32_BINS: usize = 256;
+
+/// Per-channel histogram of a [Raster].
+///
+/// Channels are in the same order as [Pixel::channels], including *alpha*.
+///
+/// [Pixel::channels]: el/trait.Pixel.html#method.channels
+/// [Raster]: struct.Raster.html
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Histogram {
+    bins: usize,
+    counts: Vec<Vec<usize>>,
+}
+
+/// Statistics for one channel of a [Raster].
+///
+/// Values are normalized to the range `0.0` to `1.0`.
+///
+/// [Raster]: struct.Raster.html
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ChannelStats {
+    /// Minimum value
+    pub min: f32,
+    /// Maximum value
+    pub max: f32,
+    /// Mean value
+    pub mean: f32,
+    /// Standard deviation (population)
+    pub std_dev: f32,
+}
+
+impl Histogram {
+    /// Create an empty histogram
+    fn new(channels: usize, bins: usize) -> Self {
+        Histogram {
+            bins,
+            counts: vec![vec![0; bins]; channels],
+        }
+    }
+
+    /// Get the number of channels.
+    pub fn channels(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Get the number of bins per channel.
+    pub fn bins(&self) -> usize {
+        self.bins
+    }
+
+    /// Get the bin counts for one channel.
+    ///
+    /// * `chan` Channel index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chan` is not less than the number of channels.
+    pub fn channel(&self, chan: usize) -> &[usize] {
+        &self.counts[chan]
+    }
+
+    /// Get the total count of one channel (number of pixels).
+    pub fn total(&self) -> usize {
+        self.counts.first().map_or(0, |c| c.iter().sum())
+    }
+
+    /// Get the bin index of a channel value
+    fn bin<C: Channel + 'static>(&self, c: C) -> usize {
+        let any = &c as &dyn Any;
+        if let Some(v) = any.downcast_ref::<Ch8>() {
+            self.scale(u8::from(*v).into(), 1 << 8)
+        } else if let Some(v) = any.downcast_ref::<Ch16>() {
+            self.scale(u16::from(*v).into(), 1 << 16)
+        } else {
+            ((c.to_f32() * self.bins as f32) as usize).min(self.bins - 1)
+        }
+    }
+
+    /// Scale an integer channel value with `levels` to a bin index
+    fn scale(&self, v: u64, levels: u64) -> usize {
+        (u128::from(v) * self.bins as u128 / u128::from(levels)) as usize
+    }
+}
+
+impl<P: Pixel> Raster<P> {
+    /// Create a histogram of a region.
+    ///
+    /// * `reg` Region within `self`.  It can be a `Region` struct, tuple of
+    ///   (*x*, *y*, *width*, *height*) or the unit type `()`.  Using
+    ///   `()` has the same result as `Raster::region()`.
+    ///
+    /// There is one bin per value for `Ch8` (256) and `Ch16` (65536)
+    /// channels, and 256 bins for `Ch32` channels.
+    ///
+    /// ### Count values
+    /// ```
+    /// use pix::rgb::Rgba8;
+    /// use pix::Raster;
+    ///
+    /// let mut r = Raster::with_color(4, 4, Rgba8::new(0x10, 0x20, 0x30, 0xFF));
+    /// *r.pixel_mut(0, 0) = Rgba8::new(0x10, 0x80, 0x30, 0x80);
+    /// let hist = r.histogram(());
+    /// assert_eq!(hist.channels(), 4);
+    /// assert_eq!(hist.channel(0)[0x10], 16);
+    /// assert_eq!(hist.channel(1)[0x20], 15);
+    /// assert_eq!(hist.channel(3)[0x80], 1);
+    /// ```
+    pub fn histogram<R>(&self, reg: R) -> Histogram
+    where
+        R: Into<Region>,
+    {
+        let bins = if TypeId::of::<P::Chan>() == TypeId::of::<Ch8>() {
+            1 << 8
+        } else if TypeId::of::<P::Chan>() == TypeId::of::<Ch16>() {
+            1 << 16
+        } else {
+            CH32_BINS
+        };
+        self.histogram_bins(reg, bins)
+    }
+
+    /// Create a histogram of a region, with a number of bins.
+    ///
+    /// * `reg` Region within `self`.  It can be a `Region` struct, tuple of
+    ///   (*x*, *y*, *width*, *height*) or the unit type `()`.  Using
+    ///   `()` has the same result as `Raster::region()`.
+    /// * `bins` Number of equal-width bins per channel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bins` is zero.
+    pub fn histogram_bins<R>(&self, reg: R, bins: usize) -> Histogram
+    where
+        R: Into<Region>,
+    {
+        assert!(bins > 0, "No histogram bins");
+        let n_chan = P::default().channels().len();
+        let mut hist = Histogram::new(n_chan, bins);
+        for row in self.rows(reg) {
+            for p in row {
+                for (i, c) in p.channels().iter().enumerate() {
+                    let b = hist.bin(*c);
+                    hist.counts[i][b] += 1;
+                }
+            }
+        }
+        hist
+    }
+
+    /// Get statistics for each channel of a region.
+    ///
+    /// * `reg` Region within `self`.  It can be a `Region` struct, tuple of
+    ///   (*x*, *y*, *width*, *height*) or the unit type `()`.  Using
+    ///   `()` has the same result as `Raster::region()`.
+    ///
+    /// Channels are in the same order as [Pixel::channels], including
+    /// *alpha*.  If the region is empty, all statistics are zero.
+    ///
+    /// ### Check exposure
+    /// ```
+    /// use pix::gray::Gray8;
+    /// use pix::Raster;
+    ///
+    /// let mut r = Raster::with_color(2, 2, Gray8::new(0xFF));
+    /// r.copy_color((0, 0, 2, 1), Gray8::new(0));
+    /// let stats = r.stats(());
+    /// assert_eq!(stats[0].min, 0.0);
+    /// assert_eq!(stats[0].max, 1.0);
+    /// assert_eq!(stats[0].mean, 0.5);
+    /// assert_eq!(stats[0].std_dev, 0.5);
+    /// ```
+    ///
+    /// [Pixel::channels]: el/trait.Pixel.html#method.channels
+    pub fn stats<R>(&self, reg: R) -> Vec<ChannelStats>
+    where
+        R: Into<Region>,
+    {
+        let n_chan = P::default().channels().len();
+        let mut min = vec![f32::INFINITY; n_chan];
+        let mut max = vec![f32::NEG_INFINITY; n_chan];
+        let mut sum = vec![0.0f64; n_chan];
+        let mut sum_sq = vec![0.0f64; n_chan];
+        let mut count = 0;
+        for row in self.rows(reg) {
+            for p in row {
+                for (i, c) in p.channels().iter().enumerate() {
+                    let v = c.to_f32();
+                    min[i] = min[i].min(v);
+                    max[i] = max[i].max(v);
+                    sum[i] += f64::from(v);
+                    sum_sq[i] += f64::from(v) * f64::from(v);
+                }
+                count += 1;
+            }
+        }
+        if count == 0 {
+            return vec![ChannelStats::default(); n_chan];
+        }
+        let n = f64::from(count);
+        (0..n_chan)
+            .map(|i| {
+                let mean = sum[i] / n;
+                let var = (sum_sq[i] / n - mean * mean).max(0.0);
+                ChannelStats {
+                    min: min[i],
+                    max: max[i],
+                    mean: mean as f32,
+                    std_dev: var.sqrt() as f32,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gray::*;
+    use crate::matte::*;
+    use crate::rgb::*;
+
+    #[test]
+    fn histogram_depths() {
+        let mut r = Raster::<Gray16>::with_clear(3, 3);
+        *r.pixel_mut(1, 1) = Gray16::new(0xFFFF);
+        *r.pixel_mut(2, 2) = Gray16::new(0x0001);
+        let hist = r.histogram((1, 1, 2, 2));
+        assert_eq!(hist.bins(), 65536);
+        assert_eq!(hist.total(), 4);
+        assert_eq!(hist.channel(0)[0], 2);
+        assert_eq!(hist.channel(0)[1], 1);
+        assert_eq!(hist.channel(0)[0xFFFF], 1);
+        let mut r = Raster::<Matte32>::with_clear(4, 1);
+        *r.pixel_mut(1, 0) = Matte32::new(0.3);
+        *r.pixel_mut(2, 0) = Matte32::new(0.5);
+        *r.pixel_mut(3, 0) = Matte32::new(1.0);
+        assert_eq!(r.histogram(()).bins(), 256);
+        let hist = r.histogram_bins((), 4);
+        assert_eq!(hist.channel(0), &[1, 1, 1, 1]);
+        let hist = r.histogram((8, 8));
+        assert_eq!(hist.total(), 0);
+    }
+
+    #[test]
+    fn histogram_ramp() {
+        let mut r = Raster::<Gray16>::with_clear(256, 256);
+        for (v, p) in r.pixels_mut().iter_mut().enumerate() {
+            *p = Gray16::new(v as u16);
+        }
+        let hist = r.histogram(());
+        assert!(hist.channel(0).iter().all(|c| *c == 1));
+        let hist = r.histogram_bins((), 256);
+        assert!(hist.channel(0).iter().all(|c| *c == 256));
+        let hist = r.histogram_bins((), 3);
+        assert_eq!(hist.channel(0), &[21846, 21845, 21845]);
+        let mut r = Raster::<Gray8>::with_clear(16, 16);
+        for (v, p) in r.pixels_mut().iter_mut().enumerate() {
+            *p = Gray8::new(v as u8);
+        }
+        let hist = r.histogram(());
+        assert!(hist.channel(0).iter().all(|c| *c == 1));
+    }
+
+    #[test]
+    fn stats_alpha() {
+        let mut r = Raster::<SRgba8>::with_clear(4, 4);
+        r.copy_color((0, 0, 4, 2), SRgba8::new(0xFF, 0x33, 0x00, 0xFF));
+        let stats = r.stats((0, 0, 4, 3));
+        assert_eq!(stats.len(), 4);
+        assert_eq!(stats[1].max, 0.2);
+        assert_eq!(stats[2].max, 0.0);
+        let alpha = stats[3];
+        assert_eq!(alpha.min, 0.0);
+        assert!((alpha.mean - 2.0 / 3.0).abs() < 1e-6);
+        assert!((alpha.std_dev - 2.0f32.sqrt() / 3.0).abs() < 1e-6);
+        let empty = r.stats((4, 4, 1, 1));
+        assert_eq!(empty[0], ChannelStats::default());
+    }
+}
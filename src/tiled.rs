@@ -0,0 +1,468 @@
+// tiled.rs     Tiled raster images.
+//
+// Copyright (c) 2023  Douglas P Lau
+//
+use crate::chan::{Linear, Premultiplied};
+use crate::el::Pixel;
+use crate::ops::Blend;
+use crate::raster::{Raster, Region};
+use crate::view::{clip_regions, RasterRef};
+use std::iter::Enumerate;
+use std::slice::{Iter, IterMut};
+
+/// Default tile size (width and height)
+const TILE_SIZE: u32 = 256;
+
+/// Maximum tile size, so that one tile fits in a `Raster`
+const TILE_SIZE_MAX: u32 = 46_340;
+
+/// Image stored as fixed-size square tiles, allocated lazily.
+///
+/// Unlike a [Raster], the total number of pixels is not limited to
+/// `i32::MAX`.  Tiles which have not been allocated read as clear (the
+/// default pixel value).
+///
+/// ### Fill part of a large image
+/// ```
+/// use pix::rgb::SRgb8;
+/// use pix::TiledRaster;
+///
+/// let mut r = TiledRaster::with_clear(100_000, 100_000);
+/// let clr = SRgb8::new(0x20, 0x40, 0x80);
+/// r.copy_color((50_000, 50_000, 300, 2), clr);
+/// assert_eq!(r.tiles().count(), 2);
+/// assert_eq!(r.pixel(50_299, 50_001), clr);
+/// assert_eq!(r.pixel(0, 0), SRgb8::default());
+/// ```
+///
+/// [Raster]: struct.Raster.html
+#[derive(Clone)]
+pub struct TiledRaster<P: Pixel> {
+    width: u32,
+    height: u32,
+    tile_size: u32,
+    columns: u32,
+    tiles: Vec<Option<Raster<P>>>,
+}
+
+/// `Iterator` of allocated tiles in a [TiledRaster].
+///
+/// Each item is the `Region` covered by a tile (clipped to the image), along
+/// with the tile `Raster`.
+///
+/// [TiledRaster]: struct.TiledRaster.html
+pub struct Tiles<'a, P: Pixel> {
+    tiles: Enumerate<Iter<'a, Option<Raster<P>>>>,
+    layout: TileLayout,
+}
+
+/// `Iterator` of mutable allocated tiles in a [TiledRaster].
+///
+/// [TiledRaster]: struct.TiledRaster.html
+pub struct TilesMut<'a, P: Pixel> {
+    tiles: Enumerate<IterMut<'a, Option<Raster<P>>>>,
+    layout: TileLayout,
+}
+
+/// Layout of tiles within an image
+#[derive(Clone, Copy)]
+struct TileLayout {
+    region: Region,
+    tile_size: u32,
+    columns: u32,
+}
+
+impl TileLayout {
+    /// Get the `Region` of a tile, clipped to the image
+    fn tile_region(self, i: usize) -> Region {
+        let col = i as u32 % self.columns;
+        let row = i as u32 / self.columns;
+        self.unclipped(col, row).intersection(self.region)
+    }
+
+    /// Get the unclipped `Region` of a tile
+    fn unclipped(self, col: u32, row: u32) -> Region {
+        let x = i64::from(col) * i64::from(self.tile_size);
+        let y = i64::from(row) * i64::from(self.tile_size);
+        let x = i32::try_from(x).unwrap_or(i32::MAX);
+        let y = i32::try_from(y).unwrap_or(i32::MAX);
+        Region::new(x, y, self.tile_size, self.tile_size)
+    }
+}
+
+impl<'a, P: Pixel> Iterator for Tiles<'a, P> {
+    type Item = (Region, &'a Raster<P>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (i, tile) in self.tiles.by_ref() {
+            if let Some(tile) = tile {
+                return Some((self.layout.tile_region(i), tile));
+            }
+        }
+        None
+    }
+}
+
+impl<'a, P: Pixel> Iterator for TilesMut<'a, P> {
+    type Item = (Region, &'a mut Raster<P>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (i, tile) in self.tiles.by_ref() {
+            if let Some(tile) = tile {
+                return Some((self.layout.tile_region(i), tile));
+            }
+        }
+        None
+    }
+}
+
+impl<P: Pixel> TiledRaster<P> {
+    /// Construct a `TiledRaster` with all pixels set to the default value.
+    ///
+    /// The default tile size of 256 is used.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` or `height` is greater than `i32::MAX`.
+    pub fn with_clear(width: u32, height: u32) -> Self {
+        Self::with_tile_size(width, height, TILE_SIZE)
+    }
+
+    /// Construct a `TiledRaster` with a specific tile size.
+    ///
+    /// No tiles are allocated, so all pixels are set to the default value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` or `height` is greater than `i32::MAX`, or if
+    /// `tile_size` is zero or greater than 46,340.
+    pub fn with_tile_size(width: u32, height: u32, tile_size: u32) -> Self {
+        let region = Region::new(0, 0, width, height);
+        assert!(tile_size > 0 && tile_size <= TILE_SIZE_MAX);
+        let columns = region.width().div_ceil(tile_size);
+        let rows = region.height().div_ceil(tile_size);
+        let len = columns as usize * rows as usize;
+        TiledRaster {
+            width,
+            height,
+            tile_size,
+            columns,
+            tiles: vec![None; len],
+        }
+    }
+
+    /// Get width of `TiledRaster`.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Get height of `TiledRaster`.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Get tile size (width and height).
+    pub fn tile_size(&self) -> u32 {
+        self.tile_size
+    }
+
+    /// Get `Region` of entire `TiledRaster`.
+    pub fn region(&self) -> Region {
+        Region::new(0, 0, self.width, self.height)
+    }
+
+    /// Get intersection of `TiledRaster` with a `Region`.
+    pub fn intersection<R>(&self, reg: R) -> Region
+    where
+        R: Into<Region>,
+    {
+        self.region().intersection(reg)
+    }
+
+    /// Get the tile layout
+    fn layout(&self) -> TileLayout {
+        TileLayout {
+            region: self.region(),
+            tile_size: self.tile_size,
+            columns: self.columns,
+        }
+    }
+
+    /// Deallocate all tiles, clearing all pixels.
+    pub fn clear(&mut self) {
+        self.tiles.iter_mut().for_each(|t| *t = None);
+    }
+
+    /// Get one pixel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` or `y` is out of bounds.
+    pub fn pixel(&self, x: i32, y: i32) -> P {
+        let (i, tx, ty) = self.pixel_tile(x, y);
+        match &self.tiles[i] {
+            Some(tile) => tile.pixel(tx, ty),
+            None => P::default(),
+        }
+    }
+
+    /// Get a mutable pixel.
+    ///
+    /// The tile containing the pixel is allocated if necessary.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` or `y` is out of bounds.
+    pub fn pixel_mut(&mut self, x: i32, y: i32) -> &mut P {
+        let (i, tx, ty) = self.pixel_tile(x, y);
+        self.tile_mut(i).pixel_mut(tx, ty)
+    }
+
+    /// Get tile index and tile coordinates of a pixel
+    fn pixel_tile(&self, x: i32, y: i32) -> (usize, i32, i32) {
+        assert!(x >= 0 && (x as u32) < self.width, "Pixel out of bounds");
+        assert!(y >= 0 && (y as u32) < self.height, "Pixel out of bounds");
+        let (x, y) = (x as u32, y as u32);
+        let i = (y / self.tile_size) as usize * self.columns as usize
+            + (x / self.tile_size) as usize;
+        let tx = (x % self.tile_size) as i32;
+        let ty = (y % self.tile_size) as i32;
+        (i, tx, ty)
+    }
+
+    /// Get a tile by index, allocating it if necessary
+    fn tile_mut(&mut self, i: usize) -> &mut Raster<P> {
+        let size = self.tile_size;
+        self.tiles[i].get_or_insert_with(|| Raster::with_clear(size, size))
+    }
+
+    /// Get an `Iterator` of allocated tiles.
+    pub fn tiles(&self) -> Tiles<'_, P> {
+        Tiles {
+            tiles: self.tiles.iter().enumerate(),
+            layout: self.layout(),
+        }
+    }
+
+    /// Get an `Iterator` of mutable allocated tiles.
+    pub fn tiles_mut(&mut self) -> TilesMut<'_, P> {
+        let layout = self.layout();
+        TilesMut {
+            tiles: self.tiles.iter_mut().enumerate(),
+            layout,
+        }
+    }
+
+    /// Call a function for each tile overlapping a region.
+    ///
+    /// * `reg` Region (must be clipped to the image).
+    /// * `alloc` Allocate tiles if necessary.
+    /// * `f` Function called with tile, tile `Region` and image `Region`.
+    fn for_each_tile<F>(&mut self, reg: Region, alloc: bool, mut f: F)
+    where
+        F: FnMut(&mut Raster<P>, Region, Region),
+    {
+        if reg.width() == 0 || reg.height() == 0 {
+            return;
+        }
+        let layout = self.layout();
+        let ts = self.tile_size;
+        let col0 = reg.left() as u32 / ts;
+        let col1 = (reg.right() - 1) as u32 / ts;
+        let row0 = reg.top() as u32 / ts;
+        let row1 = (reg.bottom() - 1) as u32 / ts;
+        for row in row0..=row1 {
+            for col in col0..=col1 {
+                let i = row as usize * self.columns as usize + col as usize;
+                let tile = layout.unclipped(col, row);
+                let part = reg.intersection(tile);
+                let local = Region::new(
+                    part.left() - tile.left(),
+                    part.top() - tile.top(),
+                    part.width(),
+                    part.height(),
+                );
+                if alloc {
+                    f(self.tile_mut(i), local, part);
+                } else if let Some(tile) = &mut self.tiles[i] {
+                    f(tile, local, part);
+                }
+            }
+        }
+    }
+
+    /// Copy a color to a region of the `TiledRaster`.
+    ///
+    /// * `reg` Region within `self`.  It can be a `Region` struct, tuple of
+    ///   (*x*, *y*, *width*, *height*) or the unit type `()`.  Using
+    ///   `()` has the same result as `TiledRaster::region()`.
+    /// * `clr` Source `Pixel` color.
+    ///
+    /// Tiles are only allocated if `clr` is not the default value.
+    pub fn copy_color<R>(&mut self, reg: R, clr: P)
+    where
+        R: Into<Region>,
+    {
+        let reg = self.intersection(reg);
+        let alloc = clr != P::default();
+        self.for_each_tile(reg, alloc, |tile, local, _| {
+            tile.copy_color(local, clr);
+        });
+    }
+
+    /// Copy from a source `Raster`.
+    ///
+    /// * `to` Region within `self` (destination).
+    /// * `src` Source `Raster`, `RasterRef` or `RasterMut`.
+    /// * `from` Region within source `Raster`.
+    ///
+    /// Regions are clipped in the same way as
+    /// [Raster::copy_raster](struct.Raster.html#method.copy_raster).
+    pub fn copy_raster<'b, R0, R1, S>(&mut self, to: R0, src: S, from: R1)
+    where
+        R0: Into<Region>,
+        R1: Into<Region>,
+        S: Into<RasterRef<'b, P>>,
+        P: 'b,
+    {
+        let src = src.into();
+        let (to, from) =
+            clip_regions(self.region(), to.into(), src.region(), from.into());
+        self.for_each_tile(to, true, |tile, local, part| {
+            let from = source_part(to, from, part);
+            tile.copy_raster(local, src, from);
+        });
+    }
+}
+
+impl<P> TiledRaster<P>
+where
+    P: Pixel<Alpha = Premultiplied, Gamma = Linear>,
+{
+    /// Composite from a source `Raster` with a blending operation.
+    ///
+    /// * `to` Region within `self` (destination).
+    /// * `src` Source `Raster`, `RasterRef` or `RasterMut`.
+    /// * `from` Region within source `Raster`.
+    /// * `op` Compositing operation.
+    ///
+    /// Regions are clipped in the same way as
+    /// [Raster::composite_raster](struct.Raster.html#method.composite_raster).
+    ///
+    /// ### Blend a `Raster` across tile boundaries
+    /// ```
+    /// use pix::ops::SrcOver;
+    /// use pix::rgb::Rgba8p;
+    /// use pix::{Raster, TiledRaster};
+    ///
+    /// let mut r0 = TiledRaster::with_tile_size(100, 100, 16);
+    /// let r1 = Raster::with_color(5, 5, Rgba8p::new(80, 0, 80, 200));
+    /// r0.composite_raster((14, 14), &r1, (), SrcOver);
+    /// assert_eq!(r0.tiles().count(), 4);
+    /// ```
+    pub fn composite_raster<'b, R0, R1, S, O>(
+        &mut self,
+        to: R0,
+        src: S,
+        from: R1,
+        op: O,
+    ) where
+        R0: Into<Region>,
+        R1: Into<Region>,
+        S: Into<RasterRef<'b, P>>,
+        P: 'b,
+        O: Blend,
+    {
+        let src = src.into();
+        let (to, from) =
+            clip_regions(self.region(), to.into(), src.region(), from.into());
+        self.for_each_tile(to, true, |tile, local, part| {
+            let from = source_part(to, from, part);
+            tile.composite_raster(local, src, from, op);
+        });
+    }
+}
+
+/// Get the part of a source region corresponding to part of a destination
+fn source_part(to: Region, from: Region, part: Region) -> Region {
+    Region::new(
+        from.left() + (part.left() - to.left()),
+        from.top() + (part.top() - to.top()),
+        part.width(),
+        part.height(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::gray::*;
+    use crate::ops::*;
+    use crate::rgb::*;
+    use crate::*;
+
+    #[test]
+    fn lazy_tiles() {
+        let mut t = TiledRaster::<Gray8>::with_tile_size(10, 7, 4);
+        assert_eq!(t.tiles().count(), 0);
+        t.copy_color((), Gray8::new(0));
+        assert_eq!(t.tiles().count(), 0);
+        *t.pixel_mut(9, 6) = Gray8::new(0x55);
+        let tiles: Vec<_> = t.tiles().map(|(r, _)| r).collect();
+        assert_eq!(tiles, [Region::new(8, 4, 2, 3)]);
+        assert_eq!(t.pixel(9, 6), Gray8::new(0x55));
+        assert_eq!(t.pixel(8, 6), Gray8::new(0));
+        t.clear();
+        assert_eq!(t.tiles().count(), 0);
+    }
+
+    #[test]
+    fn copy_color_tiles() {
+        let mut t = TiledRaster::<Gray8>::with_tile_size(6, 6, 4);
+        t.copy_color((2, 3, 3, 2), Gray8::new(1));
+        assert_eq!(t.tiles().count(), 4);
+        let mut r = Raster::<Gray8>::with_clear(6, 6);
+        for (reg, tile) in t.tiles() {
+            r.copy_raster(reg, tile, ());
+        }
+        #[rustfmt::skip]
+        let v = [
+            0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0,
+            0, 0, 1, 1, 1, 0,
+            0, 0, 1, 1, 1, 0,
+            0, 0, 0, 0, 0, 0,
+        ];
+        assert_eq!(r.as_u8_slice(), &v);
+    }
+
+    #[test]
+    fn copy_raster_tiles() {
+        let mut src = Raster::<Gray8>::with_clear(4, 4);
+        for (i, p) in src.pixels_mut().iter_mut().enumerate() {
+            *p = Gray8::new(i as u8);
+        }
+        let mut t = TiledRaster::with_tile_size(8, 8, 3);
+        t.copy_raster((-1, 2), &src, (1, 1, 3, 3));
+        assert_eq!(t.pixel(0, 2), Gray8::new(6));
+        assert_eq!(t.pixel(1, 2), Gray8::new(7));
+        assert_eq!(t.pixel(1, 4), Gray8::new(15));
+        assert_eq!(t.pixel(2, 2), Gray8::new(0));
+        assert_eq!(t.tiles().count(), 2);
+        for (_, tile) in t.tiles_mut() {
+            tile.clear();
+        }
+        assert_eq!(t.pixel(1, 4), Gray8::new(0));
+    }
+
+    #[test]
+    fn composite_tiles() {
+        let src = Raster::with_color(3, 3, Rgba8p::new(0x80, 0, 0, 0x80));
+        let mut t = TiledRaster::with_tile_size(4, 4, 2);
+        t.copy_color((), Rgba8p::new(0, 0, 0xFF, 0xFF));
+        t.composite_raster((1, 1), &src, (), SrcOver);
+        assert_eq!(t.pixel(0, 0), Rgba8p::new(0, 0, 0xFF, 0xFF));
+        assert_eq!(t.pixel(2, 2), Rgba8p::new(0x80, 0, 0x7F, 0xFF));
+        assert_eq!(t.pixel(3, 3), Rgba8p::new(0x80, 0, 0x7F, 0xFF));
+    }
+}
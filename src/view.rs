@@ -0,0 +1,892 @@
+// view.rs      Borrowed raster views.
+//
+// Copyright (c) 2023  Douglas P Lau
+//
+use crate::chan::{Ch16, Ch8, Linear, Premultiplied};
+use crate::el::Pixel;
+use crate::error::Error;
+use crate::matte::Matte;
+use crate::ops::Blend;
+use crate::raster::{
+    pixel_slice, pixel_slice_mut, Raster, Region, Rows, RowsMut,
+};
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+use std::mem::{align_of, size_of};
+
+/// Borrowed view of pixels arranged as a rectangular array.
+///
+/// Rows are ordered top to bottom, and separated by a *stride* in bytes, which
+/// can include padding at the end of each row.
+///
+/// A `RasterRef` can be created from a [Raster] reference using `into()` or
+/// [Raster::view], or by borrowing a buffer with one of the *with_* methods:
+/// * [with_u8_buffer](#method.with_u8_buffer)
+/// * [with_u16_buffer](#method.with_u16_buffer)
+///
+/// It can be used as the source of [copy_raster] and [composite_raster].
+///
+/// ### Borrow a padded byte buffer
+/// ```
+/// use pix::rgb::SRgb8;
+/// use pix::RasterRef;
+///
+/// // 3x2 pixels, with rows padded to 12 bytes
+/// let buf = [0x80; 24];
+/// let view = RasterRef::<SRgb8>::with_u8_buffer(3, 2, 12, &buf[..]);
+/// assert_eq!(view.pixel(2, 1), SRgb8::new(0x80, 0x80, 0x80));
+/// ```
+///
+/// [composite_raster]: struct.Raster.html#method.composite_raster
+/// [copy_raster]: struct.Raster.html#method.copy_raster
+/// [raster]: struct.Raster.html
+/// [Raster::view]: struct.Raster.html#method.view
+#[derive(Clone, Copy)]
+pub struct RasterRef<'a, P: Pixel> {
+    width: i32,
+    height: i32,
+    stride: usize,
+    buf: &'a [u8],
+    _pixel: PhantomData<P>,
+}
+
+/// Mutable borrowed view of pixels arranged as a rectangular array.
+///
+/// Rows are ordered top to bottom, and separated by a *stride* in bytes, which
+/// can include padding at the end of each row.
+///
+/// A `RasterMut` can be created from a mutable [Raster] reference using
+/// `into()` or [Raster::view_mut], or by borrowing a buffer with one of the
+/// *with_* methods:
+/// * [with_u8_buffer](#method.with_u8_buffer)
+/// * [with_u16_buffer](#method.with_u16_buffer)
+///
+/// ### Draw into a shared framebuffer
+/// ```
+/// use pix::ops::SrcOver;
+/// use pix::rgb::{Rgba8p, Rgba8p as Clr};
+/// use pix::{Raster, RasterMut};
+///
+/// // 4x4 pixels, with rows padded to 32 bytes
+/// let mut fb = vec![0; 32 * 4];
+/// let mut view = RasterMut::<Rgba8p>::with_u8_buffer(4, 4, 32, &mut fb[..]);
+/// let src = Raster::with_color(2, 2, Clr::new(80, 0, 80, 200));
+/// view.composite_raster((1, 1), &src, (), SrcOver);
+/// assert_eq!(view.pixel(2, 2), Rgba8p::new(80, 0, 80, 200));
+/// assert_eq!(&fb[32 + 4..32 + 8], &[80, 0, 80, 200]);
+/// ```
+///
+/// [raster]: struct.Raster.html
+/// [Raster::view_mut]: struct.Raster.html#method.view_mut
+pub struct RasterMut<'a, P: Pixel> {
+    width: i32,
+    height: i32,
+    stride: usize,
+    buf: &'a mut [u8],
+    _pixel: PhantomData<P>,
+}
+
+/// Check dimensions, stride and buffer size for a view.
+///
+/// # Returns
+/// Width and height of view.
+fn check_view<P: Pixel>(
+    width: u32,
+    height: u32,
+    stride: usize,
+    buf: &[u8],
+) -> Result<(i32, i32), Error> {
+    let width = i32::try_from(width).map_err(|_| Error::WidthTooBig)?;
+    let height = i32::try_from(height).map_err(|_| Error::HeightTooBig)?;
+    let row_len = (width as usize)
+        .checked_mul(size_of::<P>())
+        .ok_or(Error::TooBig)?;
+    if stride < row_len {
+        return Err(Error::StrideTooSmall);
+    }
+    if stride % align_of::<P>() != 0
+        || buf.as_ptr().align_offset(align_of::<P>()) != 0
+    {
+        return Err(Error::Misaligned);
+    }
+    if height > 0 {
+        let len = (height as usize - 1)
+            .checked_mul(stride)
+            .and_then(|l| l.checked_add(row_len))
+            .ok_or(Error::TooBig)?;
+        if buf.len() < len {
+            return Err(Error::LengthMismatch);
+        }
+    }
+    Ok((width, height))
+}
+
+/// Get byte offset of a pixel within a view
+fn pixel_offset<P: Pixel>(
+    width: i32,
+    height: i32,
+    stride: usize,
+    x: i32,
+    y: i32,
+) -> usize {
+    assert!(x >= 0 && x < width);
+    assert!(y >= 0 && y < height);
+    y as usize * stride + x as usize * size_of::<P>()
+}
+
+/// Get byte offset of the top-left pixel of a region within a view
+fn region_offset<P: Pixel>(stride: usize, reg: Region) -> usize {
+    reg.top() as usize * stride + reg.left() as usize * size_of::<P>()
+}
+
+/// Clip `to` / `from` regions for destination / source rasters
+pub(crate) fn clip_regions(
+    dst: Region,
+    to: Region,
+    src: Region,
+    from: Region,
+) -> (Region, Region) {
+    let (tx, ty) = (to.left().min(0).abs(), to.top().min(0).abs());
+    let (fx, fy) = (from.left().min(0).abs(), from.top().min(0).abs());
+    let to = dst.intersection(to);
+    let from = src.intersection(from);
+    let width = to.width().min(from.width());
+    let height = to.height().min(from.height());
+    let to = Region::new(to.left() + fx, to.top() + fy, width, height);
+    let from = Region::new(from.left() + tx, from.top() + ty, width, height);
+    (to, from)
+}
+
+impl<'a, P: Pixel> From<&'a Raster<P>> for RasterRef<'a, P> {
+    /// Borrow an entire `Raster`.
+    fn from(raster: &'a Raster<P>) -> Self {
+        RasterRef {
+            width: raster.width() as i32,
+            height: raster.height() as i32,
+            stride: raster.stride(),
+            buf: raster.as_u8_slice(),
+            _pixel: PhantomData,
+        }
+    }
+}
+
+impl<'a, P: Pixel> From<&RasterRef<'a, P>> for RasterRef<'a, P> {
+    /// Copy a borrowed view.
+    fn from(view: &RasterRef<'a, P>) -> Self {
+        *view
+    }
+}
+
+impl<'a, P: Pixel> From<&'a RasterMut<'_, P>> for RasterRef<'a, P> {
+    /// Borrow a mutable view immutably.
+    fn from(view: &'a RasterMut<'_, P>) -> Self {
+        RasterRef {
+            width: view.width,
+            height: view.height,
+            stride: view.stride,
+            buf: view.buf,
+            _pixel: PhantomData,
+        }
+    }
+}
+
+impl<'a, P: Pixel> From<&'a mut Raster<P>> for RasterMut<'a, P> {
+    /// Borrow an entire `Raster` mutably.
+    fn from(raster: &'a mut Raster<P>) -> Self {
+        let width = raster.width() as i32;
+        let height = raster.height() as i32;
+        let stride = raster.stride();
+        RasterMut {
+            width,
+            height,
+            stride,
+            buf: raster.as_u8_slice_mut(),
+            _pixel: PhantomData,
+        }
+    }
+}
+
+impl<'a, P: Pixel> RasterRef<'a, P> {
+    /// Borrow a `u8` buffer as a `RasterRef`.
+    ///
+    /// * `width` Width of view.
+    /// * `height` Height of view.
+    /// * `stride` Distance between rows (bytes).
+    /// * `buffer` Buffer of pixel data.
+    ///
+    /// # Panics
+    ///
+    /// * If `width` or `height` is greater than `i32::MAX`
+    /// * If `stride` is less than `width` * `std::mem::size_of::<P>()`
+    /// * If `buffer` length is less than (`height` - 1) * `stride` + `width` *
+    ///   `std::mem::size_of::<P>()`
+    ///
+    /// See [try_with_u8_buffer](#method.try_with_u8_buffer) for a
+    /// non-panicking version.
+    pub fn with_u8_buffer(
+        width: u32,
+        height: u32,
+        stride: usize,
+        buffer: &'a [u8],
+    ) -> Self
+    where
+        P: Pixel<Chan = Ch8>,
+    {
+        Self::try_with_u8_buffer(width, height, stride, buffer)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Try to borrow a `u8` buffer as a `RasterRef`.
+    ///
+    /// * `width` Width of view.
+    /// * `height` Height of view.
+    /// * `stride` Distance between rows (bytes).
+    /// * `buffer` Buffer of pixel data.
+    ///
+    /// # Errors
+    ///
+    /// * [WidthTooBig] / [HeightTooBig] if `width` or `height` is greater
+    ///   than `i32::MAX`
+    /// * [StrideTooSmall] if `stride` is less than `width` *
+    ///   `std::mem::size_of::<P>()`
+    /// * [Misaligned] if `stride` or `buffer` is not aligned for `P`
+    /// * [LengthMismatch] if `buffer` is too small for `height` rows
+    ///
+    /// [HeightTooBig]: enum.Error.html#variant.HeightTooBig
+    /// [LengthMismatch]: enum.Error.html#variant.LengthMismatch
+    /// [Misaligned]: enum.Error.html#variant.Misaligned
+    /// [StrideTooSmall]: enum.Error.html#variant.StrideTooSmall
+    /// [WidthTooBig]: enum.Error.html#variant.WidthTooBig
+    pub fn try_with_u8_buffer(
+        width: u32,
+        height: u32,
+        stride: usize,
+        buffer: &'a [u8],
+    ) -> Result<Self, Error>
+    where
+        P: Pixel<Chan = Ch8>,
+    {
+        let (width, height) = check_view::<P>(width, height, stride, buffer)?;
+        Ok(RasterRef {
+            width,
+            height,
+            stride,
+            buf: buffer,
+            _pixel: PhantomData,
+        })
+    }
+
+    /// Borrow a `u16` buffer as a `RasterRef`.
+    ///
+    /// * `width` Width of view.
+    /// * `height` Height of view.
+    /// * `stride` Distance between rows (bytes).
+    /// * `buffer` Buffer of pixel data (in native-endian byte order).
+    ///
+    /// # Panics
+    ///
+    /// * If `width` or `height` is greater than `i32::MAX`
+    /// * If `stride` is less than `width` * `std::mem::size_of::<P>()`, or is
+    ///   not a multiple of 2
+    /// * If `buffer` length (in bytes) is less than (`height` - 1) * `stride`
+    ///   + `width` * `std::mem::size_of::<P>()`
+    ///
+    /// See [try_with_u16_buffer](#method.try_with_u16_buffer) for a
+    /// non-panicking version.
+    pub fn with_u16_buffer(
+        width: u32,
+        height: u32,
+        stride: usize,
+        buffer: &'a [u16],
+    ) -> Self
+    where
+        P: Pixel<Chan = Ch16>,
+    {
+        Self::try_with_u16_buffer(width, height, stride, buffer)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Try to borrow a `u16` buffer as a `RasterRef`.
+    ///
+    /// * `width` Width of view.
+    /// * `height` Height of view.
+    /// * `stride` Distance between rows (bytes).
+    /// * `buffer` Buffer of pixel data (in native-endian byte order).
+    ///
+    /// # Errors
+    ///
+    /// * [WidthTooBig] / [HeightTooBig] if `width` or `height` is greater
+    ///   than `i32::MAX`
+    /// * [StrideTooSmall] if `stride` is less than `width` *
+    ///   `std::mem::size_of::<P>()`
+    /// * [Misaligned] if `stride` or `buffer` is not aligned for `P`
+    /// * [LengthMismatch] if `buffer` is too small for `height` rows
+    ///
+    /// [HeightTooBig]: enum.Error.html#variant.HeightTooBig
+    /// [LengthMismatch]: enum.Error.html#variant.LengthMismatch
+    /// [Misaligned]: enum.Error.html#variant.Misaligned
+    /// [StrideTooSmall]: enum.Error.html#variant.StrideTooSmall
+    /// [WidthTooBig]: enum.Error.html#variant.WidthTooBig
+    pub fn try_with_u16_buffer(
+        width: u32,
+        height: u32,
+        stride: usize,
+        buffer: &'a [u16],
+    ) -> Result<Self, Error>
+    where
+        P: Pixel<Chan = Ch16>,
+    {
+        let (_, buf, _) = unsafe { buffer.align_to::<u8>() };
+        let (width, height) = check_view::<P>(width, height, stride, buf)?;
+        Ok(RasterRef {
+            width,
+            height,
+            stride,
+            buf,
+            _pixel: PhantomData,
+        })
+    }
+
+    /// Get width of view.
+    pub fn width(&self) -> u32 {
+        self.width as u32
+    }
+
+    /// Get height of view.
+    pub fn height(&self) -> u32 {
+        self.height as u32
+    }
+
+    /// Get distance between rows (bytes).
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Get one pixel.
+    pub fn pixel(&self, x: i32, y: i32) -> P {
+        let sz = size_of::<P>();
+        let i = pixel_offset::<P>(self.width, self.height, self.stride, x, y);
+        pixel_slice::<P>(&self.buf[i..i + sz])[0]
+    }
+
+    /// Get an `Iterator` of rows within a view.
+    ///
+    /// * `reg` Region of the view to iterate.
+    pub fn rows<R>(&self, reg: R) -> Rows<'a, P>
+    where
+        R: Into<Region>,
+    {
+        let reg = self.intersection(reg.into());
+        Rows::new(self.buf, self.stride, reg)
+    }
+
+    /// Get `Region` of entire view.
+    pub fn region(&self) -> Region {
+        Region::new(0, 0, self.width(), self.height())
+    }
+
+    /// Get intersection with a `Region`.
+    pub fn intersection<R>(&self, reg: R) -> Region
+    where
+        R: Into<Region>,
+    {
+        let reg = reg.into();
+        reg.intersection(self.region())
+    }
+
+    /// Get a view of a region within this view.
+    ///
+    /// * `reg` Region of the view.  The new view's origin is at the top-left
+    ///   corner of the region, after clipping.
+    pub fn view<R>(&self, reg: R) -> RasterRef<'a, P>
+    where
+        R: Into<Region>,
+    {
+        let reg = self.intersection(reg.into());
+        let i = region_offset::<P>(self.stride, reg);
+        RasterRef {
+            width: reg.width() as i32,
+            height: reg.height() as i32,
+            stride: self.stride,
+            buf: &self.buf[i..],
+            _pixel: PhantomData,
+        }
+    }
+}
+
+impl<'a, P: Pixel> RasterMut<'a, P> {
+    /// Borrow a mutable `u8` buffer as a `RasterMut`.
+    ///
+    /// * `width` Width of view.
+    /// * `height` Height of view.
+    /// * `stride` Distance between rows (bytes).
+    /// * `buffer` Buffer of pixel data.
+    ///
+    /// # Panics
+    ///
+    /// * If `width` or `height` is greater than `i32::MAX`
+    /// * If `stride` is less than `width` * `std::mem::size_of::<P>()`
+    /// * If `buffer` length is less than (`height` - 1) * `stride` + `width` *
+    ///   `std::mem::size_of::<P>()`
+    ///
+    /// See [try_with_u8_buffer](#method.try_with_u8_buffer) for a
+    /// non-panicking version.
+    pub fn with_u8_buffer(
+        width: u32,
+        height: u32,
+        stride: usize,
+        buffer: &'a mut [u8],
+    ) -> Self
+    where
+        P: Pixel<Chan = Ch8>,
+    {
+        Self::try_with_u8_buffer(width, height, stride, buffer)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Try to borrow a mutable `u8` buffer as a `RasterMut`.
+    ///
+    /// * `width` Width of view.
+    /// * `height` Height of view.
+    /// * `stride` Distance between rows (bytes).
+    /// * `buffer` Buffer of pixel data.
+    ///
+    /// # Errors
+    ///
+    /// * [WidthTooBig] / [HeightTooBig] if `width` or `height` is greater
+    ///   than `i32::MAX`
+    /// * [StrideTooSmall] if `stride` is less than `width` *
+    ///   `std::mem::size_of::<P>()`
+    /// * [Misaligned] if `stride` or `buffer` is not aligned for `P`
+    /// * [LengthMismatch] if `buffer` is too small for `height` rows
+    ///
+    /// [HeightTooBig]: enum.Error.html#variant.HeightTooBig
+    /// [LengthMismatch]: enum.Error.html#variant.LengthMismatch
+    /// [Misaligned]: enum.Error.html#variant.Misaligned
+    /// [StrideTooSmall]: enum.Error.html#variant.StrideTooSmall
+    /// [WidthTooBig]: enum.Error.html#variant.WidthTooBig
+    pub fn try_with_u8_buffer(
+        width: u32,
+        height: u32,
+        stride: usize,
+        buffer: &'a mut [u8],
+    ) -> Result<Self, Error>
+    where
+        P: Pixel<Chan = Ch8>,
+    {
+        let (width, height) = check_view::<P>(width, height, stride, buffer)?;
+        Ok(RasterMut {
+            width,
+            height,
+            stride,
+            buf: buffer,
+            _pixel: PhantomData,
+        })
+    }
+
+    /// Borrow a mutable `u16` buffer as a `RasterMut`.
+    ///
+    /// * `width` Width of view.
+    /// * `height` Height of view.
+    /// * `stride` Distance between rows (bytes).
+    /// * `buffer` Buffer of pixel data (in native-endian byte order).
+    ///
+    /// # Panics
+    ///
+    /// * If `width` or `height` is greater than `i32::MAX`
+    /// * If `stride` is less than `width` * `std::mem::size_of::<P>()`, or is
+    ///   not a multiple of 2
+    /// * If `buffer` length (in bytes) is less than (`height` - 1) * `stride`
+    ///   + `width` * `std::mem::size_of::<P>()`
+    ///
+    /// See [try_with_u16_buffer](#method.try_with_u16_buffer) for a
+    /// non-panicking version.
+    pub fn with_u16_buffer(
+        width: u32,
+        height: u32,
+        stride: usize,
+        buffer: &'a mut [u16],
+    ) -> Self
+    where
+        P: Pixel<Chan = Ch16>,
+    {
+        Self::try_with_u16_buffer(width, height, stride, buffer)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Try to borrow a mutable `u16` buffer as a `RasterMut`.
+    ///
+    /// * `width` Width of view.
+    /// * `height` Height of view.
+    /// * `stride` Distance between rows (bytes).
+    /// * `buffer` Buffer of pixel data (in native-endian byte order).
+    ///
+    /// # Errors
+    ///
+    /// * [WidthTooBig] / [HeightTooBig] if `width` or `height` is greater
+    ///   than `i32::MAX`
+    /// * [StrideTooSmall] if `stride` is less than `width` *
+    ///   `std::mem::size_of::<P>()`
+    /// * [Misaligned] if `stride` or `buffer` is not aligned for `P`
+    /// * [LengthMismatch] if `buffer` is too small for `height` rows
+    ///
+    /// [HeightTooBig]: enum.Error.html#variant.HeightTooBig
+    /// [LengthMismatch]: enum.Error.html#variant.LengthMismatch
+    /// [Misaligned]: enum.Error.html#variant.Misaligned
+    /// [StrideTooSmall]: enum.Error.html#variant.StrideTooSmall
+    /// [WidthTooBig]: enum.Error.html#variant.WidthTooBig
+    pub fn try_with_u16_buffer(
+        width: u32,
+        height: u32,
+        stride: usize,
+        buffer: &'a mut [u16],
+    ) -> Result<Self, Error>
+    where
+        P: Pixel<Chan = Ch16>,
+    {
+        let (_, buf, _) = unsafe { buffer.align_to_mut::<u8>() };
+        let (width, height) = check_view::<P>(width, height, stride, buf)?;
+        Ok(RasterMut {
+            width,
+            height,
+            stride,
+            buf,
+            _pixel: PhantomData,
+        })
+    }
+
+    /// Get width of view.
+    pub fn width(&self) -> u32 {
+        self.width as u32
+    }
+
+    /// Get height of view.
+    pub fn height(&self) -> u32 {
+        self.height as u32
+    }
+
+    /// Get distance between rows (bytes).
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Clear all pixels to default value.
+    pub fn clear(&mut self) {
+        self.copy_color((), P::default());
+    }
+
+    /// Get one pixel.
+    pub fn pixel(&self, x: i32, y: i32) -> P {
+        RasterRef::from(self).pixel(x, y)
+    }
+
+    /// Get a mutable pixel.
+    pub fn pixel_mut(&mut self, x: i32, y: i32) -> &mut P {
+        let sz = size_of::<P>();
+        let i = pixel_offset::<P>(self.width, self.height, self.stride, x, y);
+        &mut pixel_slice_mut::<P>(&mut self.buf[i..i + sz])[0]
+    }
+
+    /// Get an `Iterator` of rows within a view.
+    ///
+    /// * `reg` Region of the view to iterate.
+    pub fn rows<R>(&self, reg: R) -> Rows<'_, P>
+    where
+        R: Into<Region>,
+    {
+        RasterRef::from(self).rows(reg)
+    }
+
+    /// Get an `Iterator` of mutable rows within a view.
+    ///
+    /// * `reg` Region of the view to iterate.
+    pub fn rows_mut<R>(&mut self, reg: R) -> RowsMut<'_, P>
+    where
+        R: Into<Region>,
+    {
+        let reg = self.intersection(reg.into());
+        RowsMut::new(self.buf, self.stride, reg)
+    }
+
+    /// Get `Region` of entire view.
+    pub fn region(&self) -> Region {
+        Region::new(0, 0, self.width(), self.height())
+    }
+
+    /// Get intersection with a `Region`.
+    pub fn intersection<R>(&self, reg: R) -> Region
+    where
+        R: Into<Region>,
+    {
+        let reg = reg.into();
+        reg.intersection(self.region())
+    }
+
+    /// Get a view of a region within this view.
+    ///
+    /// * `reg` Region of the view.  The new view's origin is at the top-left
+    ///   corner of the region, after clipping.
+    pub fn view<R>(&self, reg: R) -> RasterRef<'_, P>
+    where
+        R: Into<Region>,
+    {
+        RasterRef::from(self).view(reg)
+    }
+
+    /// Get a mutable view of a region within this view.
+    ///
+    /// * `reg` Region of the view.  The new view's origin is at the top-left
+    ///   corner of the region, after clipping.
+    pub fn view_mut<R>(&mut self, reg: R) -> RasterMut<'_, P>
+    where
+        R: Into<Region>,
+    {
+        RasterMut {
+            width: self.width,
+            height: self.height,
+            stride: self.stride,
+            buf: &mut *self.buf,
+            _pixel: PhantomData,
+        }
+        .into_view(reg)
+    }
+
+    /// Convert into a view of a region within this view.
+    pub(crate) fn into_view<R>(self, reg: R) -> Self
+    where
+        R: Into<Region>,
+    {
+        let reg = self.intersection(reg.into());
+        let i = region_offset::<P>(self.stride, reg);
+        RasterMut {
+            width: reg.width() as i32,
+            height: reg.height() as i32,
+            stride: self.stride,
+            buf: &mut self.buf[i..],
+            _pixel: PhantomData,
+        }
+    }
+
+    /// Copy a color to a region of the view.
+    ///
+    /// See [Raster::copy_color](struct.Raster.html#method.copy_color).
+    pub fn copy_color<R>(&mut self, reg: R, clr: P)
+    where
+        R: Into<Region>,
+    {
+        for drow in self.rows_mut(reg) {
+            P::copy_color(drow, &clr);
+        }
+    }
+
+    /// Copy from a source `Raster`, `RasterRef` or `RasterMut`.
+    ///
+    /// See [Raster::copy_raster](struct.Raster.html#method.copy_raster).
+    pub fn copy_raster<'b, R0, R1, S>(&mut self, to: R0, src: S, from: R1)
+    where
+        R0: Into<Region>,
+        R1: Into<Region>,
+        S: Into<RasterRef<'b, P>>,
+        P: 'b,
+    {
+        let src = src.into();
+        let (to, from) =
+            clip_regions(self.region(), to.into(), src.region(), from.into());
+        let srows = src.rows(from);
+        let drows = self.rows_mut(to);
+        for (drow, srow) in drows.zip(srows) {
+            P::copy_slice(drow, srow);
+        }
+    }
+}
+
+impl<P> RasterMut<'_, P>
+where
+    P: Pixel<Alpha = Premultiplied, Gamma = Linear>,
+{
+    /// Composite a source color to a region of the view.
+    ///
+    /// See [Raster::composite_color](struct.Raster.html#method.composite_color).
+    pub fn composite_color<R, O>(&mut self, reg: R, clr: P, op: O)
+    where
+        R: Into<Region>,
+        O: Blend,
+    {
+        for drow in self.rows_mut(reg) {
+            P::composite_color(drow, &clr, op);
+        }
+    }
+
+    /// Composite from a matte `Raster`, `RasterRef` or `RasterMut` and color.
+    ///
+    /// See [Raster::composite_matte](struct.Raster.html#method.composite_matte).
+    pub fn composite_matte<'b, R0, R1, S, M, O>(
+        &mut self,
+        to: R0,
+        src: S,
+        from: R1,
+        clr: P,
+        op: O,
+    ) where
+        R0: Into<Region>,
+        R1: Into<Region>,
+        S: Into<RasterRef<'b, M>>,
+        M: Pixel<Chan = P::Chan, Model = Matte, Gamma = P::Gamma> + 'b,
+        O: Blend,
+    {
+        let src = src.into();
+        let (to, from) =
+            clip_regions(self.region(), to.into(), src.region(), from.into());
+        let srows = src.rows(from);
+        let drows = self.rows_mut(to);
+        for (drow, srow) in drows.zip(srows) {
+            P::composite_matte(drow, srow, &clr, op);
+        }
+    }
+
+    /// Composite from a source `Raster`, `RasterRef` or `RasterMut`.
+    ///
+    /// See [Raster::composite_raster](struct.Raster.html#method.composite_raster).
+    pub fn composite_raster<'b, R0, R1, S, O>(
+        &mut self,
+        to: R0,
+        src: S,
+        from: R1,
+        op: O,
+    ) where
+        R0: Into<Region>,
+        R1: Into<Region>,
+        S: Into<RasterRef<'b, P>>,
+        P: 'b,
+        O: Blend,
+    {
+        let src = src.into();
+        let (to, from) =
+            clip_regions(self.region(), to.into(), src.region(), from.into());
+        let srows = src.rows(from);
+        let drows = self.rows_mut(to);
+        for (drow, srow) in drows.zip(srows) {
+            P::composite_slice(drow, srow, op);
+        }
+    }
+}
+
+#[cfg(test)]
+#[rustfmt::skip]
+mod test {
+    use crate::gray::*;
+    use crate::matte::*;
+    use crate::ops::*;
+    use crate::rgb::*;
+    use crate::*;
+
+    #[test]
+    fn rows_stride() {
+        let buf = [
+            0x10, 0x20, 0x30, 0xFF,
+            0x40, 0x50, 0x60, 0xFF,
+            0x70, 0x80, 0x90,
+        ];
+        let r = RasterRef::<Gray8>::with_u8_buffer(3, 3, 4, &buf[..]);
+        let mut rows = r.rows((1, 0, 2, 3));
+        assert_eq!(rows.next(), Some(&[Gray8::new(0x20), Gray8::new(0x30)][..]));
+        assert_eq!(rows.next(), Some(&[Gray8::new(0x50), Gray8::new(0x60)][..]));
+        assert_eq!(rows.next(), Some(&[Gray8::new(0x80), Gray8::new(0x90)][..]));
+        assert_eq!(rows.next(), None);
+        assert_eq!(r.pixel(0, 2), Gray8::new(0x70));
+    }
+
+    #[test]
+    #[should_panic]
+    fn buffer_too_small() {
+        let buf = [0; 11];
+        let _ = RasterRef::<SRgb8>::with_u8_buffer(2, 2, 8, &buf[..]);
+    }
+
+    #[test]
+    fn try_with_errors() {
+        let buf = [0; 12];
+        let r = RasterRef::<SRgb8>::try_with_u8_buffer(2, 2, 5, &buf[..]);
+        assert_eq!(r.err(), Some(Error::StrideTooSmall));
+        let r = RasterRef::<SRgb8>::try_with_u8_buffer(2, 2, 8, &buf[..11]);
+        assert_eq!(r.err(), Some(Error::LengthMismatch));
+        let mut buf = [0; 8];
+        let r = RasterMut::<Rgb16>::try_with_u16_buffer(1, 2, 7, &mut buf);
+        assert_eq!(r.err(), Some(Error::Misaligned));
+    }
+
+    #[test]
+    fn copy_raster_padded() {
+        let mut buf = [0xEE; 10];
+        let src = Raster::with_color(2, 2, Gray8::new(0x40));
+        let mut r = RasterMut::<Gray8>::with_u8_buffer(2, 3, 4, &mut buf[..]);
+        r.copy_raster((0, 1), &src, ());
+        *r.pixel_mut(1, 0) = Gray8::new(0x80);
+        assert_eq!(buf, [
+            0xEE, 0x80, 0xEE, 0xEE,
+            0x40, 0x40, 0xEE, 0xEE,
+            0x40, 0x40,
+        ]);
+    }
+
+    #[test]
+    fn composite_u16_buffer() {
+        let mut buf = [0; 10];
+        let mut r = RasterMut::<Graya16p>::with_u16_buffer(2, 2, 12, &mut buf);
+        r.composite_color((), Graya16p::new(0x4000, 0x8000), SrcOver);
+        let v = RasterRef::from(&r);
+        assert_eq!(v.pixel(1, 1), Graya16p::new(0x4000, 0x8000));
+        assert_eq!(buf, [
+            0x4000, 0x8000, 0x4000, 0x8000, 0, 0,
+            0x4000, 0x8000, 0x4000, 0x8000,
+        ]);
+    }
+
+    #[test]
+    fn view_region() {
+        let mut r = Raster::<Gray8>::with_clear(4, 4);
+        r.copy_color((1, 1, 2, 2), Gray8::new(0x80));
+        let v = r.view((1, 1, 10, 10));
+        assert_eq!(v.width(), 3);
+        assert_eq!(v.height(), 3);
+        assert_eq!(v.pixel(0, 0), Gray8::new(0x80));
+        assert_eq!(v.pixel(2, 2), Gray8::new(0));
+        let v = v.view((1, 1, 1, 1));
+        assert_eq!(v.pixel(0, 0), Gray8::new(0x80));
+        let v = r.view((5, 5, 2, 2));
+        assert_eq!(v.region(), Region::default());
+        assert_eq!(v.rows(()).next(), None);
+    }
+
+    #[test]
+    fn composite_tiles() {
+        let mut r = Raster::<Graya8p>::with_clear(4, 2);
+        let src = Raster::with_color(2, 2, Graya8p::new(0x40, 0x80));
+        for x in [0, 2] {
+            let mut tile = r.view_mut((x, 0, 2, 2));
+            tile.copy_color((0, 0, 1, 2), Graya8p::new(0xFF, 0xFF));
+            tile.composite_raster((), src.view((1, 0, 1, 2)), (), SrcOver);
+        }
+        let mut dst = Raster::<Graya8p>::with_clear(2, 2);
+        dst.composite_raster((), r.view((2, 0, 2, 2)), (), Src);
+        let v = [
+            Graya8p::new(0xBF, 0xFF), Graya8p::new(0x00, 0x00),
+            Graya8p::new(0xBF, 0xFF), Graya8p::new(0x00, 0x00),
+        ];
+        assert_eq!(dst.pixels(), &v);
+    }
+
+    #[test]
+    fn composite_view_to_raster() {
+        let buf = [0x00, 0x80, 0xFF, 0x00, 0x40, 0x20];
+        let m = RasterRef::<Matte8>::with_u8_buffer(2, 2, 3, &buf[..]);
+        let mut r = Raster::<Rgba8p>::with_clear(2, 2);
+        let c = Rgba8p::new(0xFF, 0x80, 0x40, 0xFF);
+        r.composite_matte((), m, (), c, SrcOver);
+        let v = [
+            Rgba8p::new(0, 0, 0, 0), Rgba8p::new(0x80, 0x40, 0x20, 0x80),
+            Rgba8p::new(0, 0, 0, 0), Rgba8p::new(0x40, 0x20, 0x10, 0x40),
+        ];
+        assert_eq!(r.pixels(), &v);
+    }
+}
@@ -0,0 +1,508 @@
+// warp.rs      Affine and perspective warping.
+//
+// Copyright (c) 2023  Douglas P Lau
+//
+use crate::chan::{Linear, Premultiplied};
+use crate::el::Pixel;
+use crate::ops::Blend;
+use crate::raster::{Raster, Region};
+use crate::resize::{from_work, to_work, work_len};
+use crate::view::{RasterMut, RasterRef};
+
+/// Projective transform, stored as a 3x3 matrix.
+///
+/// The matrix is row-major, and maps a point `(x, y, 1)` as a column vector.
+/// Affine transforms have a bottom row of `[0, 0, 1]`.
+///
+/// ### Rotate about a point
+/// ```
+/// use pix::Transform;
+///
+/// let t = Transform::translate(-2.0, -2.0)
+///     .then(Transform::rotate(std::f32::consts::FRAC_PI_2))
+///     .then(Transform::translate(2.0, 2.0));
+/// let (x, y) = t.apply(4.0, 2.0).unwrap();
+/// assert!((x - 2.0).abs() < 1e-5 && (y - 4.0).abs() < 1e-5);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    m: [f32; 9],
+}
+
+/// Sampling method for warping.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Sampling {
+    /// Nearest neighbor
+    Nearest,
+    /// Bilinear interpolation of 2x2 pixels
+    #[default]
+    Bilinear,
+    /// Bicubic (Catmull-Rom) interpolation of 4x4 pixels
+    Bicubic,
+}
+
+/// Edge mode, for sampling outside of a source raster.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Edge {
+    /// Pixels outside are clear (transparent)
+    #[default]
+    Clear,
+    /// Edge pixels are extended
+    Clamp,
+    /// Source is tiled
+    Repeat,
+    /// Source is tiled, with every other tile mirrored
+    Mirror,
+}
+
+/// Options for [composite_warp].
+///
+/// [composite_warp]: struct.Raster.html#method.composite_warp
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WarpOptions {
+    /// Sampling method
+    pub sampling: Sampling,
+    /// Edge mode
+    pub edge: Edge,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::perspective([1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0])
+    }
+}
+
+impl Transform {
+    /// Create an affine transform from a 2x3 matrix.
+    ///
+    /// * `m` Matrix values `[a, b, c, d, e, f]`, mapping `(x, y)` to
+    ///   `(ax + by + c, dx + ey + f)`.
+    pub fn affine(m: [f32; 6]) -> Self {
+        Self::perspective([m[0], m[1], m[2], m[3], m[4], m[5], 0.0, 0.0, 1.0])
+    }
+
+    /// Create a perspective transform (homography) from a 3x3 matrix.
+    pub fn perspective(m: [f32; 9]) -> Self {
+        Transform { m }
+    }
+
+    /// Create a translation transform.
+    pub fn translate(tx: f32, ty: f32) -> Self {
+        Self::affine([1.0, 0.0, tx, 0.0, 1.0, ty])
+    }
+
+    /// Create a scale transform.
+    pub fn scale(sx: f32, sy: f32) -> Self {
+        Self::affine([sx, 0.0, 0.0, 0.0, sy, 0.0])
+    }
+
+    /// Create a rotation transform (clockwise, since *Y* is down).
+    ///
+    /// * `angle` Angle in radians.
+    pub fn rotate(angle: f32) -> Self {
+        let (s, c) = angle.sin_cos();
+        Self::affine([c, -s, 0.0, s, c, 0.0])
+    }
+
+    /// Get the matrix values (row-major).
+    pub fn matrix(self) -> [f32; 9] {
+        self.m
+    }
+
+    /// Combine with a transform applied after this one.
+    pub fn then(self, next: Self) -> Self {
+        let (a, b) = (next.m, self.m);
+        let mut m = [0.0; 9];
+        for r in 0..3 {
+            for c in 0..3 {
+                m[r * 3 + c] =
+                    (0..3).map(|k| a[r * 3 + k] * b[k * 3 + c]).sum();
+            }
+        }
+        Transform { m }
+    }
+
+    /// Get the inverse transform, if it exists.
+    pub fn inverse(self) -> Option<Self> {
+        let [a, b, c, d, e, f, g, h, i] = self.m;
+        let (ca, cb, cc) = (e * i - f * h, f * g - d * i, d * h - e * g);
+        let det = a * ca + b * cb + c * cc;
+        if det.abs() < f32::EPSILON * f32::EPSILON {
+            return None;
+        }
+        let m = [
+            ca,
+            c * h - b * i,
+            b * f - c * e,
+            cb,
+            a * i - c * g,
+            c * d - a * f,
+            cc,
+            b * g - a * h,
+            a * e - b * d,
+        ];
+        Some(Transform {
+            m: m.map(|v| v / det),
+        })
+    }
+
+    /// Apply transform to a point.
+    ///
+    /// Returns `None` if the point maps to infinity or behind the viewer.
+    pub fn apply(self, x: f32, y: f32) -> Option<(f32, f32)> {
+        let m = self.m;
+        let w = m[6] * x + m[7] * y + m[8];
+        if w <= f32::EPSILON {
+            return None;
+        }
+        let tx = m[0] * x + m[1] * y + m[2];
+        let ty = m[3] * x + m[4] * y + m[5];
+        Some((tx / w, ty / w))
+    }
+}
+
+impl Edge {
+    /// Get a sample coordinate within a length
+    pub(crate) fn coord(self, i: i32, len: i32) -> Option<i32> {
+        match self {
+            Edge::Clear => (0..len).contains(&i).then_some(i),
+            Edge::Clamp => Some(i.clamp(0, len - 1)),
+            Edge::Repeat => Some(i.rem_euclid(len)),
+            Edge::Mirror => {
+                let m = i.rem_euclid(len * 2);
+                Some(if m < len { m } else { len * 2 - 1 - m })
+            }
+        }
+    }
+}
+
+/// Catmull-Rom weights for 4 taps at fractional offset `t`
+fn cubic_weights(t: f32) -> [f32; 4] {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    [
+        0.5 * (-t3 + 2.0 * t2 - t),
+        0.5 * (3.0 * t3 - 5.0 * t2 + 2.0),
+        0.5 * (-3.0 * t3 + 4.0 * t2 + t),
+        0.5 * (t3 - t2),
+    ]
+}
+
+/// Source sampler
+struct Sampler<'a, P: Pixel> {
+    src: RasterRef<'a, P>,
+    opts: WarpOptions,
+    n: usize,
+}
+
+impl<P: Pixel> Sampler<'_, P> {
+    /// Add weighted working values of one source pixel.
+    ///
+    /// Returns `true` if the pixel is within the source, with a non-zero
+    /// weight.
+    fn add(
+        &self,
+        x: i32,
+        y: i32,
+        w: f32,
+        acc: &mut [f32],
+        tmp: &mut [f32],
+    ) -> bool {
+        if w == 0.0 {
+            return false;
+        }
+        let edge = self.opts.edge;
+        let (sw, sh) = (self.src.width() as i32, self.src.height() as i32);
+        if let (Some(x), Some(y)) = (edge.coord(x, sw), edge.coord(y, sh)) {
+            to_work(self.src.pixel(x, y), tmp);
+            for (a, v) in acc.iter_mut().zip(tmp.iter()) {
+                *a += v * w;
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Sample source at a point.
+    ///
+    /// Returns `None` if no source pixels cover the point.
+    fn sample(
+        &self,
+        u: f32,
+        v: f32,
+        acc: &mut [f32],
+        tmp: &mut [f32],
+    ) -> Option<P> {
+        acc.fill(0.0);
+        let mut covered = false;
+        match self.opts.sampling {
+            Sampling::Nearest => {
+                let (x, y) = (u.floor() as i32, v.floor() as i32);
+                covered |= self.add(x, y, 1.0, acc, tmp);
+            }
+            Sampling::Bilinear => {
+                let (u, v) = (u - 0.5, v - 0.5);
+                let (x, y) = (u.floor(), v.floor());
+                let (tx, ty) = (u - x, v - y);
+                let (x, y) = (x as i32, y as i32);
+                covered |= self.add(x, y, (1.0 - tx) * (1.0 - ty), acc, tmp);
+                covered |= self.add(x + 1, y, tx * (1.0 - ty), acc, tmp);
+                covered |= self.add(x, y + 1, (1.0 - tx) * ty, acc, tmp);
+                covered |= self.add(x + 1, y + 1, tx * ty, acc, tmp);
+            }
+            Sampling::Bicubic => {
+                let (u, v) = (u - 0.5, v - 0.5);
+                let (x, y) = (u.floor(), v.floor());
+                let wx = cubic_weights(u - x);
+                let wy = cubic_weights(v - y);
+                let (x, y) = (x as i32 - 1, y as i32 - 1);
+                for (j, wy) in wy.iter().enumerate() {
+                    for (i, wx) in wx.iter().enumerate() {
+                        let (xi, yj) = (x + i as i32, y + j as i32);
+                        covered |= self.add(xi, yj, wx * wy, acc, tmp);
+                    }
+                }
+            }
+        }
+        debug_assert_eq!(acc.len(), self.n);
+        covered.then(|| from_work(acc))
+    }
+}
+
+/// Get bounding region of a transformed source, expanded for sampling
+fn bounds(xform: Transform, width: u32, height: u32) -> Option<Region> {
+    let (w, h) = (width as f32, height as f32);
+    let corners = [(0.0, 0.0), (w, 0.0), (0.0, h), (w, h)];
+    let mut x0 = f32::MAX;
+    let mut y0 = f32::MAX;
+    let mut x1 = f32::MIN;
+    let mut y1 = f32::MIN;
+    for (x, y) in corners {
+        let (x, y) = xform.apply(x, y)?;
+        x0 = x0.min(x);
+        y0 = y0.min(y);
+        x1 = x1.max(x);
+        y1 = y1.max(y);
+    }
+    let limit = i32::MAX as f32 / 2.0;
+    let x0 = (x0.floor() - 2.0).clamp(-limit, limit) as i32;
+    let y0 = (y0.floor() - 2.0).clamp(-limit, limit) as i32;
+    let x1 = (x1.ceil() + 2.0).clamp(-limit, limit) as i32;
+    let y1 = (y1.ceil() + 2.0).clamp(-limit, limit) as i32;
+    Some(Region::new(x0, y0, (x1 - x0) as u32, (y1 - y0) as u32))
+}
+
+impl<P> RasterMut<'_, P>
+where
+    P: Pixel<Alpha = Premultiplied, Gamma = Linear>,
+{
+    /// Composite a transformed source raster.
+    ///
+    /// See [Raster::composite_warp](struct.Raster.html#method.composite_warp).
+    pub fn composite_warp<'b, R, S, O>(
+        &mut self,
+        reg: R,
+        src: S,
+        xform: Transform,
+        opts: WarpOptions,
+        op: O,
+    ) where
+        R: Into<Region>,
+        S: Into<RasterRef<'b, P>>,
+        P: 'b,
+        O: Blend,
+    {
+        let src = src.into();
+        if src.width() == 0 || src.height() == 0 {
+            return;
+        }
+        let Some(inverse) = xform.inverse() else {
+            return;
+        };
+        let mut reg = self.intersection(reg);
+        if opts.edge == Edge::Clear {
+            if let Some(b) = bounds(xform, src.width(), src.height()) {
+                reg = reg.intersection(b);
+            }
+        }
+        let n = work_len::<P>();
+        let sampler = Sampler { src, opts, n };
+        let mut acc = vec![0.0; n];
+        let mut tmp = vec![0.0; n];
+        for (y, drow) in (reg.top()..).zip(self.rows_mut(reg)) {
+            for (x, p) in (reg.left()..).zip(drow.iter_mut()) {
+                let (dx, dy) = (x as f32 + 0.5, y as f32 + 0.5);
+                if let Some(s) = inverse
+                    .apply(dx, dy)
+                    .and_then(|(u, v)| sampler.sample(u, v, &mut acc, &mut tmp))
+                {
+                    p.composite_channels(&s, op);
+                }
+            }
+        }
+    }
+}
+
+impl<P> Raster<P>
+where
+    P: Pixel<Alpha = Premultiplied, Gamma = Linear>,
+{
+    /// Composite a source raster, transformed by an affine or perspective
+    /// [Transform].
+    ///
+    /// * `reg` Region within `self` to draw.  It can be a `Region` struct,
+    ///   tuple of (*x*, *y*, *width*, *height*) or the unit type `()`.
+    /// * `src` Source `Raster`, `RasterRef` or `RasterMut`.
+    /// * `xform` Transform from source to destination coordinates.
+    /// * `opts` Sampling and edge options.
+    /// * `op` Compositing operation.
+    ///
+    /// With [Edge::Clear], only pixels covered by the transformed source are
+    /// composited; others are unchanged.  Other edge modes cover all of
+    /// `reg`.
+    ///
+    /// ### Draw a rotated sprite
+    /// ```
+    /// use pix::ops::SrcOver;
+    /// use pix::rgb::Rgba8p;
+    /// use pix::{Raster, Transform, WarpOptions};
+    ///
+    /// let mut r0 = Raster::with_clear(20, 20);
+    /// let r1 = Raster::with_color(8, 4, Rgba8p::new(0xFF, 0, 0, 0xFF));
+    /// let xform = Transform::rotate(std::f32::consts::FRAC_PI_2)
+    ///     .then(Transform::translate(10.0, 2.0));
+    /// r0.composite_warp((), &r1, xform, WarpOptions::default(), SrcOver);
+    /// assert_eq!(r0.pixel(8, 5), Rgba8p::new(0xFF, 0, 0, 0xFF));
+    /// assert_eq!(r0.pixel(11, 5), Rgba8p::new(0, 0, 0, 0));
+    /// ```
+    ///
+    /// [Edge::Clear]: enum.Edge.html#variant.Clear
+    /// [Transform]: struct.Transform.html
+    pub fn composite_warp<'b, R, S, O>(
+        &mut self,
+        reg: R,
+        src: S,
+        xform: Transform,
+        opts: WarpOptions,
+        op: O,
+    ) where
+        R: Into<Region>,
+        S: Into<RasterRef<'b, P>>,
+        P: 'b,
+        O: Blend,
+    {
+        RasterMut::from(self).composite_warp(reg, src, xform, opts, op);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chan::Channel;
+    use crate::gray::*;
+    use crate::ops::*;
+    use crate::rgb::*;
+
+    #[test]
+    fn transform() {
+        let t = Transform::scale(2.0, 3.0).then(Transform::translate(1.0, 1.0));
+        assert_eq!(t.apply(1.0, 1.0), Some((3.0, 4.0)));
+        let i = t.inverse().unwrap();
+        assert_eq!(i.apply(3.0, 4.0), Some((1.0, 1.0)));
+        assert_eq!(Transform::scale(0.0, 1.0).inverse(), None);
+        let p = Transform::perspective([
+            1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.5, 0.0, 1.0,
+        ]);
+        assert_eq!(p.apply(2.0, 4.0), Some((1.0, 2.0)));
+        assert_eq!(p.apply(-2.0, 0.0), None);
+    }
+
+    #[test]
+    fn edges() {
+        let c: Vec<_> =
+            (-4..6).map(|i| Edge::Mirror.coord(i, 3).unwrap()).collect();
+        assert_eq!(c, [2, 2, 1, 0, 0, 1, 2, 2, 1, 0]);
+        let c: Vec<_> =
+            (-4..6).map(|i| Edge::Repeat.coord(i, 3).unwrap()).collect();
+        assert_eq!(c, [2, 0, 1, 2, 0, 1, 2, 0, 1, 2]);
+        assert_eq!(Edge::Clamp.coord(-4, 3), Some(0));
+        assert_eq!(Edge::Clear.coord(3, 3), None);
+    }
+
+    #[test]
+    fn warp_nearest() {
+        let mut src = Raster::<Graya8p>::with_clear(2, 2);
+        *src.pixel_mut(0, 0) = Graya8p::new(0x10, 0xFF);
+        *src.pixel_mut(1, 0) = Graya8p::new(0x20, 0xFF);
+        *src.pixel_mut(0, 1) = Graya8p::new(0x30, 0xFF);
+        *src.pixel_mut(1, 1) = Graya8p::new(0x40, 0xFF);
+        let opts = WarpOptions {
+            sampling: Sampling::Nearest,
+            edge: Edge::Repeat,
+        };
+        let mut dst = Raster::<Graya8p>::with_clear(4, 3);
+        let xform =
+            Transform::scale(2.0, 2.0).then(Transform::translate(1.0, 0.0));
+        dst.composite_warp((), &src, xform, opts, Src);
+        let g: Vec<u8> =
+            dst.pixels().iter().map(|p| u8::from(p.one())).collect();
+        #[rustfmt::skip]
+        let v = [
+            0x20, 0x10, 0x10, 0x20,
+            0x20, 0x10, 0x10, 0x20,
+            0x40, 0x30, 0x30, 0x40,
+        ];
+        assert_eq!(g, v);
+    }
+
+    #[test]
+    fn warp_bilinear() {
+        let mut src = Raster::with_color(2, 1, Graya32p::new(0.0, 1.0));
+        *src.pixel_mut(1, 0) = Graya32p::new(1.0, 1.0);
+        let mut dst = Raster::<Graya32p>::with_clear(4, 1);
+        let opts = WarpOptions {
+            edge: Edge::Clamp,
+            ..Default::default()
+        };
+        dst.composite_warp((), &src, Transform::scale(2.0, 1.0), opts, Src);
+        let g: Vec<f32> =
+            dst.pixels().iter().map(|p| p.one().to_f32()).collect();
+        assert_eq!(g, [0.0, 0.25, 0.75, 1.0]);
+        let opts = WarpOptions {
+            sampling: Sampling::Bicubic,
+            edge: Edge::Clamp,
+        };
+        let src = Raster::with_color(3, 3, Graya32p::new(0.5, 1.0));
+        dst.composite_warp((), &src, Transform::rotate(0.3), opts, Src);
+        assert!(dst
+            .pixels()
+            .iter()
+            .all(|p| (p.one().to_f32() - 0.5).abs() < 1e-5));
+    }
+
+    #[test]
+    fn warp_clear_bounds() {
+        let src = Raster::with_color(2, 2, Rgba8p::new(0, 0xFF, 0, 0xFF));
+        let mut dst = Raster::with_color(10, 10, Rgba8p::new(0, 0, 0xFF, 0xFF));
+        let opts = WarpOptions {
+            sampling: Sampling::Nearest,
+            edge: Edge::Clear,
+        };
+        dst.composite_warp((), &src, Transform::translate(4.0, 4.0), opts, Src);
+        assert_eq!(dst.pixel(0, 0), Rgba8p::new(0, 0, 0xFF, 0xFF));
+        assert_eq!(dst.pixel(3, 3), Rgba8p::new(0, 0, 0xFF, 0xFF));
+        assert_eq!(dst.pixel(4, 5), Rgba8p::new(0, 0xFF, 0, 0xFF));
+        let mut dst = Raster::with_color(10, 10, Rgba8p::new(0, 0, 0xFF, 0xFF));
+        let opts = WarpOptions {
+            sampling: Sampling::Bilinear,
+            edge: Edge::Clear,
+        };
+        let xform = Transform::translate(4.5, 4.0);
+        dst.composite_warp((), &src, xform, opts, Src);
+        assert_eq!(dst.pixel(3, 4), Rgba8p::new(0, 0, 0xFF, 0xFF));
+        assert_eq!(dst.pixel(4, 4), Rgba8p::new(0, 0x80, 0, 0x80));
+        assert_eq!(dst.pixel(5, 5), Rgba8p::new(0, 0xFF, 0, 0xFF));
+        assert_eq!(dst.pixel(4, 6), Rgba8p::new(0, 0, 0xFF, 0xFF));
+    }
+}
@@ -11,6 +11,11 @@ use crate::el::{Pix3, Pix4, PixRgba, Pixel};
 use crate::ColorModel;
 use std::ops::Range;
 
+pub use crate::frame::{
+    ChromaFilter, ChromaOptions, ChromaSiting, FrameLayout, Subsampling,
+    YCbCrFrame,
+};
+
 /// [YCbCr] [color model] (used in JPEG and other formats).
 ///
 /// The components are *[y]*, *[cb]*, *[cr]* and optional *[alpha]*.